@@ -0,0 +1,302 @@
+use chess::consts::PieceColor;
+
+use crate::clock::TimeControl;
+use crate::comm::EngineOptions;
+use crate::game_manager::{Adjudication, Sprt};
+
+pub struct Args {
+    pub engine_a: String,
+    pub engine_b: Option<String>,
+    // Options of each engine, given after its path, e.g. "engines/sf:Hash=128,Threads=2". An
+    // engine playing against itself uses the same options for both sides.
+    pub engine_options: [EngineOptions; 2],
+    // Play the match without opening a window
+    pub headless: bool,
+    // Maximum number of games in the match
+    pub games: Option<u32>,
+    // Maximum number of rounds, i.e. positions played twice with the engines swapping colors
+    pub rounds: Option<u32>,
+    // Points one engine has to be ahead by to win the match early
+    pub margin: Option<f32>,
+    // Stop the match once the SPRT accepts one of its hypotheses
+    pub sprt: Option<Sprt>,
+    // When games are called from the engines' scores instead of being played out
+    pub adjudication: Option<Adjudication>,
+    // Let the engines think on their opponent's time
+    pub ponder: bool,
+    // Seed for the random choices, e.g. of positions, to repeat a previous run
+    pub seed: Option<u64>,
+    pub time_control: Option<TimeControl>,
+    // Search every move to this depth instead of for a fixed time
+    pub depth: Option<u32>,
+    // Continue the match saved on the last exit
+    pub resume: bool,
+    // Color a person plays against the first engine, instead of the second engine
+    pub human: Option<PieceColor>,
+    // Directory of the piece sets, fonts and other assets
+    pub assets: Option<String>,
+    // File with the positions the games are played from
+    pub fens: Option<String>,
+    // PGN of openings to play the games from, instead of the positions of the fens file
+    pub book: Option<String>,
+}
+
+pub fn usage(program: &str) -> String {
+    format!(
+        "Usage: '{}' [options] <engine-1>[:<name>=<value>,...] [engine-2[:<name>=<value>,...]]\n       \
+                '{}' bench\n       \
+                '{}' divide <depth> [<fen>]\n\
+         Options:\n    \
+             --headless    play the match without opening a window\n    \
+             --games <N>   stop the match after N games\n    \
+             --rounds <N>  stop the match after N rounds of two games from the same position\n    \
+             --margin <P>  stop the match once an engine is ahead by P points\n    \
+             --sprt <E0,E1[,A,B]>\n                  \
+                           stop the match once an SPRT of elo0 E0 against elo1 E1 (with the\n                  \
+                           error rates A and B, 0.05 by default) accepts either of them\n    \
+             --adjudicate <P>[:<name>=<value>,...]\n                  \
+                           call games from the engines' scores: 'off' (default), 'draw', 'win'\n                  \
+                           or 'both'. The thresholds are 'draw_score' (10 cp), 'draw_plies' (8)\n                  \
+                           and 'draw_after' (move 40), and 'win_score' (1000 cp), 'win_plies' (6)\n    \
+             --ponder      let the engines think on their opponent's time\n    \
+             --seed <S>    seed for picking random positions\n    \
+             --tc <M+S>    time control, i.e. minutes per game plus seconds per move (default: 1+0)\n    \
+             --depth <D>   search every move to depth D instead of for a fixed time\n    \
+             --resume      continue the match saved on the last exit\n    \
+             --human <C>   play 'white' or 'black' yourself against the first engine\n    \
+             --assets <D>  directory of the assets (default: 'assets' next to the program)\n    \
+             --fens <F>    file of positions to play from (default: 'fens.txt' next to the program)\n    \
+             --book <F>    PGN of openings to play from, instead of the positions of the fens file\n\
+         'bench' measures the speed of the move generator, and 'divide' prints the perft count\n\
+         after each legal move of a position (the starting position by default)",
+        program, program, program
+    )
+}
+
+// Parses the command line arguments (without the program name)
+pub fn parse(args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut engines = vec![];
+    let mut headless = false;
+    let mut games = None;
+    let mut rounds = None;
+    let mut margin = None;
+    let mut sprt = None;
+    let mut adjudication = None;
+    let mut ponder = false;
+    let mut seed = None;
+    let mut time_control = None;
+    let mut depth = None;
+    let mut resume = false;
+    let mut human = None;
+    let mut assets = None;
+    let mut fens = None;
+    let mut book = None;
+
+    let mut args = args;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--headless" => headless = true,
+            "--resume" => resume = true,
+            "--ponder" => ponder = true,
+            "--games" => {
+                let value = args.next().ok_or("'--games' expects a number of games")?;
+                match value.parse::<u32>() {
+                    Ok(n) if n > 0 => games = Some(n),
+                    _ => return Err(format!("Invalid number of games: '{}'", value)),
+                }
+            },
+            "--rounds" => {
+                let value = args.next().ok_or("'--rounds' expects a number of rounds")?;
+                match value.parse::<u32>() {
+                    Ok(n) if n > 0 => rounds = Some(n),
+                    _ => return Err(format!("Invalid number of rounds: '{}'", value)),
+                }
+            },
+            "--margin" => {
+                let value = args.next().ok_or("'--margin' expects a number of points")?;
+                match value.parse::<f32>() {
+                    Ok(p) if p > 0.0 && p.is_finite() => margin = Some(p),
+                    _ => return Err(format!("Invalid winning margin: '{}'", value)),
+                }
+            },
+            "--sprt" => {
+                let value = args.next().ok_or("'--sprt' expects the bounds of the test, e.g. '0,5'")?;
+                sprt = Some(Sprt::parse(&value)?);
+            },
+            "--adjudicate" => {
+                let value = args.next().ok_or("'--adjudicate' expects 'off', 'draw', 'win' or 'both'")?;
+                adjudication = Some(Adjudication::parse(&value)?);
+            },
+            "--seed" => {
+                let value = args.next().ok_or("'--seed' expects a number")?;
+                seed = Some(value.parse::<u64>().map_err(|_| format!("Invalid seed: '{}'", value))?);
+            },
+            "--tc" => {
+                let value = args.next().ok_or("'--tc' expects a time control, e.g. '3+2'")?;
+                time_control = Some(TimeControl::parse(&value)?);
+            },
+            "--depth" => {
+                let value = args.next().ok_or("'--depth' expects a search depth")?;
+                match value.parse::<u32>() {
+                    Ok(d) if d > 0 => depth = Some(d),
+                    _ => return Err(format!("Invalid search depth: '{}'", value)),
+                }
+            },
+            "--human" => {
+                let value = args.next().ok_or("'--human' expects a color, 'white' or 'black'")?;
+                human = match value.as_str() {
+                    "white" => Some(PieceColor::Light),
+                    "black" => Some(PieceColor::Dark),
+                    _ => return Err(format!("Invalid color: '{}', expected 'white' or 'black'", value)),
+                };
+            },
+            "--assets" => assets = Some(args.next().ok_or("'--assets' expects a directory")?),
+            "--fens" => fens = Some(args.next().ok_or("'--fens' expects a file")?),
+            "--book" => book = Some(args.next().ok_or("'--book' expects a PGN file")?),
+            _ if arg.starts_with("--") => return Err(format!("Unknown option '{}'", arg)),
+            _ => engines.push(arg),
+        }
+    }
+
+    // the first engine is a requirement, the second one is optional
+    if engines.is_empty() {
+        return Err("No engine was provided".to_string());
+    }
+    if engines.len() > 2 {
+        return Err(format!("Expected at most 2 engines, but got {}", engines.len()));
+    }
+    // Moves are entered on the board, so there has to be one
+    if headless && human.is_some() {
+        return Err("'--human' can't be used with '--headless'".to_string());
+    }
+    let engine_b = engines.get(1).map(|arg| parse_engine(arg)).transpose()?;
+    let (engine_a, options_a) = parse_engine(&engines[0])?;
+    let (engine_b, options_b) = match engine_b {
+        Some((path, options)) => (Some(path), options),
+        None => (None, options_a.clone()),
+    };
+    Ok(Args {
+        engine_a, engine_b, engine_options: [options_a, options_b],
+        headless, games, rounds, margin, sprt, adjudication, ponder, seed, time_control, depth, resume, human, assets, fens, book
+    })
+}
+
+// Splits an engine argument into the engine's path and its options. The options start at the
+// first ':' that is followed by an option name, so that paths with a ':' in them (e.g.
+// "C:\engines\sf.exe:Hash=64") are left intact.
+fn parse_engine(arg: &str) -> Result<(String, EngineOptions), String> {
+    let start = arg.match_indices(':').map(|(i, _)| i).find(|&i| {
+        arg[i + 1..].split_once('=')
+            .map_or(false, |(name, _)| !name.trim().is_empty() && !name.contains(['/', '\\', ':']))
+    });
+    let start = if let Some(start) = start { start } else { return Ok((arg.to_string(), vec![])); };
+    let mut options = vec![];
+    for option in arg[start + 1..].split(',') {
+        match option.split_once('=') {
+            Some((name, value)) if !name.trim().is_empty() => {
+                options.push((name.trim().to_string(), value.trim().to_string()));
+            },
+            _ => return Err(format!("Invalid engine option '{}', expected '<name>=<value>'", option)),
+        }
+    }
+    Ok((arg[..start].to_string(), options))
+}
+
+#[cfg(test)]
+mod tests {
+    use chess::consts::PieceColor;
+
+    use crate::args;
+    use crate::game_manager::{Adjudication, AdjudicationPolicy, Sprt};
+
+    fn parse(line: &str) -> Result<args::Args, String> {
+        args::parse(line.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn parse_engines_and_options() {
+        let a = parse("engines/a").unwrap();
+        assert_eq!(a.engine_a, "engines/a");
+        assert_eq!(a.engine_b, None);
+        assert!(!a.headless);
+        assert_eq!(a.games, None);
+        assert_eq!(a.rounds, None);
+        assert_eq!(a.margin, None);
+        assert_eq!(a.sprt, None);
+        assert_eq!(a.adjudication, None);
+        assert!(!a.ponder);
+        assert_eq!(a.seed, None);
+        assert_eq!(a.time_control, None);
+        assert_eq!(a.depth, None);
+        assert!(!a.resume);
+        assert_eq!(a.human, None);
+        assert_eq!(a.assets, None);
+        assert_eq!(a.fens, None);
+        assert_eq!(a.book, None);
+        assert_eq!(a.engine_options, [vec![], vec![]]);
+
+        let a = parse("--headless engines/a --games 10 engines/b --rounds 4 --margin 2.5 --seed 42 --tc 3+2 --depth 12 --resume --ponder --assets /usr/share/engine-gui --fens my-fens.txt --book openings.pgn").unwrap();
+        assert_eq!(a.engine_a, "engines/a");
+        assert_eq!(a.engine_b.as_deref(), Some("engines/b"));
+        assert!(a.headless);
+        assert_eq!(a.games, Some(10));
+        assert_eq!(a.rounds, Some(4));
+        assert_eq!(a.margin, Some(2.5));
+        assert_eq!(a.seed, Some(42));
+        assert_eq!(a.time_control.unwrap().base.as_secs(), 180);
+        assert_eq!(a.depth, Some(12));
+        assert!(a.resume);
+        assert!(a.ponder);
+        assert_eq!(a.assets.as_deref(), Some("/usr/share/engine-gui"));
+        assert_eq!(a.fens.as_deref(), Some("my-fens.txt"));
+        assert_eq!(a.book.as_deref(), Some("openings.pgn"));
+
+        let a = parse("engines/a --sprt 0,5").unwrap();
+        assert_eq!(a.sprt, Some(Sprt { elo0: 0.0, elo1: 5.0, alpha: 0.05, beta: 0.05 }));
+        let a = parse("engines/a --sprt -1.5,3,0.1,0.2").unwrap();
+        assert_eq!(a.sprt, Some(Sprt { elo0: -1.5, elo1: 3.0, alpha: 0.1, beta: 0.2 }));
+
+        let a = parse("engines/a --adjudicate both").unwrap();
+        assert_eq!(a.adjudication, Some(Adjudication { policy: AdjudicationPolicy::Both, ..Adjudication::default() }));
+        let a = parse("engines/a --adjudicate draw:draw_score=5,draw_after=30").unwrap().adjudication.unwrap();
+        assert_eq!((a.policy, a.draw_score, a.draw_plies, a.draw_after), (AdjudicationPolicy::DrawOnly, 5, 8, 30));
+
+        let a = parse("engines/a --human black").unwrap();
+        assert_eq!(a.human, Some(PieceColor::Dark));
+        assert_eq!(parse("engines/a --human white").unwrap().human, Some(PieceColor::Light));
+    }
+
+    #[test]
+    fn parse_invalid_arguments() {
+        for line in ["", "--headless", "a b c", "a --games", "a --games 0", "a --games x", "a --rounds", "a --rounds 0", "a --fast",
+                     "a --margin", "a --margin 0", "a --margin -1", "a --margin inf",
+                     "a --seed", "a --seed -1",
+                     "a --sprt", "a --sprt 5", "a --sprt 5,0", "a --sprt 0,5,0.05", "a --sprt 0,5,0,0.05", "a --sprt 0,x",
+                     "a --adjudicate", "a --adjudicate always", "a --adjudicate win:win_score=0", "a --adjudicate win:plies=3",
+                     "a --tc", "a --tc 0+1", "a --depth", "a --depth 0", "a --depth x", "a --assets", "a --fens", "a --book",
+                     "a --human", "a --human red", "a --human white --headless"] {
+            assert!(parse(line).is_err(), "'{}'", line);
+        }
+    }
+
+    #[test]
+    fn parse_engine_options() {
+        let option = |name: &str, value: &str| (name.to_string(), value.to_string());
+        let a = parse("engines/a:Hash=128,Threads=2 engines/b:Hash=256").unwrap();
+        assert_eq!(a.engine_a, "engines/a");
+        assert_eq!(a.engine_b.as_deref(), Some("engines/b"));
+        assert_eq!(a.engine_options, [vec![option("Hash", "128"), option("Threads", "2")], vec![option("Hash", "256")]]);
+
+        // The engine plays itself with the same options
+        let a = parse("engines/a:Hash=64").unwrap();
+        assert_eq!(a.engine_options, [vec![option("Hash", "64")], vec![option("Hash", "64")]]);
+
+        let a = parse("C:\\engines\\a.exe:SyzygyPath=C:\\tb engines/b").unwrap();
+        assert_eq!(a.engine_a, "C:\\engines\\a.exe");
+        assert_eq!(a.engine_options, [vec![option("SyzygyPath", "C:\\tb")], vec![]]);
+
+        for line in ["engines/a:Hash=1,", "engines/a:Hash=1,Threads", "engines/a engines/b:Hash=1,=2"] {
+            assert!(parse(line).is_err(), "'{}'", line);
+        }
+    }
+}
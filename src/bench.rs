@@ -0,0 +1,67 @@
+use chess::attack::AttackInfo;
+use chess::fen::{self, FEN_POSITIONS};
+use chess::move_gen;
+use chess::moves;
+use chess::zobrist::ZobristInfo;
+
+use std::time::{Duration, Instant};
+
+// Positions of 'fen::FEN_POSITIONS' and the depth each one is searched to. The depths are fixed,
+// so that the node counts (and the time taken) can be compared between commits.
+const BENCH_POSITIONS: [(usize, u32); 6] = [(1, 6), (2, 4), (3, 6), (4, 5), (5, 4), (6, 4)];
+
+// Runs perft on a few positions and reports how fast the moves were generated. It's meant to be
+// run with a release build, i.e. 'cargo run --release --bin engine-gui -- bench'.
+pub fn bench_main() {
+    let attack_info = AttackInfo::new();
+    let zobrist_info = ZobristInfo::new();
+
+    let mut total_nodes = 0;
+    let mut total_time = Duration::ZERO;
+    for (ind, depth) in BENCH_POSITIONS {
+        let board = fen::parse(FEN_POSITIONS[ind], &zobrist_info);
+        let start = Instant::now();
+        let nodes = move_gen::perft(&board, depth, &attack_info, &zobrist_info);
+        let time = start.elapsed();
+        println!(
+            "{:<80} depth {}: {:>9} nodes in {:>6.3}s ({:.0} nodes/s)",
+            FEN_POSITIONS[ind], depth, nodes, time.as_secs_f64(), nodes_per_sec(nodes, time)
+        );
+        total_nodes += nodes;
+        total_time += time;
+    }
+    println!(
+        "Total: {} nodes in {:.3}s ({:.0} nodes/s)",
+        total_nodes, total_time.as_secs_f64(), nodes_per_sec(total_nodes, total_time)
+    );
+}
+
+fn nodes_per_sec(nodes: u64, time: Duration) -> f64 {
+    nodes as f64 / time.as_secs_f64().max(f64::EPSILON)
+}
+
+// Prints the perft divide of a position, one "<move>: <nodes>" line per legal move, to compare
+// with the output of another engine, e.g. 'engine-gui divide 3 <fen>'. The moves are sorted so
+// that the two lists line up.
+pub fn divide_main(mut args: impl Iterator<Item = String>) -> Result<(), String> {
+    let depth = args.next().ok_or("Expected a depth")?;
+    let depth = match depth.parse::<u32>() {
+        Ok(depth) if depth > 0 => depth,
+        _ => return Err(format!("Invalid depth: '{}'", depth)),
+    };
+    // The FEN is allowed to be split into several arguments, so that it doesn't need quotes
+    let fen = args.collect::<Vec<_>>().join(" ");
+    let fen = if fen.is_empty() { FEN_POSITIONS[1] } else { &fen };
+    let attack_info = AttackInfo::new();
+    let zobrist_info = ZobristInfo::new();
+    let board = fen::try_parse(fen, &zobrist_info).map_err(|e| format!("Invalid FEN '{}': {}", fen, e))?;
+    let mut counts: Vec<(String, u64)> = move_gen::perft_divide(&board, depth, &attack_info, &zobrist_info)
+        .into_iter().map(|(mv, nodes)| (moves::to_uci(mv), nodes)).collect();
+    counts.sort();
+    for (mv, nodes) in &counts {
+        println!("{}: {}", mv, nodes);
+    }
+    println!();
+    println!("Total: {}", counts.iter().map(|(_, nodes)| nodes).sum::<u64>());
+    Ok(())
+}
@@ -5,6 +5,8 @@ use super::consts::{Direction, PieceColor, PieceType, Sq};
 use super::magic_consts::{BISHOP_MAGICS, ROOK_MAGICS};
 use crate::{COL, ROW, SQ};
 
+use std::sync::OnceLock;
+
 // Total number of square a bishop can go to from a certain square
 #[rustfmt::skip]
 pub const BISHOP_RELEVANT_BITS: [u32; 64] = [
@@ -36,9 +38,11 @@ pub struct AttackInfo {
     pub knight: [BB; 64],
     pub king: [BB; 64],
     pub bishop_occ_mask: [BB; 64],
-    pub bishop_attack: [[BB; 512]; 64],
+    // The attack tables are boxed, since they're too big for the stack (the rook attacks alone
+    // are 2 MB)
+    pub bishop_attack: Box<[[BB; 512]; 64]>,
     pub rook_occ_mask: [BB; 64],
-    pub rook_attack: [[BB; 4096]; 64],
+    pub rook_attack: Box<[[BB; 4096]; 64]>,
 }
 
 impl AttackInfo {
@@ -48,9 +52,9 @@ impl AttackInfo {
             knight: [0; 64],
             king: [0; 64],
             bishop_occ_mask: [0; 64],
-            bishop_attack: [[0; 512]; 64],
+            bishop_attack: zeroed_table(),
             rook_occ_mask: [0; 64],
-            rook_attack: [[0; 4096]; 64],
+            rook_attack: zeroed_table(),
         };
         this.init();
         this
@@ -83,6 +87,18 @@ impl AttackInfo {
     }
 }
 
+// Allocated on the heap right away, as 'Box::new' could build the table on the stack first
+fn zeroed_table<const N: usize>() -> Box<[[BB; N]; 64]> {
+    vec![[0; N]; 64].into_boxed_slice().try_into().unwrap()
+}
+
+// Generating the tables is by far the slowest part of starting up, so code that doesn't need its
+// own copy, like the tests, can share this one
+pub fn shared_attack_info() -> &'static AttackInfo {
+    static SHARED: OnceLock<AttackInfo> = OnceLock::new();
+    SHARED.get_or_init(AttackInfo::new)
+}
+
 fn gen_leapers(attack_info: &mut AttackInfo) {
     for sq in 0..64 {
         gen_pawn(attack_info, sq, PieceColor::Light);
@@ -406,3 +422,24 @@ pub fn set_occ(ind: usize, relevant_bits: u32, mut occ_mask: BB) -> BB {
     }
     occ
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::attack;
+    use crate::consts::Sq;
+
+    // Building the tables takes about 40 ms in a debug build and 10 ms in a release one, which
+    // every test used to pay for, while getting the shared instance again takes well under a
+    // microsecond
+    #[test]
+    fn shared_attack_info_is_built_once() {
+        let shared = attack::shared_attack_info();
+        assert!(std::ptr::eq(shared, attack::shared_attack_info()));
+
+        // The tables match the attacks computed without them, e.g. for a rook and a bishop on d4
+        // with a few blockers around
+        let blockers = 0x0022_0008_4400_2200;
+        assert_eq!(shared.get_rook_attack(Sq::D4, blockers), attack::gen_rook_attack(Sq::D4 as usize, blockers));
+        assert_eq!(shared.get_bishop_attack(Sq::D4, blockers), attack::gen_bishop_attack(Sq::D4 as usize, blockers));
+    }
+}
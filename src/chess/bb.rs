@@ -8,9 +8,25 @@ pub trait BBUtil {
     fn pop(&mut self, ind: usize);
     fn lsb(&self) -> usize;
     fn pop_lsb(&mut self) -> usize;
+    fn squares(&self) -> Squares;
     fn print(&self);
 }
 
+// Iterates over the set squares of a copy of a bitboard, from the lowest index to the highest
+pub struct Squares(BB);
+
+impl Iterator for Squares {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.0 == 0 {
+            None
+        } else {
+            Some(self.0.pop_lsb())
+        }
+    }
+}
+
 impl BBUtil for BB {
     fn set(&mut self, ind: usize) {
         *self |= 1 << ind;
@@ -39,6 +55,10 @@ impl BBUtil for BB {
         ind
     }
 
+    fn squares(&self) -> Squares {
+        Squares(*self)
+    }
+
     fn print(&self) {
         for r in 0..8 {
             print!(" {} |", 8 - r);
@@ -51,3 +71,22 @@ impl BBUtil for BB {
         println!("     a b c d e f g h");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::bb::{BB, BBUtil};
+    use crate::consts::Sq;
+
+    #[test]
+    fn squares_yields_set_bits() {
+        let mut bb: BB = 0;
+        for sq in [Sq::A8, Sq::E4, Sq::H1, Sq::D7] {
+            bb.set(sq as usize);
+        }
+        let squares: Vec<usize> = bb.squares().collect();
+        assert_eq!(squares, vec![Sq::A8 as usize, Sq::D7 as usize, Sq::E4 as usize, Sq::H1 as usize]);
+        // The bitboard itself isn't changed
+        assert_eq!(bb.count_ones(), 4);
+        assert_eq!((0 as BB).squares().next(), None);
+    }
+}
@@ -2,7 +2,7 @@ use super::attack::AttackInfo;
 use super::bb::{BBUtil, BB};
 use super::consts::{Piece, PieceColor, Sq};
 use super::fen;
-use super::zobrist::ZobristInfo;
+use super::zobrist::{self, ZobristInfo};
 use crate::SQ;
 
 #[derive(Clone)]
@@ -108,6 +108,11 @@ impl Board {
         fen::parse(fen, zobrist_info)
     }
 
+    // Starts setting up a position on an empty board with white to move and no castling rights
+    pub fn empty(zobrist_info: &ZobristInfo) -> BoardBuilder<'_> {
+        BoardBuilder { board: Board::new(), zobrist_info }
+    }
+
     pub fn find_piece(&self, sq: usize) -> Option<Piece> {
         for i in 0..12 {
             if self.pos.piece[i].get(sq) {
@@ -117,10 +122,34 @@ impl Board {
         None
     }
 
+    // Number of pieces of the given kind on the board
+    pub fn count(&self, piece: Piece) -> u32 {
+        self.pos.piece[piece as usize].count_ones()
+    }
+
+    // Number of pieces, including the king, that 'color' has on the board
+    pub fn count_color(&self, color: PieceColor) -> u32 {
+        self.pos.units[color as usize].count_ones()
+    }
+
     pub fn is_white_to_move(&self) -> bool {
         self.state.side == PieceColor::Light
     }
 
+    pub fn side_to_move(&self) -> PieceColor {
+        self.state.side
+    }
+
+    // Starts at 1 and goes up after each of black's moves
+    pub fn full_moves(&self) -> u32 {
+        self.state.full_moves
+    }
+
+    // Number of plies since the last pawn move or capture
+    pub fn halfmove_clock(&self) -> u32 {
+        self.state.half_moves
+    }
+
     pub fn display(&self) {
         println!("\n    +---+---+---+---+---+---+---+---+");
         for r in 0..8 {
@@ -172,6 +201,12 @@ impl Board {
         println!("{}", castling_ltrs.iter().collect::<String>());
     }
 
+    // Whether any piece of 'by' attacks 'sq', including squares occupied by its own pieces (i.e.
+    // squares it defends)
+    pub fn is_square_attacked(&self, sq: Sq, by: PieceColor, attack_info: &AttackInfo) -> bool {
+        sq_attacked(&self.pos, attack_info, sq, by)
+    }
+
     pub fn is_in_check(&self, attack_info: &AttackInfo, side: PieceColor) -> bool {
         let king_type = if side == PieceColor::Light {
             Piece::DK
@@ -187,6 +222,38 @@ impl Board {
     }
 }
 
+// Sets up a position piece by piece, e.g. for tests, without having to write a FEN. The derived
+// state (units and zobrist keys) is computed once the position is built.
+pub struct BoardBuilder<'a> {
+    board: Board,
+    zobrist_info: &'a ZobristInfo,
+}
+
+impl<'a> BoardBuilder<'a> {
+    // Replaces any piece that is already on 'sq'
+    pub fn place(mut self, piece: Piece, sq: Sq) -> Self {
+        for bb in self.board.pos.piece.iter_mut() {
+            *bb &= !(1 << sq as usize);
+        }
+        self.board.pos.piece[piece as usize].set(sq as usize);
+        self
+    }
+
+    pub fn set_side(mut self, color: PieceColor) -> Self {
+        if self.board.state.side != color {
+            self.board.state.change_side();
+        }
+        self
+    }
+
+    pub fn build(mut self) -> Board {
+        self.board.pos.update_units();
+        self.board.state.key = zobrist::gen_board_key(&self.zobrist_info.key, &self.board);
+        self.board.state.lock = zobrist::gen_board_lock(&self.zobrist_info.lock, &self.board);
+        self.board
+    }
+}
+
 pub fn sq_attacked(pos: &Position, attack_info: &AttackInfo, sq: Sq, side: PieceColor) -> bool {
     assert!(side != PieceColor::Both);
     if side == PieceColor::Light
@@ -229,3 +296,45 @@ pub fn sq_attacked(pos: &Position, attack_info: &AttackInfo, sq: Sq, side: Piece
     }
     return false;
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::attack;
+    use crate::board::Board;
+    use crate::consts::{Piece, PieceColor, Sq};
+    use crate::fen;
+    use crate::zobrist;
+
+    #[test]
+    fn builder_matches_fen() {
+        let zobrist_info = zobrist::shared_zobrist_info();
+        let built = Board::empty(zobrist_info)
+            .place(Piece::LK, Sq::E1)
+            .place(Piece::DK, Sq::E8)
+            .place(Piece::LQ, Sq::A4)
+            // Replaces the queen
+            .place(Piece::DP, Sq::A4)
+            .set_side(PieceColor::Dark)
+            .build();
+        let parsed = Board::from_fen("4k3/8/8/8/p7/8/8/4K3 b - - 0 1", zobrist_info);
+        assert_eq!(fen::gen_fen(&built), fen::gen_fen(&parsed));
+        assert_eq!(built.pos.units, parsed.pos.units);
+        assert_eq!((built.state.key, built.state.lock), (parsed.state.key, parsed.state.lock));
+    }
+
+    #[test]
+    fn squares_attacked_by_one_side() {
+        let attack_info = attack::shared_attack_info();
+        let zobrist_info = zobrist::shared_zobrist_info();
+        // The knight blocks the rook along the first rank
+        let board = Board::from_fen("7k/8/8/8/8/P7/8/RN5K w - - 0 1", zobrist_info);
+        let mut attacked: Vec<String> = (0..64).map(Sq::from_num)
+            .filter(|&sq| board.is_square_attacked(sq, PieceColor::Light, attack_info))
+            .map(Sq::to_string)
+            .collect();
+        attacked.sort();
+        assert_eq!(attacked, ["a2", "a3", "b1", "b4", "c3", "d2", "g1", "g2", "h2"]);
+        assert!(board.is_square_attacked(Sq::G7, PieceColor::Dark, attack_info));
+        assert!(!board.is_square_attacked(Sq::G2, PieceColor::Dark, attack_info));
+    }
+}
@@ -47,7 +47,7 @@ const STR_COORDS: [&str; 65] = [
     "a1", "b1", "c1", "d1", "e1", "f1", "g1", "h1", " "
 ];
 
-#[derive(Copy, Clone, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum PieceColor {
     Light,
     Dark,
@@ -59,11 +59,9 @@ pub fn parse(fen: &str, zobrist_info: &ZobristInfo) -> Board {
         board.state.enpassant = Sq::from_str(enpass_square);
     }
     // Set 50 move rule
-    let half_moves = fen_parts.next().unwrap().parse::<u32>().unwrap();
-    board.state.half_moves = half_moves;
-    // Set move counter
-    let full_moves = fen_parts.next().unwrap().parse::<u32>().unwrap();
-    board.state.full_moves = full_moves;
+    board.state.half_moves = parse_counter(fen_parts.next(), 0);
+    // Set move counter, which starts at 1
+    board.state.full_moves = parse_counter(fen_parts.next(), 1).max(1);
 
     // Update units bitboard from piece bitboard
     board.pos.update_units();
@@ -74,6 +72,69 @@ pub fn parse(fen: &str, zobrist_info: &ZobristInfo) -> Board {
     board
 }
 
+// The move counters are often left out, e.g. in EPD. A counter that is missing, or that can't be
+// read (e.g. "-1"), gets its value at the start of a game instead.
+fn parse_counter(counter: Option<&str>, default: u32) -> u32 {
+    counter.and_then(|c| c.parse().ok()).unwrap_or(default)
+}
+
+// Like 'parse', but checks the FEN first so that text from the user can't make the parsing panic.
+// The halfmove clock and fullmove number can be left out, in which case they are 0 and 1.
+pub fn try_parse(fen: &str, zobrist_info: &ZobristInfo) -> Result<Board, String> {
+    let parts: Vec<&str> = fen.split_ascii_whitespace().collect();
+    if parts.len() != 4 && parts.len() != 6 {
+        return Err(format!("Expected 4 or 6 fields in FEN, found {}", parts.len()));
+    }
+
+    let ranks: Vec<&str> = parts[0].split('/').collect();
+    if ranks.len() != 8 {
+        return Err(format!("Expected 8 ranks in FEN, found {}", ranks.len()));
+    }
+    for rank in &ranks {
+        let mut file_count = 0;
+        for c in rank.chars() {
+            match c {
+                '1'..='8' => file_count += c as u32 - '0' as u32,
+                'P' | 'N' | 'B' | 'R' | 'Q' | 'K' | 'p' | 'n' | 'b' | 'r' | 'q' | 'k' => file_count += 1,
+                _ => return Err(format!("Invalid character in FEN: '{}'", c)),
+            }
+        }
+        if file_count != 8 {
+            return Err(format!("Rank '{}' doesn't have 8 squares", rank));
+        }
+    }
+    if parts[0].matches('K').count() != 1 || parts[0].matches('k').count() != 1 {
+        return Err("Each side should have exactly one king".to_string());
+    }
+
+    if parts[1] != "w" && parts[1] != "b" {
+        return Err(format!("Invalid side to move: '{}'", parts[1]));
+    }
+    if parts[2] != "-" && (parts[2].is_empty() || !parts[2].chars().all(|c| "KQkq".contains(c))) {
+        return Err(format!("Invalid castling rights: '{}'", parts[2]));
+    }
+    let enpassant = parts[3].as_bytes();
+    if parts[3] != "-" && !(enpassant.len() == 2
+        && (b'a'..=b'h').contains(&enpassant[0]) && (enpassant[1] == b'3' || enpassant[1] == b'6'))
+    {
+        return Err(format!("Invalid enpassant square: '{}'", parts[3]));
+    }
+    let (half_moves, full_moves) = if parts.len() == 6 { (parts[4], parts[5]) } else { ("0", "1") };
+    if half_moves.parse::<u32>().is_err() || full_moves.parse::<u32>().is_err() {
+        return Err(format!("Invalid move counters: '{} {}'", half_moves, full_moves));
+    }
+
+    let fen = format!("{} {} {} {} {} {}", parts[0], parts[1], parts[2], parts[3], half_moves, full_moves);
+    Ok(parse(&fen, zobrist_info))
+}
+
+// Rewrites a FEN in the form 'gen_fen' gives it, so that FENs which describe the same position
+// compare equal: whitespace is collapsed, castling rights are ordered as 'KQkq' and missing move
+// counters are filled in
+pub fn normalize(fen: &str, zobrist_info: &ZobristInfo) -> Result<String, String> {
+    try_parse(fen, zobrist_info).map(|board| gen_fen(&board))
+}
+
 fn parse_pieces(fen_piece: &str, pos: &mut Position) {
     let mut sq: u8 = 0;
     for piece_char in fen_piece.chars().into_iter() {
@@ -163,3 +224,111 @@ pub fn gen_fen(board: &Board) -> String {
 
     output
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::attack;
+    use crate::fen::{self, FEN_POSITIONS};
+    use crate::moves::{self, MoveFlag};
+    use crate::zobrist;
+
+    #[test]
+    fn try_parse_accepts_valid_fens() {
+        let zobrist_info = zobrist::shared_zobrist_info();
+        // The first position is an empty board, which has no kings
+        for fen in &FEN_POSITIONS[1..] {
+            let board = fen::try_parse(fen, zobrist_info).unwrap();
+            assert_eq!(fen::gen_fen(&board), *fen);
+        }
+        let board = fen::try_parse("  4k3/8/8/8/8/8/8/4K3 b -  - ", zobrist_info).unwrap();
+        assert_eq!(fen::gen_fen(&board), "4k3/8/8/8/8/8/8/4K3 b - - 0 1");
+    }
+
+    #[test]
+    fn move_counters_are_optional() {
+        let zobrist_info = zobrist::shared_zobrist_info();
+        let parsed = |fen: &str| fen::gen_fen(&fen::parse(fen, zobrist_info));
+        let tried = |fen: &str| fen::gen_fen(&fen::try_parse(fen, zobrist_info).unwrap());
+        // 4 fields, as in EPD
+        assert_eq!(parsed("4k3/8/8/8/8/8/8/4K3 w - -"), "4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+        assert_eq!(tried("4k3/8/8/8/8/8/8/4K3 w - -"), "4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+        // 6 fields
+        assert_eq!(parsed("4k3/8/8/8/8/8/8/4K3 b - - 12 34"), "4k3/8/8/8/8/8/8/4K3 b - - 12 34");
+        assert_eq!(tried("4k3/8/8/8/8/8/8/4K3 b - - 12 34"), "4k3/8/8/8/8/8/8/4K3 b - - 12 34");
+        assert_eq!(tried("4k3/8/8/8/8/8/8/4K3 b - - 0 4000000000"), "4k3/8/8/8/8/8/8/4K3 b - - 0 4000000000");
+        // Counters that can't be read don't make 'parse' panic
+        assert_eq!(parsed("4k3/8/8/8/8/8/8/4K3 w - - -1 x"), "4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+        assert_eq!(parsed("4k3/8/8/8/8/8/8/4K3 w - - 7"), "4k3/8/8/8/8/8/8/4K3 w - - 7 1");
+        assert_eq!(parsed("4k3/8/8/8/8/8/8/4K3 w - - 0 99999999999"), "4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+        assert_eq!(parsed("4k3/8/8/8/8/8/8/4K3 w - - 3 0"), "4k3/8/8/8/8/8/8/4K3 w - - 3 1");
+    }
+
+    #[test]
+    fn normalize_equivalent_fens() {
+        let zobrist_info = zobrist::shared_zobrist_info();
+        for fen in &FEN_POSITIONS {
+            assert_eq!(fen::gen_fen(&fen::parse(fen, zobrist_info)), *fen);
+        }
+        for fen in &FEN_POSITIONS[1..] {
+            assert_eq!(fen::normalize(fen, zobrist_info).unwrap(), *fen);
+        }
+        let normalized = fen::normalize(" r3k2r/8/8/8/8/8/8/R3K2R  w qkQK - ", zobrist_info).unwrap();
+        assert_eq!(normalized, "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        assert!(fen::normalize("8/8/8/8/8/8/8/8 w - - 0 1", zobrist_info).is_err());
+    }
+
+    #[test]
+    fn gen_fen_follows_played_moves() {
+        let zobrist_info = zobrist::shared_zobrist_info();
+        let attack_info = attack::shared_attack_info();
+        let expected = [
+            (FEN_POSITIONS[1], vec![
+                ("e2e4", "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"),
+                ("c7c5", "rnbqkbnr/pp1ppppp/8/2p5/4P3/8/PPPP1PPP/RNBQKBNR w KQkq c6 0 2"),
+                ("g1f3", "rnbqkbnr/pp1ppppp/8/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"),
+                ("d7d6", "rnbqkbnr/pp2pppp/3p4/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 0 3"),
+                ("f1e2", "rnbqkbnr/pp2pppp/3p4/2p5/4P3/5N2/PPPPBPPP/RNBQK2R b KQkq - 1 3"),
+                ("e8d7", "rnbq1bnr/pp1kpppp/3p4/2p5/4P3/5N2/PPPPBPPP/RNBQK2R w KQ - 2 4"),
+                ("e1g1", "rnbq1bnr/pp1kpppp/3p4/2p5/4P3/5N2/PPPPBPPP/RNBQ1RK1 b - - 3 4"),
+                ("d7e8", "rnbqkbnr/pp2pppp/3p4/2p5/4P3/5N2/PPPPBPPP/RNBQ1RK1 w - - 4 5"),
+                ("e4e5", "rnbqkbnr/pp2pppp/3p4/2p1P3/8/5N2/PPPPBPPP/RNBQ1RK1 b - - 0 5"),
+                ("f7f5", "rnbqkbnr/pp2p1pp/3p4/2p1Pp2/8/5N2/PPPPBPPP/RNBQ1RK1 w - f6 0 6"),
+                ("e5f6", "rnbqkbnr/pp2p1pp/3p1P2/2p5/8/5N2/PPPPBPPP/RNBQ1RK1 b - - 0 6"),
+            ]),
+            (FEN_POSITIONS[7], vec![
+                ("f5e6", "rnbqkb1r/pp1p1pPp/4P3/2p5/1P1P4/3P3P/P1P1P3/RNBQKBNR b KQkq - 0 1"),
+                ("d7e6", "rnbqkb1r/pp3pPp/4p3/2p5/1P1P4/3P3P/P1P1P3/RNBQKBNR w KQkq - 0 2"),
+                ("g7h8q", "rnbqkb1Q/pp3p1p/4p3/2p5/1P1P4/3P3P/P1P1P3/RNBQKBNR b KQq - 0 2"),
+            ]),
+        ];
+        for (start, line) in expected {
+            let mut board = fen::parse(start, zobrist_info);
+            for (uci, fen) in line {
+                let mv = moves::from_uci(uci, &board, attack_info).unwrap();
+                assert!(moves::make(&mut board, attack_info, zobrist_info, mv, MoveFlag::AllMoves));
+                assert_eq!(fen::gen_fen(&board), fen, "{}", uci);
+            }
+        }
+    }
+
+    #[test]
+    fn try_parse_rejects_invalid_fens() {
+        let zobrist_info = zobrist::shared_zobrist_info();
+        for fen in [
+            "",
+            "not a fen",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w KQkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR/8 w KQkq - 0 1",
+            "rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqkbnr/ppppxppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQ1BNR w kq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQxq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e4 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - -1 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0",
+        ] {
+            assert!(fen::try_parse(fen, zobrist_info).is_err(), "{}", fen);
+        }
+    }
+}
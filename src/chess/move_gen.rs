@@ -2,7 +2,8 @@ use super::attack::AttackInfo;
 use super::bb::{BBUtil, BB};
 use super::board::{self, Board, CastlingType};
 use super::consts::{Direction, Piece, PieceColor, Sq};
-use super::moves::{Move, MoveUtil};
+use super::moves::{self, Move, MoveFlag, MoveUtil};
+use super::zobrist::ZobristInfo;
 
 pub struct MoveList {
     pub moves: Vec<Move>,
@@ -33,11 +34,20 @@ impl MoveList {
         println!("\n    Total number of moves: {}", self.moves.len());
     }
 
-    pub fn search(&mut self, source: Sq, target: Sq, promoted: Option<Piece>) -> Option<Move> {
-        self.moves
-            .iter()
-            .find(|mv| mv.source() == source && mv.target() == target && mv.promoted() == promoted)
-            .copied()
+    pub fn search(&self, source: Sq, target: Sq, promoted: Option<Piece>) -> Option<Move> {
+        self.find(|mv| mv.source() == source && mv.target() == target && mv.promoted() == promoted)
+    }
+
+    pub fn contains(&self, source: Sq, target: Sq, promoted: Option<Piece>) -> bool {
+        self.search(source, target, promoted).is_some()
+    }
+
+    pub fn find(&self, predicate: impl Fn(&Move) -> bool) -> Option<Move> {
+        self.moves.iter().find(|mv| predicate(mv)).copied()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Move> {
+        self.moves.iter()
     }
 }
 
@@ -469,3 +479,98 @@ fn gen_dark_castling(board: &Board, attack_info: &AttackInfo, ml: &mut MoveList)
         }
     }
 }
+
+// Counts the leaf nodes of the tree of legal moves 'depth' plies deep. The counts of well-known
+// positions are published, which makes this the standard check for the move generator.
+pub fn perft(board: &Board, depth: u32, attack_info: &AttackInfo, zobrist_info: &ZobristInfo) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let mut ml = MoveList::new();
+    generate_all(board, attack_info, &mut ml);
+    let mut nodes = 0;
+    for mv in ml.moves {
+        let mut next = board.clone();
+        // Pseudo-legal moves that leave the king in check aren't made
+        if moves::make(&mut next, attack_info, zobrist_info, mv, MoveFlag::AllMoves) {
+            nodes += if depth == 1 { 1 } else { perft(&next, depth - 1, attack_info, zobrist_info) };
+        }
+    }
+    nodes
+}
+
+// Perft split by the first move: each legal move along with the leaf nodes below it, which is how
+// a wrong count is narrowed down to the move that causes it by comparing with another engine
+pub fn perft_divide(board: &Board, depth: u32, attack_info: &AttackInfo, zobrist_info: &ZobristInfo) -> Vec<(Move, u64)> {
+    let mut ml = MoveList::new();
+    generate_all(board, attack_info, &mut ml);
+    let mut counts = vec![];
+    for mv in ml.moves {
+        let mut next = board.clone();
+        if moves::make(&mut next, attack_info, zobrist_info, mv, MoveFlag::AllMoves) {
+            counts.push((mv, perft(&next, depth.saturating_sub(1), attack_info, zobrist_info)));
+        }
+    }
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::attack;
+    use crate::consts::{Piece, Sq};
+    use crate::fen::{self, FEN_POSITIONS};
+    use crate::move_gen::{self, MoveList};
+    use crate::moves::MoveUtil;
+    use crate::zobrist;
+
+    #[test]
+    fn move_list_membership() {
+        let attack_info = attack::shared_attack_info();
+        let zobrist_info = zobrist::shared_zobrist_info();
+        // Kiwipete, where white can castle both ways
+        let board = fen::parse(FEN_POSITIONS[2], zobrist_info);
+        let mut ml = MoveList::new();
+        move_gen::generate_all(&board, attack_info, &mut ml);
+        assert!(ml.contains(Sq::E1, Sq::G1, None));
+        assert!(ml.contains(Sq::E1, Sq::C1, None));
+        assert!(ml.find(|mv| mv.is_castling()).is_some());
+        assert_eq!(ml.iter().filter(|mv| mv.is_castling()).count(), 2);
+        assert!(!ml.contains(Sq::E1, Sq::E3, None));
+        assert!(!ml.contains(Sq::D5, Sq::D6, Some(Piece::LQ)));
+    }
+
+    #[test]
+    fn perft_of_known_positions() {
+        let attack_info = attack::shared_attack_info();
+        let zobrist_info = zobrist::shared_zobrist_info();
+        // Node counts from https://www.chessprogramming.org/Perft_Results
+        let expected: [(usize, &[u64]); 6] = [
+            (1, &[20, 400, 8902]),
+            (2, &[48, 2039, 97862]),
+            (3, &[14, 191, 2812, 43238]),
+            (4, &[6, 264, 9467]),
+            (5, &[44, 1486, 62379]),
+            (6, &[46, 2079, 89890]),
+        ];
+        for (ind, counts) in expected {
+            let board = fen::parse(FEN_POSITIONS[ind], zobrist_info);
+            for (depth, &count) in counts.iter().enumerate() {
+                let nodes = move_gen::perft(&board, depth as u32 + 1, attack_info, zobrist_info);
+                assert_eq!(nodes, count, "{} at depth {}", FEN_POSITIONS[ind], depth + 1);
+            }
+        }
+    }
+
+    #[test]
+    fn perft_divide_adds_up() {
+        let attack_info = attack::shared_attack_info();
+        let zobrist_info = zobrist::shared_zobrist_info();
+        let board = fen::parse(FEN_POSITIONS[2], zobrist_info);
+        let counts = move_gen::perft_divide(&board, 3, attack_info, zobrist_info);
+        assert_eq!(counts.len(), 48);
+        assert_eq!(counts.iter().map(|(_, nodes)| nodes).sum::<u64>(), 97862);
+        // Published divide of Kiwipete at depth 3
+        let castling = counts.iter().find(|(mv, _)| mv.source() == Sq::E1 && mv.target() == Sq::G1);
+        assert_eq!(castling.map(|(_, nodes)| *nodes), Some(2059));
+    }
+}
@@ -2,6 +2,7 @@ use super::attack::AttackInfo;
 use super::bb::BBUtil;
 use super::board::{self, Board};
 use super::consts::{Direction, Piece, PieceColor, Sq};
+use super::move_gen::{self, MoveList};
 use super::zobrist::{self, ZobristAction, ZobristInfo};
 
 pub type Move = u32;
@@ -120,6 +121,57 @@ impl MoveUtil for Move {
     }
 }
 
+fn parse_sq(sq_str: &str) -> Option<Sq> {
+    let mut chars = sq_str.chars();
+    match (chars.next(), chars.next()) {
+        (Some(file @ 'a'..='h'), Some(rank @ '1'..='8')) => Some(Sq::from_str(&format!("{}{}", file, rank))),
+        _ => None,
+    }
+}
+
+// Moves in UCI notation always have the promotion letter in lower case (e.g. 'e7e8q' and 'e2e1q'),
+// whichever side promotes. The colored piece is only worked out from the side to move, here and
+// in 'from_uci'.
+pub fn to_uci(mv: Move) -> String {
+    let mut uci = format!("{}{}", Sq::to_string(mv.source()), Sq::to_string(mv.target()));
+    if let Some(piece) = mv.promoted() {
+        uci.push(Piece::to_char(Some(piece)).to_ascii_lowercase());
+    }
+    uci
+}
+
+// Converts a move in UCI notation (e.g. 'e2e4', 'e7e8q') into the matching move generated for
+// the side to move. Engines that send the promotion letter in upper case are understood as well.
+// Whether the move leaves the king in check is only known once it's made with 'make'.
+pub fn from_uci(uci: &str, board: &Board, attack_info: &AttackInfo) -> Option<Move> {
+    let uci = uci.trim();
+    if !uci.is_ascii() || (uci.len() != 4 && uci.len() != 5) {
+        return None;
+    }
+    let source = parse_sq(&uci[0..2])?;
+    let target = parse_sq(&uci[2..4])?;
+    let promoted = match uci.chars().nth(4) {
+        Some(ch @ ('n' | 'b' | 'r' | 'q' | 'N' | 'B' | 'R' | 'Q')) => {
+            let ch = if board.is_white_to_move() {
+                ch.to_ascii_uppercase()
+            } else {
+                ch.to_ascii_lowercase()
+            };
+            Piece::from_char(ch)
+        },
+        Some(_) => return None,
+        None => None,
+    };
+    let piece = board.find_piece(source as usize)?;
+    let (color, _) = Piece::to_tuple(Some(piece));
+    if color != board.state.side as usize {
+        return None;
+    }
+    let mut ml = MoveList::new();
+    move_gen::generate_by_piece(board, attack_info, &mut ml, piece);
+    ml.search(source, target, promoted)
+}
+
 #[derive(PartialEq)]
 pub enum MoveFlag {
     AllMoves,
@@ -343,11 +395,11 @@ pub fn make(
             *main = clone;
             return false;
         } else {
-            // Increment full moves
-            if main.state.side == PieceColor::Dark {
+            // Increment full moves once black has moved (the side was already switched above)
+            if main.state.side == PieceColor::Light {
                 main.state.full_moves += 1;
             }
-            if piece == Piece::LP as usize || is_capture {
+            if piece == Piece::LP as usize || piece == Piece::DP as usize || is_capture {
                 main.state.half_moves = 0;
             } else {
                 main.state.half_moves += 1;
@@ -360,3 +412,97 @@ pub fn make(
         false
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::attack;
+    use crate::board::Board;
+    use crate::consts::{Piece, PieceColor, Sq};
+    use crate::moves::{self, MoveFlag, MoveUtil};
+    use crate::zobrist;
+
+    #[test]
+    fn from_uci_finds_generated_moves() {
+        let attack_info = attack::shared_attack_info();
+        let zobrist_info = zobrist::shared_zobrist_info();
+        let board = Board::from_fen("r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1", zobrist_info);
+
+        let mv = moves::from_uci("e1g1", &board, attack_info).unwrap();
+        assert!(mv.is_castling());
+        let mv = moves::from_uci("e5d6", &board, attack_info).unwrap();
+        assert!(mv.is_enpassant());
+        let mv = moves::from_uci("b7a8q", &board, attack_info).unwrap();
+        assert!(mv.is_capture());
+        assert!(mv.promoted() == Some(Piece::LQ));
+        // Engines that send the promotion letter in upper case are also understood
+        let mv = moves::from_uci("b7b8N", &board, attack_info).unwrap();
+        assert!(mv.source() == Sq::B7 && mv.target() == Sq::B8);
+        assert!(mv.promoted() == Some(Piece::LN));
+
+        let board = Board::empty(zobrist_info)
+            .place(Piece::LK, Sq::E1)
+            .place(Piece::DK, Sq::E8)
+            .place(Piece::DP, Sq::A2)
+            .set_side(PieceColor::Dark)
+            .build();
+        let mv = moves::from_uci("a2a1R", &board, attack_info).unwrap();
+        assert!(mv.promoted() == Some(Piece::DR));
+    }
+
+    #[test]
+    fn promotions_are_lower_case_in_uci() {
+        let attack_info = attack::shared_attack_info();
+        let zobrist_info = zobrist::shared_zobrist_info();
+        let board = Board::from_fen("4k3/1P6/8/8/8/8/6p1/4K3 w - - 0 1", zobrist_info);
+        let mv = moves::from_uci("b7b8q", &board, attack_info).unwrap();
+        assert!(mv.promoted() == Some(Piece::LQ));
+        assert_eq!(moves::to_uci(mv), "b7b8q");
+        assert_eq!(moves::to_uci(moves::from_uci("e1d2", &board, attack_info).unwrap()), "e1d2");
+
+        let board = Board::from_fen("4k3/1P6/8/8/8/8/6p1/4K3 b - - 0 1", zobrist_info);
+        let mv = moves::from_uci("g2g1n", &board, attack_info).unwrap();
+        assert!(mv.promoted() == Some(Piece::DN));
+        assert_eq!(moves::to_uci(mv), "g2g1n");
+        let mv = moves::from_uci("g2g1Q", &board, attack_info).unwrap();
+        assert!(mv.promoted() == Some(Piece::DQ));
+        assert_eq!(moves::to_uci(mv), "g2g1q");
+    }
+
+    #[test]
+    fn from_uci_rejects_bad_input() {
+        let attack_info = attack::shared_attack_info();
+        let zobrist_info = zobrist::shared_zobrist_info();
+        let board = Board::from_fen("4k3/1P6/8/8/8/8/4P3/4K3 w - - 0 1", zobrist_info);
+
+        for uci in ["", "e2", "e2e", "e2e4e5", "i2i4", "e0e4", "e2e9", "a8a8P", "e7e5", "e2e5", "b7b8", "b7b8k", "e2e3x", "é2e4"] {
+            assert!(moves::from_uci(uci, &board, attack_info).is_none(), "'{}'", uci);
+        }
+    }
+
+    #[test]
+    fn make_updates_halfmove_clock() {
+        let attack_info = attack::shared_attack_info();
+        let zobrist_info = zobrist::shared_zobrist_info();
+        let mut board = Board::from_fen("4k3/3p4/8/8/8/8/3r4/R3K1N1 w - - 37 60", zobrist_info);
+
+        // Quiet move by a piece other than a pawn
+        let mv = moves::from_uci("g1f3", &board, attack_info).unwrap();
+        assert!(moves::make(&mut board, attack_info, zobrist_info, mv, MoveFlag::AllMoves));
+        assert_eq!(board.halfmove_clock(), 38);
+        // Pawn move (by black)
+        let mv = moves::from_uci("d7d6", &board, attack_info).unwrap();
+        assert!(moves::make(&mut board, attack_info, zobrist_info, mv, MoveFlag::AllMoves));
+        assert_eq!(board.halfmove_clock(), 0);
+        let mv = moves::from_uci("f3h4", &board, attack_info).unwrap();
+        assert!(moves::make(&mut board, attack_info, zobrist_info, mv, MoveFlag::AllMoves));
+        assert_eq!(board.halfmove_clock(), 1);
+        // Capture
+        let mv = moves::from_uci("d2a2", &board, attack_info).unwrap();
+        assert!(moves::make(&mut board, attack_info, zobrist_info, mv, MoveFlag::AllMoves));
+        assert_eq!(board.halfmove_clock(), 2);
+        let mv = moves::from_uci("a1a2", &board, attack_info).unwrap();
+        assert!(mv.is_capture());
+        assert!(moves::make(&mut board, attack_info, zobrist_info, mv, MoveFlag::AllMoves));
+        assert_eq!(board.halfmove_clock(), 0);
+    }
+}
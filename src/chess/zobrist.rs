@@ -2,6 +2,8 @@ use super::bb::BBUtil;
 use super::board::Board;
 use super::consts::{Piece, PieceColor, Sq};
 
+use std::sync::OnceLock;
+
 #[derive(Clone)]
 pub struct ZobristKey {
     pub piece: [[u64; 64]; 12],
@@ -83,6 +85,13 @@ impl ZobristInfo {
     }
 }
 
+// The keys are random, so boards only hash consistently with the same instance. Tests share this
+// one instead of each generating their own.
+pub fn shared_zobrist_info() -> &'static ZobristInfo {
+    static SHARED: OnceLock<ZobristInfo> = OnceLock::new();
+    SHARED.get_or_init(ZobristInfo::new)
+}
+
 pub enum ZobristAction {
     Castling,
     ChangeColor,
@@ -113,11 +122,8 @@ pub fn update(info: &ZobristInfo, action: ZobristAction, board: &mut Board) {
 
 pub fn gen_board_key(key: &ZobristKey, board: &Board) -> u64 {
     let mut final_key = 0;
-    let mut bb_copy;
     for piece in 0..12 {
-        bb_copy = board.pos.piece[piece];
-        while bb_copy != 0 {
-            let sq = bb_copy.pop_lsb();
+        for sq in board.pos.piece[piece].squares() {
             final_key ^= key.piece[piece][sq];
         }
     }
@@ -136,11 +142,8 @@ pub fn gen_board_key(key: &ZobristKey, board: &Board) -> u64 {
 
 pub fn gen_board_lock(lock: &ZobristLock, board: &Board) -> u64 {
     let mut final_lock = 0;
-    let mut bb_copy;
     for piece in 0..12 {
-        bb_copy = board.pos.piece[piece];
-        while bb_copy != 0 {
-            let sq = bb_copy.pop_lsb();
+        for sq in board.pos.piece[piece].squares() {
             final_lock ^= lock.piece[piece][sq];
         }
     }
@@ -0,0 +1,252 @@
+#[cfg(test)]
+use std::cell::Cell;
+#[cfg(test)]
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+// Where the time used for time accounting comes from. The GUI and headless matches use the real
+// time, while tests can advance a virtual time by hand to get deterministic results.
+pub trait TimeSource {
+    // Time elapsed since the source was created
+    fn now(&self) -> Duration;
+}
+
+pub struct RealTime {
+    start: Instant,
+}
+
+impl RealTime {
+    pub fn new() -> Self {
+        Self { start: Instant::now() }
+    }
+}
+
+impl TimeSource for RealTime {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+// Clones share the same time, so a test can keep one to advance the time of the clock it handed
+// the other one to
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub struct VirtualTime {
+    now: Rc<Cell<Duration>>,
+}
+
+#[cfg(test)]
+impl VirtualTime {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn advance(&self, time: Duration) {
+        self.now.set(self.now.get() + time);
+    }
+}
+
+#[cfg(test)]
+impl TimeSource for VirtualTime {
+    fn now(&self) -> Duration {
+        self.now.get()
+    }
+}
+
+// Time each side starts with and the time added after each of its moves
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimeControl {
+    pub base: Duration,
+    pub increment: Duration,
+}
+
+impl Default for TimeControl {
+    fn default() -> Self {
+        Self { base: Duration::from_secs(60), increment: Duration::ZERO }
+    }
+}
+
+impl TimeControl {
+    // Reads a time control like "3+2", i.e. the base time in minutes and the increment in seconds.
+    // The increment can be left out.
+    pub fn parse(tc: &str) -> Result<Self, String> {
+        let (base, increment) = tc.split_once('+').unwrap_or((tc, "0"));
+        let to_duration = |value: &str, unit: f64| {
+            value.trim().parse::<f64>().ok()
+                .and_then(|v| Duration::try_from_secs_f64(v * unit).ok())
+                .ok_or(format!("Invalid time control: '{}'", tc))
+        };
+        let (base, increment) = (to_duration(base, 60.0)?, to_duration(increment, 1.0)?);
+        if base.is_zero() {
+            return Err(format!("Invalid time control: '{}'", tc));
+        }
+        Ok(Self { base, increment })
+    }
+
+    // As written in the 'TimeControl' tag of a PGN, where both times are in seconds (e.g. "180+2")
+    pub fn to_pgn(&self) -> String {
+        format!("{}+{}", self.base.as_secs_f64(), self.increment.as_secs_f64())
+    }
+}
+
+// Keeps track of the time each side has left, where sides are indexed by engine. Only the time
+// between starting and stopping the clock of a side (i.e. while an engine is searching) is
+// charged, so time spent by the GUI in between moves doesn't count against anyone.
+pub struct ChessClock {
+    source: Box<dyn TimeSource>,
+    start_time: Duration,
+    increment: Duration,
+    time_left: [Duration; 2],
+    // The side whose clock is running and when it was started
+    running: Option<(usize, Duration)>,
+}
+
+impl ChessClock {
+    pub fn new(source: Box<dyn TimeSource>, start_time: Duration, increment: Duration) -> Self {
+        Self {
+            source,
+            start_time,
+            increment,
+            time_left: [start_time, start_time],
+            running: None,
+        }
+    }
+
+    pub fn now(&self) -> Duration {
+        self.source.now()
+    }
+
+    // Takes effect right away, so both sides get the new starting time
+    pub fn set_time_control(&mut self, time_control: TimeControl) {
+        self.start_time = time_control.base;
+        self.increment = time_control.increment;
+        self.reset();
+    }
+
+    // Gives both sides their starting time back
+    pub fn reset(&mut self) {
+        self.time_left = [self.start_time, self.start_time];
+        self.running = None;
+    }
+
+    pub fn start(&mut self, side: usize) {
+        self.stop();
+        self.running = Some((side, self.now()));
+    }
+
+    // Charges the running side for the time since its clock was started and returns that time
+    pub fn stop(&mut self) -> Duration {
+        if let Some((side, started)) = self.running.take() {
+            let elapsed = self.now().saturating_sub(started);
+            self.time_left[side] = self.time_left[side].saturating_sub(elapsed);
+            elapsed
+        } else {
+            Duration::ZERO
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.is_some()
+    }
+
+    pub fn add_increment(&mut self, side: usize) {
+        self.time_left[side] += self.increment;
+    }
+
+    pub fn time_left(&self, side: usize) -> Duration {
+        match self.running {
+            Some((running_side, started)) if running_side == side => {
+                let elapsed = self.now().saturating_sub(started);
+                self.time_left[side].saturating_sub(elapsed)
+            },
+            _ => self.time_left[side],
+        }
+    }
+
+    pub fn is_flagged(&self, side: usize) -> bool {
+        self.time_left(side).is_zero()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::clock::{ChessClock, TimeControl, VirtualTime};
+    use std::time::Duration;
+
+    #[test]
+    fn parse_time_controls() {
+        let tc = TimeControl::parse("3+2").unwrap();
+        assert_eq!(tc, TimeControl { base: Duration::from_secs(180), increment: Duration::from_secs(2) });
+        assert_eq!(tc.to_pgn(), "180+2");
+        let tc = TimeControl::parse("10").unwrap();
+        assert_eq!(tc, TimeControl { base: Duration::from_secs(600), increment: Duration::ZERO });
+        assert_eq!(TimeControl::parse("0.5+0.1").unwrap().to_pgn(), "30+0.1");
+        for tc in ["", "0", "0+1", "x+2", "3+", "-1+2", "3+-2", "inf"] {
+            assert!(TimeControl::parse(tc).is_err(), "'{}'", tc);
+        }
+    }
+
+    #[test]
+    fn reset_to_the_configured_time() {
+        let time = VirtualTime::new();
+        let mut clock = ChessClock::new(Box::new(time.clone()), Duration::from_secs(60), Duration::ZERO);
+        clock.set_time_control(TimeControl::parse("3+2").unwrap());
+        clock.start(0);
+        time.advance(Duration::from_secs(10));
+        clock.stop();
+        clock.add_increment(0);
+        assert_eq!(clock.time_left(0), Duration::from_secs(172));
+        clock.reset();
+        assert_eq!(clock.time_left(0), Duration::from_secs(180));
+        assert_eq!(clock.time_left(1), Duration::from_secs(180));
+    }
+
+    #[test]
+    fn flag_slow_move() {
+        let time = VirtualTime::new();
+        let mut clock = ChessClock::new(Box::new(time.clone()), Duration::from_secs(1), Duration::ZERO);
+
+        // The engine returns a move after 1.2s, but only has 1s left
+        clock.start(0);
+        time.advance(Duration::from_millis(1200));
+        assert!(clock.is_flagged(0));
+        assert_eq!(clock.stop(), Duration::from_millis(1200));
+        assert!(clock.is_flagged(0));
+        assert!(!clock.is_flagged(1));
+    }
+
+    #[test]
+    fn charge_only_the_side_to_move() {
+        let time = VirtualTime::new();
+        let mut clock = ChessClock::new(Box::new(time.clone()), Duration::from_secs(60), Duration::from_secs(2));
+
+        clock.start(1);
+        time.advance(Duration::from_millis(1500));
+        assert_eq!(clock.time_left(1), Duration::from_millis(58_500));
+        clock.stop();
+        clock.add_increment(1);
+        // Time that passes while no clock is running isn't charged to anyone
+        time.advance(Duration::from_secs(10));
+        clock.start(0);
+        time.advance(Duration::from_millis(250));
+        clock.stop();
+
+        assert_eq!(clock.time_left(0), Duration::from_millis(59_750));
+        assert_eq!(clock.time_left(1), Duration::from_millis(60_500));
+        assert!(!clock.is_flagged(0) && !clock.is_flagged(1));
+    }
+
+    #[test]
+    fn long_frame_between_moves_is_free() {
+        let time = VirtualTime::new();
+        let mut clock = ChessClock::new(Box::new(time.clone()), Duration::from_secs(1), Duration::ZERO);
+
+        // The GUI stalls for 5s before the engine is told to search
+        time.advance(Duration::from_secs(5));
+        clock.start(0);
+        time.advance(Duration::from_millis(400));
+        assert_eq!(clock.stop(), Duration::from_millis(400));
+        assert_eq!(clock.time_left(0), Duration::from_millis(600));
+        assert!(!clock.is_flagged(0));
+    }
+}
@@ -1,25 +1,169 @@
 use std::process::{Command, Child, ChildStdin, ChildStdout, Stdio};
 use std::io::{Write, Read};
-use std::time::Duration;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// The latest state of a search as reported by the engine's 'info' lines
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SearchInfo {
+    pub depth: Option<u32>,
+    // Score as sent by the engine, e.g. "cp 25" or "mate 3"
+    pub score: Option<String>,
+    // Principal variation in UCI notation
+    pub pv: Vec<String>,
+    pub nodes: Option<u64>,
+    // Time searched so far
+    pub time_ms: Option<u64>,
+    // Which of the lines this is when the engine sends several, starting at 1
+    pub multipv: Option<u32>,
+}
+
+impl SearchInfo {
+    // Whether this is the engine's best line, which is the only one without MultiPV
+    fn is_main_line(&self) -> bool {
+        self.multipv.map_or(true, |multipv| multipv == 1)
+    }
+}
+
+// An engine's evaluation, from the point of view of the side it was searching for
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Score {
+    // In centipawns
+    Cp(i32),
+    // Number of moves to mate, which is negative (or 0) when the engine is the one getting mated
+    Mate(i32),
+}
+
+impl Score {
+    // Reads a score as it's kept in 'SearchInfo', e.g. "cp 25" or "mate -3". Other kinds of
+    // scores (e.g. "wdl 1 2 3") give nothing.
+    pub fn parse(score: &str) -> Option<Score> {
+        let (kind, value) = score.trim().split_once(' ')?;
+        let value = value.trim().parse().ok()?;
+        match kind {
+            "cp" => Some(Score::Cp(value)),
+            "mate" => Some(Score::Mate(value)),
+            _ => None,
+        }
+    }
+}
+
+// What an engine sent at the end of its search
+#[derive(Clone, Debug, PartialEq)]
+pub enum BestMove {
+    // In UCI notation, as sent by the engine, so it can still be illegal
+    Move(String),
+    // The engine had no move to play, e.g. because it was checkmated or stalemated
+    NoMove,
+}
+
+impl BestMove {
+    // Reads the move that follows "bestmove". Engines without a move send "(none)" or the null
+    // move "0000" instead.
+    fn parse(text: &str) -> BestMove {
+        if text.trim_start().starts_with("(none)") {
+            return BestMove::NoMove;
+        }
+        match leading_move(text) {
+            "0000" => BestMove::NoMove,
+            mv => BestMove::Move(mv.to_string()),
+        }
+    }
+}
+
+// An option the engine reported in the handshake, e.g.
+// "option name Hash type spin default 16 min 1 max 33554432"
+#[derive(Clone, Debug, PartialEq)]
+pub struct UciOption {
+    pub name: String,
+    pub kind: UciOptionKind,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum UciOptionKind {
+    Spin { default: i64, min: i64, max: i64 },
+    Check { default: bool },
+    String { default: String },
+    // One of the values in 'vars'
+    Combo { default: String, vars: Vec<String> },
+    // Does something when it's set, without a value
+    Button,
+}
+
+impl UciOption {
+    // Why 'value' can't be given to the option, if it can't
+    fn check_value(&self, value: &str) -> Result<(), String> {
+        match &self.kind {
+            UciOptionKind::Spin { min, max, .. } => match value.parse::<i64>() {
+                Ok(v) if v >= *min && v <= *max => Ok(()),
+                _ => Err(format!("expected a number from {} to {}", min, max)),
+            },
+            UciOptionKind::Check { .. } if value != "true" && value != "false" => {
+                Err("expected 'true' or 'false'".to_string())
+            },
+            UciOptionKind::Combo { vars, .. } if !vars.iter().any(|var| var.eq_ignore_ascii_case(value)) => {
+                Err(format!("expected one of '{}'", vars.join("', '")))
+            },
+            _ => Ok(()),
+        }
+    }
+}
+
+// Options to set on an engine once it has started, as (name, value) pairs
+pub type EngineOptions = Vec<(String, String)>;
 
 pub struct EngineComm {
     process: Child,
     stdin: Option<ChildStdin>,
-    stdout: Option<ChildStdout>,
+    // Everything the engine writes, read by a thread of its own so that the GUI never waits on it
+    output: mpsc::Receiver<String>,
 
     name: String,
-    search_time_left: Option<Duration>,
+    author: String,
+    // Options the engine reported in the handshake
+    options: Vec<UciOption>,
     searching: bool,
+    // Move the engine expects its opponent to play, while it searches the position after it on
+    // the opponent's time. The search is still running, so 'searching' is set too.
+    pondering: Option<String>,
+    // Whether the search only ends once it's stopped, i.e. it's an analysis
+    infinite: bool,
+    // Searches that were stopped before they sent their move. Each of them still ends with a
+    // 'bestmove', and their output up to it is thrown away.
+    stopped_searches: usize,
+    // Move the current search ended with, once it has arrived
+    best_move: Option<BestMove>,
+    search_info: SearchInfo,
+    // Best lines of the current or last search, with the best one first. There's more than one
+    // once the engine was asked for them with 'set_multipv'.
+    pv_lines: Vec<(Score, Vec<String>)>,
+    // Reply the engine expects to its last move, if it sent one along with the move
+    ponder_move: Option<String>,
+    // Output that doesn't end in a newline yet
+    partial_line: String,
+    // Last line the current search sent, to tell why it failed if it doesn't send a move
+    last_output: String,
+    // When the current search started or last sent a line
+    last_output_time: Instant,
 }
 
 impl EngineComm {
-    const MAX_RE_READ_COUNT: usize = 4;
-    pub fn new(file_path: &str) -> Result<Self, ()> {
-        let mut process = Command::new(file_path)
+    // Maximum amount of wall-clock time an engine has to answer each step of the handshake
+    const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+    // How long an engine gets to exit after 'quit' before it's killed
+    const QUIT_TIMEOUT: Duration = Duration::from_millis(500);
+    // How long the rest of the output of an engine that exited has to arrive
+    const EXIT_OUTPUT_TIMEOUT: Duration = Duration::from_millis(100);
+
+    pub fn new(file_path: &str) -> Result<Self, String> {
+        let mut process = match Command::new(file_path)
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
-            .spawn()
-            .expect("Failed to start child process");
+            .spawn() {
+            Ok(process) => process,
+            Err(e) => return Err(format!("Failed to start engine '{}': {}", file_path, e)),
+        };
 
         // Take ownership of stdin and stdout
         let stdin = process.stdin.take().expect("Failed to open stdin");
@@ -27,45 +171,83 @@ impl EngineComm {
         let mut this = Self {
             process, 
             stdin: Some(stdin), 
-            stdout: Some(stdout),
+            output: Self::spawn_reader(stdout),
             name: String::new(),
-            search_time_left: None,
+            author: String::new(),
+            options: vec![],
             searching: false,
+            pondering: None,
+            infinite: false,
+            stopped_searches: 0,
+            best_move: None,
+            search_info: SearchInfo::default(),
+            pv_lines: vec![],
+            ponder_move: None,
+            partial_line: String::new(),
+            last_output: String::new(),
+            last_output_time: Instant::now(),
         };
-        if !this.uci() {
-            return Err(());
+        if let Err(e) = this.uci() {
+            // The process may not be a UCI engine at all, so it can't be trusted to exit on 'quit'
+            let _ = this.process.kill();
+            return Err(format!("'{}' is not a UCI engine: {}", file_path, e));
         }
         Ok(this)
     }
 
-    fn read(&mut self, buf: &mut String) {
-        assert!(self.stdout.is_some());
-        let stdout = self.stdout.as_mut().unwrap();
-
+    // Returns false once the engine's output has ended, e.g. because it exited
+    fn read(stdout: &mut ChildStdout, buf: &mut String) -> bool {
         let mut buffer = [0; 1024 * 64];
         match stdout.read(&mut buffer) {
-            Ok(_) => {
-                buf.clear();
-                *buf = String::from_utf8_lossy(&buffer).into_owned();
-                // *buf = String::from_utf8((&buffer).to_vec()).unwrap();
+            Ok(0) => false,
+            // Only the part of the buffer that was read into is kept
+            Ok(n) => {
+                *buf = String::from_utf8_lossy(&buffer[..n]).into_owned();
+                true
             }
-            Err(e) => eprintln!("[ERROR] {e}"),
-        };
+            Err(e) => {
+                eprintln!("[ERROR] {e}");
+                false
+            },
+        }
+    }
+
+    // The reads block, so they're done on a thread that sends over what it read until the
+    // engine's output ends
+    fn spawn_reader(mut stdout: ChildStdout) -> mpsc::Receiver<String> {
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let mut chunk = String::new();
+            while Self::read(&mut stdout, &mut chunk) {
+                if tx.send(std::mem::take(&mut chunk)).is_err() {
+                    break;
+                }
+            }
+        });
+        rx
     }
 
+    // Adds whatever output has arrived to 'buf' without waiting for more, and returns where the
+    // last 'pat' in 'buf' is
     fn read_until_rmatch(&mut self, pat: &str, buf: &mut String) -> Option<usize> {
-        let mut temp = String::new();
-        let mut loop_count = 0;
-        // Note: Loop count needed to prevent the current thread from being
-        //       infinitely blocked.
-        while loop_count <= Self::MAX_RE_READ_COUNT {
-            self.read(&mut temp);
-            buf.push_str(&temp);
-            let found_pat = buf.rfind(pat);
-            if found_pat.is_some() { return found_pat; }
-            loop_count += 1;
+        while let Ok(chunk) = self.output.try_recv() {
+            buf.push_str(&chunk);
+        }
+        buf.rfind(pat)
+    }
+
+    // Same as 'read_until_rmatch', but waits at most 'timeout' for 'pat' to arrive
+    fn read_until_rmatch_timeout(&mut self, pat: &str, buf: &mut String, timeout: Duration) -> Option<usize> {
+        let start = Instant::now();
+        loop {
+            if let Some(ind) = self.read_until_rmatch(pat, buf) {
+                return Some(ind);
+            }
+            match self.output.recv_timeout(timeout.saturating_sub(start.elapsed())) {
+                Ok(chunk) => buf.push_str(&chunk),
+                Err(_) => return None,
+            }
         }
-        None
     }
 
     fn send(&mut self, cmd: &str) {
@@ -83,97 +265,705 @@ impl EngineComm {
         // println!("[SEND] {}", cmd.trim());
     }
 
-    fn uci(&mut self) -> bool {
+    fn uci(&mut self) -> Result<(), String> {
         let mut buf = String::new();
         self.send("uci");
-        if self.read_until_rmatch("uciok", &mut buf).is_none() {
-            return false;
+        if self.read_until_rmatch_timeout("uciok", &mut buf, Self::HANDSHAKE_TIMEOUT).is_none() {
+            return Err("no 'uciok' received in response to 'uci'".to_string());
         }
         for line in buf.lines() {
-            let mut words = line.split_whitespace();
-            if let Some(word) = words.next() {
-                if &word[word.len() - 2..] != "id" { continue; }
-            }
-            if let Some(word) = words.next() {
-                match word {
-                    "name" => self.name = words.next().unwrap_or("No name").to_string(),
-                    _ => {}
-                };
+            match parse_id_line(line) {
+                Some(("name", value)) => self.name = value.to_string(),
+                Some(("author", value)) => self.author = value.to_string(),
+                _ => {}
+            };
+            if let Some(option) = parse_option_line(line) {
+                self.options.push(option);
             }
         }
+        if self.name.is_empty() {
+            self.name = "No name".to_string();
+        }
         self.send("isready");
         buf.clear();
-        if self.read_until_rmatch("readyok", &mut buf).is_none() {
-            return false;
+        if self.read_until_rmatch_timeout("readyok", &mut buf, Self::HANDSHAKE_TIMEOUT).is_none() {
+            return Err("no 'readyok' received in response to 'isready'".to_string());
         }
-        true
+        Ok(())
+    }
+
+    pub fn options(&self) -> &[UciOption] {
+        &self.options
+    }
+
+    // Option names aren't case sensitive in UCI. An option the engine doesn't have, or a value it
+    // doesn't take, is only warned about, since it shouldn't keep the match from being played.
+    pub fn set_option(&mut self, name: &str, value: &str) {
+        let option = self.options.iter().find(|option| option.name.eq_ignore_ascii_case(name));
+        let option = if let Some(option) = option { option } else {
+            eprintln!("[WARN] Engine '{}' has no option '{}', ignoring it", self.name, name);
+            return;
+        };
+        if let Err(e) = option.check_value(value) {
+            eprintln!("[WARN] Invalid value '{}' for option '{}' of engine '{}': {}", value, option.name, self.name, e);
+            return;
+        }
+        let command = match option.kind {
+            UciOptionKind::Button => format!("setoption name {}", option.name),
+            _ => format!("setoption name {} value {}", option.name, value),
+        };
+        self.send(&command);
+    }
+
+    // Sets all the options, then waits until the engine is done applying them (e.g. allocating
+    // its hash table) so that it doesn't happen on the clock
+    pub fn set_options(&mut self, options: &[(String, String)]) -> Result<(), String> {
+        if options.is_empty() { return Ok(()); }
+        for (name, value) in options {
+            self.set_option(name, value);
+        }
+        self.send("isready");
+        let mut buf = String::new();
+        if self.read_until_rmatch_timeout("readyok", &mut buf, Self::HANDSHAKE_TIMEOUT).is_none() {
+            return Err(format!("Engine '{}' wasn't ready after setting its options", self.name));
+        }
+        Ok(())
+    }
+
+    // Makes the engine send its 'count' best lines instead of only the best one, from the next
+    // search on
+    pub fn set_multipv(&mut self, count: u32) {
+        self.set_option("MultiPV", &count.to_string());
     }
 
     pub fn fen(&mut self, fen: &str) {
         self.send(&format!("position fen {}", fen));
     }
 
+    pub fn has_option(&self, name: &str) -> bool {
+        self.options.iter().any(|option| option.name.eq_ignore_ascii_case(name))
+    }
+
+    // Ends the search without waiting for its move, which is thrown away once it arrives
     pub fn stop(&mut self) {
-        self.searching = false;
-        self.search_time_left = None;
         self.send("stop");
+        if self.searching {
+            self.read_search_output();
+            if self.best_move.is_none() {
+                self.stopped_searches += 1;
+            }
+        }
+        self.end_search();
+    }
+
+    fn start_search(&mut self, cmd: &str) {
+        self.send(cmd);
+        self.searching = true;
+        self.best_move = None;
+        self.search_info = SearchInfo::default();
+        self.pv_lines.clear();
+        self.ponder_move = None;
+        self.last_output.clear();
+        self.last_output_time = Instant::now();
+    }
+
+    fn end_search(&mut self) {
+        self.searching = false;
+        self.pondering = None;
+        self.infinite = false;
+        self.best_move = None;
+    }
+
+    // Searches the current position until 'stop' is called. The search can be followed with
+    // 'poll_analysis' and 'search_info'.
+    pub fn go_infinite(&mut self) {
+        self.search_in_background("go infinite", true);
+    }
+
+    // Searches the current position to 'depth', which can take any amount of time, so the search
+    // is followed with 'poll_best_move' instead of waiting for it
+    pub fn search_depth(&mut self, depth: u32) {
+        self.search_in_background(&format!("go depth {}", depth), false);
+    }
+
+    fn search_in_background(&mut self, cmd: &str, infinite: bool) {
+        self.start_search(cmd);
+        self.infinite = infinite;
+    }
+
+    pub fn is_analyzing(&self) -> bool {
+        self.searching && self.infinite
+    }
+
+    // Goes through the output received since the last call without waiting for more, and returns
+    // whether the search info was updated
+    pub fn poll_analysis(&mut self) -> bool {
+        self.searching && self.read_search_output()
+    }
+
+    // Reads the complete lines the engine has sent so far, and returns whether the search info
+    // was updated. A line that is still arriving is kept until the rest of it has been read, so
+    // that e.g. a 'bestmove' line is never taken before its move is complete.
+    fn read_search_output(&mut self) -> bool {
+        while let Ok(chunk) = self.output.try_recv() {
+            self.partial_line.push_str(&chunk);
+        }
+        let end = if let Some(end) = self.partial_line.rfind('\n') { end } else { return false; };
+        let rest = self.partial_line.split_off(end + 1);
+        let lines = std::mem::replace(&mut self.partial_line, rest);
+
+        // Lines of the current search, up to its move
+        let mut output = String::new();
+        for line in lines.lines() {
+            let best_move = parse_best_move_line(line);
+            if self.stopped_searches > 0 {
+                // Everything up to the move of a stopped search belongs to it
+                if best_move.is_some() {
+                    self.stopped_searches -= 1;
+                }
+                continue;
+            }
+            if !self.searching || self.best_move.is_some() { continue; }
+            self.last_output_time = Instant::now();
+            if let Some((best_move, ponder_move)) = best_move {
+                self.best_move = Some(best_move);
+                self.ponder_move = ponder_move;
+            } else {
+                output.push_str(line);
+                output.push('\n');
+            }
+            if !line.trim().is_empty() {
+                self.last_output = line.trim().to_string();
+            }
+        }
+        for info in output.lines().filter_map(parse_info_line) {
+            update_pv_lines(&mut self.pv_lines, &info);
+        }
+        if let Some(info) = last_scored_info(&output) {
+            self.search_info = info;
+            return true;
+        }
+        false
+    }
+
+    // Returns the best move of the search once it has ended, without waiting for it
+    pub fn poll_best_move(&mut self) -> Option<BestMove> {
+        self.read_search_output();
+        let best_move = self.best_move.take()?;
+        self.end_search();
+        Some(best_move)
+    }
+
+    pub fn search_info(&self) -> &SearchInfo {
+        &self.search_info
     }
 
     pub fn name(&self) -> &String {
         &self.name
     }
 
+    pub fn author(&self) -> &String {
+        &self.author
+    }
+
+    // Searches the position after 'ponder_move' is played from 'fen' until the opponent has
+    // moved. Then 'ponderhit' turns it into a search of 'time_ms' if the opponent played that
+    // move, and otherwise the search has to be stopped and its move thrown away.
+    pub fn ponder(&mut self, fen: &str, ponder_move: &str, time_ms: u64) {
+        self.send(&format!("position fen {} moves {}", fen, ponder_move));
+        self.start_search(&format!("go ponder movetime {}", time_ms));
+        self.pondering = Some(ponder_move.to_string());
+    }
+
+    // The opponent played the move the engine was pondering on, so the search goes on as a
+    // normal one, whose move is read with 'best_move'
+    pub fn ponderhit(&mut self) {
+        self.send("ponderhit");
+        self.pondering = None;
+    }
+
+    pub fn pondered_move(&self) -> Option<&str> {
+        self.pondering.as_deref()
+    }
+
     pub fn search_movetime(&mut self, time_ms: u64) {
-        self.send(&format!("go movetime {}", time_ms));
-        self.search_time_left = Some(Duration::from_millis(time_ms));
-        self.searching = true;
+        self.start_search(&format!("go movetime {}", time_ms));
     }
 
     pub fn is_searching(&mut self) -> bool {
         self.searching
     }
 
-    pub fn update_time_left(&mut self, time_s: f32) {
-        if let Some(stl) = self.search_time_left.take() {
-            let frame_dur = Duration::from_secs_f32(time_s);
-            self.search_time_left = stl.checked_sub(frame_dur);
+    // Waits at most 'timeout' for the move, so that an engine that stopped responding can't hang
+    // the GUI. With no timeout, it only checks whether the move has arrived, and the search goes
+    // on if it hasn't.
+    pub fn best_move(&mut self, timeout: Duration) -> Option<BestMove> {
+        let start = Instant::now();
+        loop {
+            if let Some(best_move) = self.poll_best_move() {
+                return Some(best_move);
+            }
+            match self.output.recv_timeout(timeout.saturating_sub(start.elapsed())) {
+                Ok(chunk) => self.partial_line.push_str(&chunk),
+                Err(_) => return None,
+            }
         }
     }
 
-    pub fn search_time_over(&mut self) -> bool {
-        let result = self.search_time_left.is_none();
-        if result { self.searching = false; }
-        result
+    pub fn ponder_move(&self) -> Option<String> {
+        self.ponder_move.clone()
     }
 
-    pub fn best_move(&mut self) -> Option<String> {
-        let mut buf = String::new();
-        if let Some(ind) = self.read_until_rmatch("bestmove", &mut buf) {
-            // TODO: try to parse the last evaluation from the output produced by the engine
-
-            let best_move = &buf[(ind+8)..].trim_start();
-            let mut i = 0;
-            loop {
-                let mut iter = best_move.chars();
-                if let Some(ch) = iter.nth(i) {
-                    if ch.is_ascii_alphanumeric() {
-                        i += 1;
-                    } else {
-                        break;
+    // Best lines of the engine's last search, each with its score, best first
+    pub fn pv_lines(&self) -> &Vec<(Score, Vec<String>)> {
+        &self.pv_lines
+    }
+
+    // Evaluation of the engine's last search, which is the last score it sent before its move
+    pub fn last_eval(&self) -> Option<Score> {
+        self.search_info.score.as_deref().and_then(Score::parse)
+    }
+
+    pub fn last_output(&self) -> &String {
+        &self.last_output
+    }
+
+    // How long it's been since the current search sent anything
+    pub fn silent_for(&self) -> Duration {
+        self.last_output_time.elapsed()
+    }
+
+    pub fn is_alive(&mut self) -> bool {
+        matches!(self.process.try_wait(), Ok(None))
+    }
+
+    // Reads the rest of the output of an engine that exited, which can still be on its way, e.g.
+    // the move it sent right before exiting. Once the output has ended, a line the engine didn't
+    // finish counts as complete.
+    pub fn read_remaining_output(&mut self) {
+        let start = Instant::now();
+        loop {
+            match self.output.recv_timeout(Self::EXIT_OUTPUT_TIMEOUT.saturating_sub(start.elapsed())) {
+                Ok(chunk) => self.partial_line.push_str(&chunk),
+                Err(mpsc::RecvTimeoutError::Timeout) => return,
+                Err(mpsc::RecvTimeoutError::Disconnected) => {
+                    if !self.partial_line.is_empty() && !self.partial_line.ends_with('\n') {
+                        self.partial_line.push('\n');
                     }
-                }
+                    return;
+                },
             }
-            Some(best_move[0..i].to_string())
-        } else {
-            None
         }
     }
 }
 
+// Starts the two engines of a match and sets their options. Without a second engine, the first
+// one plays against itself.
+pub fn start_engines(
+    engine_a_path: &str, engine_b_path: Option<&str>, options: &[EngineOptions; 2]
+) -> Result<(EngineComm, EngineComm), String> {
+    let mut engine_a = EngineComm::new(engine_a_path)?;
+    let mut engine_b = EngineComm::new(engine_b_path.unwrap_or(engine_a_path))?;
+    engine_a.set_options(&options[0])?;
+    engine_b.set_options(&options[1])?;
+    Ok((engine_a, engine_b))
+}
+
+// Reads the depth, score, node count, time and principal variation from an 'info' line
+fn parse_info_line(line: &str) -> Option<SearchInfo> {
+    let line = line.trim_matches(|ch: char| ch == '\0' || ch.is_whitespace());
+    let mut tokens = line.split_whitespace();
+    if tokens.next() != Some("info") { return None; }
+
+    let mut info = SearchInfo::default();
+    while let Some(token) = tokens.next() {
+        match token {
+            "depth" => info.depth = tokens.next().and_then(|d| d.parse().ok()),
+            "nodes" => info.nodes = tokens.next().and_then(|n| n.parse().ok()),
+            "time" => info.time_ms = tokens.next().and_then(|t| t.parse().ok()),
+            "multipv" => info.multipv = tokens.next().and_then(|m| m.parse().ok()),
+            "score" => {
+                if let (Some(kind), Some(value)) = (tokens.next(), tokens.next()) {
+                    info.score = Some(format!("{} {}", kind, value));
+                }
+            },
+            // The pv is always the last part of the line
+            "pv" => {
+                info.pv = tokens.map(String::from).collect();
+                break;
+            },
+            // Free-form text that could contain any of the keywords above
+            "string" => break,
+            _ => {}
+        }
+    }
+    Some(info)
+}
+
+// The last 'info' line with a score in the output of a search, which is the engine's evaluation
+// of its move. The output can have any number of lines, read at once or not.
+fn last_scored_info(output: &str) -> Option<SearchInfo> {
+    output.lines().rev().filter_map(parse_info_line).find(|info| info.score.is_some() && info.is_main_line())
+}
+
+// Keeps the latest version of each of the lines, which the engine sends in order of their index
+// for every depth
+fn update_pv_lines(lines: &mut Vec<(Score, Vec<String>)>, info: &SearchInfo) {
+    let score = if let Some(score) = info.score.as_deref().and_then(Score::parse) { score } else { return; };
+    if info.pv.is_empty() { return; }
+    let ind = info.multipv.unwrap_or(1).saturating_sub(1) as usize;
+    if ind < lines.len() {
+        lines[ind] = (score, info.pv.clone());
+    } else if ind == lines.len() {
+        lines.push((score, info.pv.clone()));
+    }
+}
+
+// Reads the move and the ponder move, if there's one, from a 'bestmove' line, e.g.
+// "bestmove e2e4 ponder e7e5"
+fn parse_best_move_line(line: &str) -> Option<(BestMove, Option<String>)> {
+    let line = line.trim_matches(|ch: char| ch == '\0' || ch.is_whitespace());
+    let rest = line.strip_prefix("bestmove")?;
+    if !rest.starts_with(char::is_whitespace) || rest.trim().is_empty() { return None; }
+    Some((BestMove::parse(rest), parse_ponder_move(rest)))
+}
+
+// Reads the move after 'ponder' from what follows "bestmove" on its line, e.g. " e2e4 ponder e7e5"
+fn parse_ponder_move(text: &str) -> Option<String> {
+    let mut tokens = text.lines().next()?.split_whitespace().skip(1);
+    if tokens.next() != Some("ponder") { return None; }
+    tokens.next().filter(|&mv| mv != "(none)" && mv != "0000").map(String::from)
+}
+
+// The move at the start of 'text', e.g. what follows "bestmove". The output can end right after
+// the move, so it ends at the end of 'text' if nothing else comes first.
+fn leading_move(text: &str) -> &str {
+    let text = text.trim_start();
+    let end = text.find(|ch: char| !ch.is_ascii_alphanumeric()).unwrap_or(text.len());
+    &text[..end]
+}
+
+// Splits an 'id' line (e.g. "id name Stockfish 16") into its field and value, where the value is
+// the rest of the line so that multi-word names and authors are kept intact
+fn parse_id_line(line: &str) -> Option<(&str, &str)> {
+    // Stray NUL bytes are trimmed along with the whitespace
+    let line = line.trim_matches(|ch: char| ch == '\0' || ch.is_whitespace());
+    let (cmd, rest) = line.split_once(char::is_whitespace)?;
+    if cmd != "id" { return None; }
+    let rest = rest.trim_start();
+    let (field, value) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    Some((field, value.trim()))
+}
+
+// Reads an 'option' line, e.g. "option name Skill Level type spin default 20 min 0 max 20", where
+// the name and the values can be several words long. A combo lists each of its values after a
+// 'var'.
+fn parse_option_line(line: &str) -> Option<UciOption> {
+    let mut tokens = line.trim_matches(|ch: char| ch == '\0' || ch.is_whitespace()).split_whitespace();
+    if tokens.next() != Some("option") || tokens.next() != Some("name") { return None; }
+    let name: Vec<&str> = tokens.by_ref().take_while(|&token| token != "type").collect();
+    if name.is_empty() { return None; }
+    let kind = tokens.next()?;
+
+    // Every value, along with the keyword in front of it
+    let mut fields: Vec<(&str, Vec<&str>)> = vec![];
+    for token in tokens {
+        match token {
+            "default" | "min" | "max" | "var" => fields.push((token, vec![])),
+            _ => fields.last_mut()?.1.push(token),
+        }
+    }
+    let field = |key: &str| fields.iter().find(|(k, _)| *k == key).map(|(_, words)| words.join(" "));
+    let number = |key: &str| field(key).and_then(|value| value.parse::<i64>().ok());
+    let default = field("default").unwrap_or_default();
+    let kind = match kind {
+        // A bound that's missing doesn't limit the values
+        "spin" => UciOptionKind::Spin {
+            default: number("default").unwrap_or(0),
+            min: number("min").unwrap_or(i64::MIN),
+            max: number("max").unwrap_or(i64::MAX),
+        },
+        "check" => UciOptionKind::Check { default: default == "true" },
+        "string" => UciOptionKind::String { default },
+        "combo" => {
+            let vars = fields.iter().filter(|(key, _)| *key == "var").map(|(_, words)| words.join(" ")).collect();
+            UciOptionKind::Combo { default, vars }
+        },
+        "button" => UciOptionKind::Button,
+        _ => return None,
+    };
+    Some(UciOption { name: name.join(" "), kind })
+}
+
 impl Drop for EngineComm {
+    // Nothing here is allowed to panic or block for long, so that an engine which ignores 'quit'
+    // is killed instead of being left running or hanging the GUI
     fn drop(&mut self) {
-        self.send("quit");
+        if self.stdin.is_some() {
+            self.send("quit");
+        }
         drop(self.stdin.take());
-        let _ = self.process.wait().expect("Failed to wait for child process");
+        let start = Instant::now();
+        while start.elapsed() < Self::QUIT_TIMEOUT {
+            match self.process.try_wait() {
+                Ok(Some(_)) => return,
+                Ok(None) => thread::sleep(Duration::from_millis(10)),
+                Err(_) => break,
+            }
+        }
+        eprintln!("[WARN] Engine '{}' didn't quit in time, killing it", self.name);
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::comm::{self, BestMove, EngineComm, Score, UciOptionKind};
+
+    #[test]
+    fn parse_multi_word_id_name() {
+        assert_eq!(comm::parse_id_line("id name Stockfish 16"), Some(("name", "Stockfish 16")));
+        assert_eq!(comm::parse_id_line("id name The King"), Some(("name", "The King")));
+        assert_eq!(
+            comm::parse_id_line("id name  My Engine v1.2 \r"),
+            Some(("name", "My Engine v1.2"))
+        );
+        assert_eq!(
+            comm::parse_id_line("id author the Stockfish developers (see AUTHORS file)"),
+            Some(("author", "the Stockfish developers (see AUTHORS file)"))
+        );
+    }
+
+    #[test]
+    fn parse_info_lines() {
+        let info = comm::parse_info_line(
+            "info depth 12 seldepth 18 multipv 1 score cp -35 nodes 5031 nps 503100 time 10 pv e7e5 g1f3 b8c6"
+        ).unwrap();
+        assert_eq!(info.depth, Some(12));
+        assert_eq!(info.nodes, Some(5031));
+        assert_eq!(info.time_ms, Some(10));
+        assert_eq!(info.score.as_deref(), Some("cp -35"));
+        assert_eq!(info.pv, vec!["e7e5", "g1f3", "b8c6"]);
+        assert_eq!(info.multipv, Some(1));
+
+        let info = comm::parse_info_line("\0\0info depth 3 score mate 2 upperbound pv d1h5\r").unwrap();
+        assert_eq!(info.score.as_deref(), Some("mate 2"));
+        assert_eq!(info.pv, vec!["d1h5"]);
+
+        let info = comm::parse_info_line("info string depth 5 pv a2a4").unwrap();
+        assert_eq!(info, comm::SearchInfo::default());
+        assert!(comm::parse_info_line("bestmove e2e4").is_none());
+    }
+
+    #[test]
+    fn keep_the_last_score() {
+        let output = "info depth 1 score cp 20 pv e2e4\ninfo depth 2 score mate 3 pv d1h5 e8e7\n\
+                      info depth 2 currmove g1f3 currmovenumber 2\ninfo string hello\n\0\0";
+        let info = comm::last_scored_info(output).unwrap();
+        assert_eq!((info.depth, info.score.as_deref()), (Some(2), Some("mate 3")));
+        assert_eq!(comm::last_scored_info("info depth 1 pv e2e4\n"), None);
+
+        assert_eq!(Score::parse("cp -35"), Some(Score::Cp(-35)));
+        assert_eq!(Score::parse("mate 3"), Some(Score::Mate(3)));
+        assert_eq!(Score::parse("mate -2"), Some(Score::Mate(-2)));
+        assert_eq!(Score::parse("wdl 1 2 3"), None);
+        assert_eq!(Score::parse("cp"), None);
+    }
+
+    #[test]
+    fn collect_multipv_lines() {
+        let mut lines = vec![];
+        let output = "info depth 1 multipv 1 score cp 30 pv e2e4\n\
+                      info depth 1 multipv 2 score cp 20 pv d2d4\n\
+                      info depth 2 multipv 1 score cp 25 pv d2d4 d7d5\n\
+                      info depth 2 currmove e2e4 currmovenumber 2\n\
+                      info depth 2 multipv 2 score mate 4 pv e2e4 e7e5\n\
+                      info depth 2 multipv 4 score cp 0 pv a2a3\n";
+        for info in output.lines().filter_map(comm::parse_info_line) {
+            comm::update_pv_lines(&mut lines, &info);
+        }
+        let pv = |moves: &str| moves.split(' ').map(String::from).collect::<Vec<_>>();
+        assert_eq!(lines, [(Score::Cp(25), pv("d2d4 d7d5")), (Score::Mate(4), pv("e2e4 e7e5"))]);
+        // The best line is the engine's evaluation
+        assert_eq!(comm::last_scored_info(output).unwrap().score.as_deref(), Some("cp 25"));
+
+        // Without MultiPV, every line is the best one
+        let mut lines = vec![];
+        for line in ["info depth 1 score cp 5 pv g1f3", "info depth 2 score cp 8 pv e2e4"] {
+            comm::update_pv_lines(&mut lines, &comm::parse_info_line(line).unwrap());
+        }
+        assert_eq!(lines, [(Score::Cp(8), pv("e2e4"))]);
+    }
+
+    #[test]
+    fn parse_best_move_lines() {
+        let best_move = |mv: &str, ponder: Option<&str>| Some((BestMove::Move(mv.to_string()), ponder.map(String::from)));
+        assert_eq!(comm::parse_best_move_line("bestmove e2e4"), best_move("e2e4", None));
+        assert_eq!(comm::parse_best_move_line("\0\0bestmove e7e8q ponder a2a3\r"), best_move("e7e8q", Some("a2a3")));
+        assert_eq!(comm::parse_best_move_line("bestmove"), None);
+        assert_eq!(comm::parse_best_move_line("info depth 1 pv e2e4"), None);
+    }
+
+    #[test]
+    fn no_move_in_a_finished_position() {
+        assert_eq!(comm::parse_best_move_line("bestmove (none)"), Some((BestMove::NoMove, None)));
+        assert_eq!(comm::parse_best_move_line("bestmove 0000"), Some((BestMove::NoMove, None)));
+        assert_eq!(BestMove::parse(" (none)\n"), BestMove::NoMove);
+        assert_eq!(BestMove::parse(" 0000"), BestMove::NoMove);
+        assert_eq!(BestMove::parse(" a7a8q ponder 0000"), BestMove::Move("a7a8q".to_string()));
+    }
+
+    #[test]
+    fn read_the_ponder_move() {
+        assert_eq!(comm::parse_ponder_move(" e2e4 ponder e7e5\ninfo depth 1"), Some("e7e5".to_string()));
+        assert_eq!(comm::parse_ponder_move(" e2e4 ponder e7e5"), Some("e7e5".to_string()));
+        assert_eq!(comm::parse_ponder_move(" e2e4\nponder e7e5"), None);
+        assert_eq!(comm::parse_ponder_move(" e2e4 ponder (none)"), None);
+        assert_eq!(comm::parse_ponder_move(" e2e4 ponder"), None);
+    }
+
+    #[test]
+    fn read_the_move_at_the_end_of_the_output() {
+        let output = "info depth 1 score cp 20 pv e2e4\nbestmove e2e4";
+        let ind = output.rfind("bestmove").unwrap();
+        assert_eq!(comm::leading_move(&output[(ind+8)..]), "e2e4");
+        assert_eq!(comm::leading_move(" e7e8q ponder a2a3\n"), "e7e8q");
+        assert_eq!(comm::leading_move(" g1f3\0\0"), "g1f3");
+        assert_eq!(comm::leading_move(""), "");
+    }
+
+    #[test]
+    fn parse_option_lines() {
+        let option = |line: &str| comm::parse_option_line(line).map(|option| (option.name, option.kind));
+        assert_eq!(
+            option("option name Hash type spin default 16 min 1 max 33554432"),
+            Some(("Hash".to_string(), UciOptionKind::Spin { default: 16, min: 1, max: 33554432 }))
+        );
+        assert_eq!(
+            option("\0option name Skill Level type spin default 20 min 0 max 20\r"),
+            Some(("Skill Level".to_string(), UciOptionKind::Spin { default: 20, min: 0, max: 20 }))
+        );
+        assert_eq!(option("option name Ponder type check default false").unwrap().1, UciOptionKind::Check { default: false });
+        assert_eq!(
+            option("option name SyzygyPath type string default <empty>").unwrap().1,
+            UciOptionKind::String { default: "<empty>".to_string() }
+        );
+        assert_eq!(
+            option("option name Style type combo default Normal var Solid var Normal var Very Risky").unwrap().1,
+            UciOptionKind::Combo {
+                default: "Normal".to_string(),
+                vars: vec!["Solid".to_string(), "Normal".to_string(), "Very Risky".to_string()],
+            }
+        );
+        assert_eq!(option("option name Clear Hash type button").unwrap().1, UciOptionKind::Button);
+        assert_eq!(option("option name type check"), None);
+        assert_eq!(
+            option("option name Depth type spin default 16").unwrap().1,
+            UciOptionKind::Spin { default: 16, min: i64::MIN, max: i64::MAX }
+        );
+        assert_eq!(option("id name Hash"), None);
+    }
+
+    #[test]
+    fn check_option_values() {
+        let hash = comm::parse_option_line("option name Hash type spin default 16 min 1 max 1024").unwrap();
+        assert!(hash.check_value("128").is_ok());
+        assert!(hash.check_value("0").is_err() && hash.check_value("big").is_err());
+        let ponder = comm::parse_option_line("option name Ponder type check default false").unwrap();
+        assert!(ponder.check_value("true").is_ok() && ponder.check_value("yes").is_err());
+        let style = comm::parse_option_line("option name Style type combo default Normal var Solid var Normal").unwrap();
+        assert!(style.check_value("solid").is_ok() && style.check_value("Risky").is_err());
+    }
+
+    #[test]
+    fn parse_non_id_lines() {
+        assert_eq!(comm::parse_id_line("\0\0\0id name Leftover Padding"), Some(("name", "Leftover Padding")));
+        assert_eq!(comm::parse_id_line("option name Hash type spin default 16 min 1 max 33554432"), None);
+        assert_eq!(comm::parse_id_line("uciok"), None);
+        assert_eq!(comm::parse_id_line("idname Engine"), None);
+        assert_eq!(comm::parse_id_line(""), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn short_reads_have_no_padding() {
+        use std::process::{Command, Stdio};
+
+        let mut child = Command::new("sh").args(["-c", "echo readyok"]).stdout(Stdio::piped()).spawn().unwrap();
+        let mut stdout = child.stdout.take().unwrap();
+        let mut buf = String::new();
+        assert!(EngineComm::read(&mut stdout, &mut buf));
+        assert_eq!(buf, "readyok\n");
+        assert!(!EngineComm::read(&mut stdout, &mut buf));
+        let _ = child.wait();
+    }
+
+    // Writes a script that acts as an engine, which answers the commands matched by 'cases' (lines
+    // of a shell 'case') on top of the handshake
+    #[cfg(unix)]
+    fn script_engine(name: &str, cases: &str) -> String {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = std::env::temp_dir().join(format!("engine-gui-mock-{}-{}", std::process::id(), name));
+        let path = path.to_string_lossy().into_owned();
+        let script = format!(
+            "#!/bin/sh\n\
+             while read -r cmd; do\n\
+               case \"$cmd\" in\n\
+                 uci) echo 'id name {}'; echo 'uciok' ;;\n\
+                 isready) echo 'readyok' ;;\n\
+                 {}\n\
+                 quit) exit 0 ;;\n\
+               esac\n\
+             done\n",
+            name, cases
+        );
+        std::fs::write(&path, script).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn wait_for_whole_lines_and_skip_stopped_searches() {
+        use std::time::Duration;
+
+        // The first search only sends its move late once it's stopped, and the second one sends
+        // its move in two parts
+        let path = script_engine("Split", "stop) sleep 0.1; echo 'bestmove a2a3' ;;\n\
+            'go movetime 2') printf 'info depth 1 score cp 5 pv e2e4 e7e5\\nbestmove e2'; sleep 0.2; echo 'e4 ponder e7e5' ;;");
+        let mut engine = EngineComm::new(&path).unwrap();
+
+        engine.search_movetime(1);
+        engine.stop();
+        assert!(!engine.is_searching());
+        engine.search_movetime(2);
+        std::thread::sleep(Duration::from_millis(150));
+        assert_eq!(engine.best_move(Duration::ZERO), None);
+        assert_eq!(engine.best_move(Duration::from_secs(2)), Some(BestMove::Move("e2e4".to_string())));
+        assert_eq!(engine.ponder_move().as_deref(), Some("e7e5"));
+        assert_eq!(engine.last_eval(), Some(Score::Cp(5)));
+
+        drop(engine);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn read_the_move_sent_before_exiting() {
+        // The move isn't even a whole line, but the output ends right after it
+        let path = script_engine("Leaving", "go*) printf 'bestmove f2f3'; exit 0 ;;");
+        let mut engine = EngineComm::new(&path).unwrap();
+
+        engine.search_movetime(1);
+        while engine.is_alive() {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        engine.read_remaining_output();
+        assert_eq!(engine.poll_best_move(), Some(BestMove::Move("f2f3".to_string())));
+
+        drop(engine);
+        let _ = std::fs::remove_file(path);
     }
 }
@@ -0,0 +1,183 @@
+use std::fmt;
+
+// Settings that are remembered between launches. They are stored as 'key=value' lines.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    pub window_width: i32,
+    pub window_height: i32,
+    pub window_pos: Option<(i32, i32)>,
+    pub theme: String,
+    // Themes defined by the user, each as its name and the colors of the dark and light squares,
+    // e.g. 'ocean #8ca2ad #dee3e6'. Every one is on a line of its own.
+    pub custom_themes: Vec<String>,
+    pub piece_set: String,
+    pub movetime_ms: u64,
+    // An engine that hasn't sent its move after this many times the movetime forfeits
+    pub watchdog_factor: f32,
+    pub flip: bool,
+    // How the board is turned: 'fixed' (as set with 'flip'), always from 'white', or towards the
+    // 'side_to_move'
+    pub orientation: String,
+    // Where the coordinates are drawn: 'inside' the squares, 'outside' the board along the bottom
+    // and left edges, or outside along 'all' edges
+    pub coord_style: String,
+    // Time between moves when replaying a game, and whether the replay starts over at the end
+    pub replay_interval_ms: u64,
+    pub replay_loop: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            window_width: 1000,
+            window_height: 600,
+            window_pos: None,
+            theme: "green".to_string(),
+            custom_themes: Vec::new(),
+            piece_set: "chesscom".to_string(),
+            movetime_ms: 1000,
+            watchdog_factor: 2.0,
+            flip: false,
+            orientation: "fixed".to_string(),
+            coord_style: "inside".to_string(),
+            replay_interval_ms: 1000,
+            replay_loop: false,
+        }
+    }
+}
+
+pub const CONFIG_FILE: &str = "engine-gui.cfg";
+
+impl Config {
+    // A missing file gives the default settings
+    pub fn load(path: &str) -> Self {
+        match std::fs::read_to_string(path) {
+            Ok(content) => Self::parse(&content),
+            Err(_) => Self::default(),
+        }
+    }
+
+    // Settings that are missing or can't be read keep their default value
+    pub fn parse(content: &str) -> Self {
+        let mut config = Self::default();
+        let (mut window_x, mut window_y) = (None, None);
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') { continue; }
+            let (key, value) = if let Some(kv) = line.split_once('=') { kv } else {
+                eprintln!("[WARN] Ignoring invalid line in config: '{}'", line);
+                continue;
+            };
+            let (key, value) = (key.trim(), value.trim());
+            let is_valid = match key {
+                "window_width" => parse_into(value, &mut config.window_width),
+                "window_height" => parse_into(value, &mut config.window_height),
+                "window_x" => value.parse().map(|x| window_x = Some(x)).is_ok(),
+                "window_y" => value.parse().map(|y| window_y = Some(y)).is_ok(),
+                "theme" => { config.theme = value.to_string(); true },
+                "custom_theme" => { config.custom_themes.push(value.to_string()); true },
+                "piece_set" => { config.piece_set = value.to_string(); true },
+                "movetime_ms" => match value.parse() {
+                    Ok(ms) if ms > 0 => { config.movetime_ms = ms; true },
+                    _ => false,
+                },
+                "watchdog_factor" => match value.parse::<f32>() {
+                    Ok(factor) if factor > 1.0 && factor.is_finite() => { config.watchdog_factor = factor; true },
+                    _ => false,
+                },
+                "flip" => parse_into(value, &mut config.flip),
+                "orientation" => { config.orientation = value.to_string(); true },
+                "coord_style" => { config.coord_style = value.to_string(); true },
+                "replay_interval_ms" => match value.parse() {
+                    Ok(ms) if ms > 0 => { config.replay_interval_ms = ms; true },
+                    _ => false,
+                },
+                "replay_loop" => parse_into(value, &mut config.replay_loop),
+                _ => true,
+            };
+            if !is_valid {
+                eprintln!("[WARN] Invalid value for '{}' in config: '{}'", key, value);
+            }
+        }
+        if let (Some(x), Some(y)) = (window_x, window_y) {
+            config.window_pos = Some((x, y));
+        }
+        config
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        std::fs::write(path, self.to_string())
+            .map_err(|e| format!("Couldn't save config to '{}': {}", path, e))
+    }
+}
+
+impl fmt::Display for Config {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "window_width={}", self.window_width)?;
+        writeln!(f, "window_height={}", self.window_height)?;
+        if let Some((x, y)) = self.window_pos {
+            writeln!(f, "window_x={}", x)?;
+            writeln!(f, "window_y={}", y)?;
+        }
+        writeln!(f, "theme={}", self.theme)?;
+        for theme in &self.custom_themes {
+            writeln!(f, "custom_theme={}", theme)?;
+        }
+        writeln!(f, "piece_set={}", self.piece_set)?;
+        writeln!(f, "movetime_ms={}", self.movetime_ms)?;
+        writeln!(f, "watchdog_factor={}", self.watchdog_factor)?;
+        writeln!(f, "flip={}", self.flip)?;
+        writeln!(f, "orientation={}", self.orientation)?;
+        writeln!(f, "coord_style={}", self.coord_style)?;
+        writeln!(f, "replay_interval_ms={}", self.replay_interval_ms)?;
+        writeln!(f, "replay_loop={}", self.replay_loop)?;
+        Ok(())
+    }
+}
+
+// Only overwrites 'value' if 'text' could be parsed
+fn parse_into<T: std::str::FromStr>(text: &str, value: &mut T) -> bool {
+    match text.parse() {
+        Ok(parsed) => { *value = parsed; true },
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+
+    #[test]
+    fn config_round_trip() {
+        let config = Config {
+            window_width: 1280,
+            window_height: 720,
+            window_pos: Some((40, -20)),
+            theme: "ocean".to_string(),
+            custom_themes: vec!["ocean #8ca2ad #dee3e6".to_string(), "sand #c8a064 #f0e0c0".to_string()],
+            piece_set: "cburnett".to_string(),
+            movetime_ms: 250,
+            watchdog_factor: 1.5,
+            flip: true,
+            orientation: "side_to_move".to_string(),
+            coord_style: "all".to_string(),
+            replay_interval_ms: 1500,
+            replay_loop: true,
+        };
+        assert_eq!(Config::parse(&config.to_string()), config);
+    }
+
+    #[test]
+    fn corrupt_config_falls_back_to_defaults() {
+        let config = Config::parse("window_width=wide\nflip=maybe\nmovetime_ms=0\nwatchdog_factor=1\nnot a setting\n\0\0\nwindow_x=10\nwindow_height=800\n");
+        let default = Config::default();
+        assert_eq!(config.window_width, default.window_width);
+        assert_eq!(config.flip, default.flip);
+        assert_eq!(config.movetime_ms, default.movetime_ms);
+        assert_eq!(config.watchdog_factor, default.watchdog_factor);
+        // A position needs both coordinates
+        assert_eq!(config.window_pos, None);
+        assert_eq!(config.window_height, 800);
+        assert_eq!(Config::parse(""), default);
+    }
+}
@@ -0,0 +1,117 @@
+use chess::attack::AttackInfo;
+use chess::board::Board;
+use chess::fen;
+use chess::moves::{self, MoveFlag};
+use chess::zobrist::ZobristInfo;
+
+use std::collections::HashMap;
+
+// Name of the table in the assets directory
+pub const ECO_FILE: &str = "eco.tsv";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Opening {
+    pub eco: String,
+    pub name: String,
+}
+
+// Openings keyed by the position they lead to, so that a game which transposes into a known line
+// is still recognized
+#[derive(Default)]
+pub struct EcoTable {
+    positions: HashMap<String, Opening>,
+}
+
+// Placement of the pieces, side to move and castling rights; the move counters and enpassant
+// square would keep transpositions from matching
+fn position_key(board: &Board) -> String {
+    let fen = fen::gen_fen(board);
+    fen.split_ascii_whitespace().take(3).collect::<Vec<_>>().join(" ")
+}
+
+impl EcoTable {
+    pub fn load(path: &str, attack_info: &AttackInfo, zobrist_info: &ZobristInfo) -> Result<Self, String> {
+        match std::fs::read_to_string(path) {
+            Ok(content) => Ok(Self::parse(&content, attack_info, zobrist_info)),
+            Err(e) => Err(format!("Couldn't load openings from '{}': {}", path, e)),
+        }
+    }
+
+    // Each line has the ECO code, the name of the opening and its moves in UCI notation, separated
+    // by tabs. Lines that can't be read are skipped.
+    pub fn parse(content: &str, attack_info: &AttackInfo, zobrist_info: &ZobristInfo) -> Self {
+        let mut table = Self::default();
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') { continue; }
+            let fields: Vec<&str> = line.split('\t').collect();
+            if fields.len() != 3 {
+                eprintln!("[WARN] Ignoring invalid line in ECO table: '{}'", line);
+                continue;
+            }
+            let mut board = Board::from_fen(fen::FEN_POSITIONS[1], zobrist_info);
+            let mut is_valid = true;
+            for uci in fields[2].split_ascii_whitespace() {
+                let mv = moves::from_uci(uci, &board, attack_info);
+                if !mv.map_or(false, |mv| moves::make(&mut board, attack_info, zobrist_info, mv, MoveFlag::AllMoves)) {
+                    is_valid = false;
+                    break;
+                }
+            }
+            if !is_valid {
+                eprintln!("[WARN] Ignoring opening with illegal moves: '{}'", line);
+                continue;
+            }
+            let opening = Opening { eco: fields[0].to_string(), name: fields[1].to_string() };
+            table.positions.insert(position_key(&board), opening);
+        }
+        table
+    }
+
+    pub fn find(&self, board: &Board) -> Option<&Opening> {
+        self.positions.get(&position_key(board))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chess::attack::{self, AttackInfo};
+    use chess::board::Board;
+    use chess::fen;
+    use chess::moves::{self, MoveFlag};
+    use chess::zobrist::{self, ZobristInfo};
+
+    use crate::eco::EcoTable;
+
+    const TABLE: &str = "# comment\n\
+        B20\tSicilian Defence\te2e4 c7c5\n\
+        A50\tQueen's Pawn Game\td2d4 g8f6 c2c4\n\
+        E20\tNimzo-Indian Defence\td2d4 g8f6 c2c4 e7e6 b1c3 f8b4\n\
+        X00\tBroken\te2e5\n\
+        not enough fields\n";
+
+    fn play(moves_str: &str, attack_info: &AttackInfo, zobrist_info: &ZobristInfo) -> Board {
+        let mut board = Board::from_fen(fen::FEN_POSITIONS[1], zobrist_info);
+        for uci in moves_str.split_ascii_whitespace() {
+            let mv = moves::from_uci(uci, &board, attack_info).unwrap();
+            assert!(moves::make(&mut board, attack_info, zobrist_info, mv, MoveFlag::AllMoves));
+        }
+        board
+    }
+
+    #[test]
+    fn find_openings_by_position() {
+        let attack_info = attack::shared_attack_info();
+        let zobrist_info = zobrist::shared_zobrist_info();
+        let table = EcoTable::parse(TABLE, attack_info, zobrist_info);
+        assert_eq!(table.positions.len(), 3);
+
+        let board = play("e2e4 c7c5", attack_info, zobrist_info);
+        assert_eq!(table.find(&board).unwrap().eco, "B20");
+        // Reached with a different move order
+        let board = play("c2c4 e7e6 d2d4 g8f6 b1c3 f8b4", attack_info, zobrist_info);
+        assert_eq!(table.find(&board).unwrap().name, "Nimzo-Indian Defence");
+        let board = play("e2e4 e7e5", attack_info, zobrist_info);
+        assert_eq!(table.find(&board), None);
+    }
+}
@@ -0,0 +1,86 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+// Why the program couldn't start or keep running a match, in the GUI or headless. Each cause has
+// its own variant, so that it can be told apart and explained with what to do about it.
+#[derive(Debug)]
+pub enum GuiError {
+    // An engine couldn't be started or didn't accept its options
+    EngineInit(String),
+    // A file that is needed to open the window, e.g. the pieces or a font
+    AssetLoad { path: String, reason: String },
+    // A file that couldn't be read or written, e.g. the list of positions or the saved match
+    Io { path: String, source: io::Error },
+    // A position that can't be played from
+    Fen { fen: String, reason: String },
+    // An opening book that can't be played from
+    Book { path: String, reason: String },
+    // The saved match can't be continued
+    Resume(String),
+}
+
+impl GuiError {
+    // For the 'map_err' of a call that reads or writes 'path'
+    pub fn io(path: &str) -> impl FnOnce(io::Error) -> GuiError + '_ {
+        move |source| GuiError::Io { path: path.to_string(), source }
+    }
+
+    // For the 'map_err' of a raylib load call, whose errors are only a message
+    pub fn asset(path: &str) -> impl FnOnce(String) -> GuiError + '_ {
+        move |reason| GuiError::AssetLoad { path: path.to_string(), reason }
+    }
+
+    // What can be done about the error, if there's anything
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            GuiError::EngineInit(_) => Some("Check the path of the engine, and that it speaks UCI and has the options that were given"),
+            GuiError::AssetLoad { .. } => Some("Set the assets directory with '--assets'"),
+            GuiError::Io { .. } => None,
+            GuiError::Fen { .. } => Some("Fix or remove the position"),
+            GuiError::Book { .. } => Some("Pick another opening book, or play from the list of positions without '--book'"),
+            GuiError::Resume(_) => Some("Start without '--resume' to play a new match"),
+        }
+    }
+}
+
+impl fmt::Display for GuiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GuiError::EngineInit(reason) => write!(f, "{}", reason),
+            GuiError::AssetLoad { path, reason } => write!(f, "Couldn't load the asset '{}': {}", path, reason),
+            GuiError::Io { path, source } => write!(f, "Couldn't access '{}': {}", path, source),
+            GuiError::Fen { fen, reason } => write!(f, "Invalid position '{}': {}", fen, reason),
+            GuiError::Book { path, reason } => write!(f, "Invalid opening book '{}': {}", path, reason),
+            GuiError::Resume(reason) => write!(f, "Couldn't continue the saved match: {}", reason),
+        }
+    }
+}
+
+impl Error for GuiError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            GuiError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::GuiError;
+
+    use std::error::Error;
+    use std::io;
+
+    #[test]
+    fn errors_keep_their_cause() {
+        let e = GuiError::io("fens.txt")(io::Error::new(io::ErrorKind::NotFound, "not found"));
+        assert_eq!(e.to_string(), "Couldn't access 'fens.txt': not found");
+        assert!(e.source().is_some() && e.hint().is_none());
+
+        let e = GuiError::asset("assets/pieces.png")("bad format".to_string());
+        assert!(matches!(&e, GuiError::AssetLoad { path, .. } if path == "assets/pieces.png"));
+        assert!(e.source().is_none() && e.hint().unwrap().contains("--assets"));
+    }
+}
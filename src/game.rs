@@ -1,13 +1,16 @@
 use chess::attack::AttackInfo;
 use chess::bb::BBUtil;
 use chess::board::Board;
-use chess::consts::PieceColor;
+use chess::consts::{Piece, PieceColor};
 use chess::fen;
-use chess::moves::{self, Move, MoveFlag, MoveUtil};
+use chess::moves::{self, Move, MoveFlag};
 use chess::move_gen::{self, MoveList};
 use chess::zobrist::ZobristInfo;
 use chess::{COL, ROW};
 
+use crate::clock::TimeControl;
+use crate::comm::SearchInfo;
+use crate::eco::Opening;
 use crate::pgn;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -19,42 +22,145 @@ pub enum GameState {
     DarkLostOnTime,
     LightIllegalMove,
     DarkIllegalMove,
+    // The engine stopped answering, e.g. because it crashed or hung
+    LightNoResponse,
+    DarkNoResponse,
+    // The engines' scores called the game before it was played out
+    LightWinByAdjudication,
+    DarkWinByAdjudication,
     DrawByStalemate,
     DrawByFiftyMoveRule,
     DrawByThreefoldRepetition,
     DrawByInsufficientMaterial,
+    DrawByAdjudication,
 }
+
+impl GameState {
+    // Every way a game can be drawn
+    pub const DRAWS: [GameState; 5] = [
+        GameState::DrawByStalemate,
+        GameState::DrawByFiftyMoveRule,
+        GameState::DrawByThreefoldRepetition,
+        GameState::DrawByInsufficientMaterial,
+        GameState::DrawByAdjudication,
+    ];
+
+    // The color of the side that won the game, if the game is over and wasn't drawn
+    pub fn winner(&self) -> Option<PieceColor> {
+        match self {
+            GameState::LightWinByCheckmate | GameState::DarkLostOnTime | GameState::DarkIllegalMove
+                | GameState::DarkNoResponse | GameState::LightWinByAdjudication => Some(PieceColor::Light),
+            GameState::DarkWinByCheckmate | GameState::LightLostOnTime | GameState::LightIllegalMove
+                | GameState::LightNoResponse | GameState::DarkWinByAdjudication => Some(PieceColor::Dark),
+            _ => None,
+        }
+    }
+
+    pub fn is_draw(&self) -> bool {
+        *self != GameState::Ongoing && self.winner().is_none()
+    }
+
+    // Explains how the game ended in words
+    pub fn description(&self) -> &'static str {
+        match self {
+            GameState::Ongoing => "Game in progress",
+            GameState::LightWinByCheckmate => "White wins by checkmate",
+            GameState::DarkWinByCheckmate => "Black wins by checkmate",
+            GameState::LightLostOnTime => "White lost on time",
+            GameState::DarkLostOnTime => "Black lost on time",
+            GameState::LightIllegalMove => "White forfeits by an illegal move",
+            GameState::DarkIllegalMove => "Black forfeits by an illegal move",
+            GameState::LightNoResponse => "White forfeits by not sending a move",
+            GameState::DarkNoResponse => "Black forfeits by not sending a move",
+            GameState::LightWinByAdjudication => "White wins by adjudication",
+            GameState::DarkWinByAdjudication => "Black wins by adjudication",
+            GameState::DrawByStalemate => "Draw by stalemate",
+            GameState::DrawByFiftyMoveRule => "Draw by the fifty-move rule",
+            GameState::DrawByThreefoldRepetition => "Draw by threefold repetition",
+            GameState::DrawByInsufficientMaterial => "Draw by insufficient material",
+            GameState::DrawByAdjudication => "Draw by adjudication",
+        }
+    }
+}
+
+// Whether a move gave check, and whether that check was mate
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct GivenCheck {
+    check: bool,
+    mate: bool,
+}
+
 pub struct Game {
     start_fen: String,
     state: GameState,
+    // The position before each move and after the last one, so there's always one more board than
+    // there are moves. The vectors of moves only change through 'push_move' and 'set_start_pos',
+    // which keep them the same length.
     boards: Vec<Board>,
     moves: Vec<Move>,
+    // Score the engine reported for each move, from its own point of view
+    scores: Vec<Option<String>>,
+    // What the engine reported about the search for each move, e.g. how deep it got
+    searches: Vec<Option<SearchInfo>>,
+    // Worked out once for each move, so the PGN and the board agree on which moves were checks
+    checks: Vec<GivenCheck>,
     white_name: String,
-    black_name: String
+    black_name: String,
+    // Deepest known opening that the game went through
+    opening: Option<Opening>,
+    // Result of a game loaded from a PGN, when its moves alone don't decide it (e.g. a resignation)
+    loaded_result: Option<String>,
+    time_control: Option<TimeControl>,
 }
 
 impl Game {
-    pub fn new(white_name: &str, black_name: &str, zobrist_info: &ZobristInfo) -> Self {
-        Self::from_fen(white_name, black_name, fen::FEN_POSITIONS[1], zobrist_info)
+    pub fn new(white_name: &str, black_name: &str, attack_info: &AttackInfo, zobrist_info: &ZobristInfo) -> Self {
+        Self::from_fen(white_name, black_name, fen::FEN_POSITIONS[1], attack_info, zobrist_info)
     }
 
-    pub fn from_fen(white_name: &str, black_name: &str, fen: &str, zobrist_info: &ZobristInfo) -> Self {
+    // The position can already be the end of the game, e.g. a checkmate, in which case the game
+    // starts out finished
+    pub fn from_fen(
+        white_name: &str, black_name: &str, fen: &str, attack_info: &AttackInfo, zobrist_info: &ZobristInfo
+    ) -> Self {
         let board = Board::from_fen(fen, zobrist_info);
+        let state = Self::start_state(&board, attack_info, zobrist_info);
         Self {
             start_fen: fen.to_string(),
-            state: GameState::Ongoing,
+            state,
             boards: vec![board],
             moves: vec![],
+            scores: vec![],
+            searches: vec![],
+            checks: vec![],
             white_name: white_name.to_string(),
-            black_name: black_name.to_string()
+            black_name: black_name.to_string(),
+            opening: None,
+            loaded_result: None,
+            time_control: None,
         }
     }
 
-    pub fn set_start_pos(&mut self, fen: &str, zobrist_info: &ZobristInfo) {
+    // Drops every move that was played, along with what is known about them
+    pub fn set_start_pos(&mut self, fen: &str, attack_info: &AttackInfo, zobrist_info: &ZobristInfo) {
         self.start_fen = fen.to_string();
-        self.boards.clear();
         let board = Board::from_fen(fen, zobrist_info);
-        self.boards.push(board);
+        self.state = Self::start_state(&board, attack_info, zobrist_info);
+        self.boards = vec![board];
+        self.moves.clear();
+        self.scores.clear();
+        self.searches.clear();
+        self.checks.clear();
+    }
+
+    // Whether every move has a board before and after it, and a place for its score, search and
+    // check. The game relies on this, so it's only checked in debug builds and tests.
+    pub fn is_consistent(&self) -> bool {
+        let count = self.moves.len();
+        self.boards.len() == count + 1
+            && self.scores.len() == count
+            && self.searches.len() == count
+            && self.checks.len() == count
     }
 
     pub fn is_ongoing(&self) -> bool {
@@ -78,10 +184,64 @@ impl Game {
         }
     }
 
+    pub fn illegal_move(&mut self, is_white: bool) {
+        if is_white {
+            self.state = GameState::LightIllegalMove;
+        } else {
+            self.state = GameState::DarkIllegalMove;
+        }
+    }
+
+    pub fn no_response(&mut self, is_white: bool) {
+        if is_white {
+            self.state = GameState::LightNoResponse;
+        } else {
+            self.state = GameState::DarkNoResponse;
+        }
+    }
+
+    // Ends the game with the result the engines' scores agree on, without playing it out
+    pub fn adjudicate(&mut self, winner: Option<PieceColor>) {
+        self.state = match winner {
+            Some(PieceColor::Light) => GameState::LightWinByAdjudication,
+            Some(PieceColor::Dark) => GameState::DarkWinByAdjudication,
+            _ => GameState::DrawByAdjudication,
+        };
+    }
+
     pub fn black_name(&self) -> &String {
         &self.black_name
     }
 
+    pub fn set_names(&mut self, white_name: &str, black_name: &str) {
+        self.white_name = white_name.to_string();
+        self.black_name = black_name.to_string();
+    }
+
+    pub fn opening(&self) -> Option<&Opening> {
+        self.opening.as_ref()
+    }
+
+    pub fn set_opening(&mut self, opening: Opening) {
+        self.opening = Some(opening);
+    }
+
+    pub fn loaded_result(&self) -> Option<&String> {
+        self.loaded_result.as_ref()
+    }
+
+    pub fn set_loaded_result(&mut self, result: &str) {
+        self.loaded_result = Some(result.to_string());
+    }
+
+    pub fn time_control(&self) -> Option<TimeControl> {
+        self.time_control
+    }
+
+    pub fn set_time_control(&mut self, time_control: TimeControl) {
+        self.time_control = Some(time_control);
+    }
+
     pub fn state(&self) -> GameState {
         self.state
     }
@@ -91,10 +251,24 @@ impl Game {
     }
 
     pub fn move_count(&self) -> usize {
-        assert!(self.moves.len() == self.boards.len() - 1);
+        debug_assert!(self.is_consistent());
         self.moves.len()
     }
 
+    // Number of times the position at 'ind' has occurred so far in the game, counting itself
+    pub fn position_count(&self, ind: usize) -> usize {
+        if let Some(board) = self.boards.get(ind) {
+            occurrences(board, &self.boards[..=ind])
+        } else {
+            0
+        }
+    }
+
+    // Number of times the current position has occurred, where 3 is a draw
+    pub fn repetition_count(&self) -> usize {
+        self.position_count(self.boards.len() - 1)
+    }
+
     pub fn current_fen(&self) -> String {
         if let Some(recent) = self.boards.last() {
             fen::gen_fen(recent)
@@ -103,6 +277,34 @@ impl Game {
         }
     }
 
+    pub fn set_last_score(&mut self, score: Option<String>) {
+        if let Some(last) = self.scores.last_mut() {
+            *last = score;
+        }
+    }
+
+    pub fn score_at(&self, ind: usize) -> Option<&String> {
+        self.scores.get(ind)?.as_ref()
+    }
+
+    pub fn set_last_search(&mut self, info: SearchInfo) {
+        if let Some(last) = self.searches.last_mut() {
+            *last = Some(info);
+        }
+    }
+
+    pub fn search_at(&self, ind: usize) -> Option<&SearchInfo> {
+        self.searches.get(ind)?.as_ref()
+    }
+
+    pub fn gave_check(&self, move_ind: usize) -> bool {
+        self.checks.get(move_ind).map_or(false, |given| given.check)
+    }
+
+    pub fn gave_checkmate(&self, move_ind: usize) -> bool {
+        self.checks.get(move_ind).map_or(false, |given| given.mate)
+    }
+
     pub fn move_at(&self, ind: usize) -> Option<&Move> {
         self.moves.get(ind)
     }
@@ -115,6 +317,11 @@ impl Game {
         self.moves.first()
     }
 
+    // Position after 'ind' moves, the first one being the starting position
+    pub fn board_at(&self, ind: usize) -> Option<&Board> {
+        self.boards.get(ind)
+    }
+
     pub fn board_before_move(&self, move_ind: usize) -> Option<&Board> {
         self.boards.get(move_ind)
     }
@@ -135,20 +342,42 @@ impl Game {
         self.boards.last()
     }
 
-    pub fn save(&self, filename: Option<String>, attack_info: &AttackInfo) -> bool {
+    pub fn save(&self, filename: Option<String>, attack_info: &AttackInfo, zobrist_info: &ZobristInfo) -> bool {
         let name;
         if let None = filename {
             name = format!("{}_vs_{}.pgn", self.white_name, self.black_name);
         } else { 
             name = filename.unwrap();
         };
-        let is_saved = pgn::save(&name, &self, &attack_info).is_err();
+        let is_saved = pgn::save(&name, self, attack_info, zobrist_info).is_ok();
         if !is_saved {
             eprintln!("[ERROR] Couldn't save game to file '{}'", name);
         }
         is_saved
     }
 
+    pub fn append_to(&self, filename: &str, attack_info: &AttackInfo, zobrist_info: &ZobristInfo) -> bool {
+        let is_saved = pgn::append(filename, self, attack_info, zobrist_info).is_ok();
+        if !is_saved {
+            eprintln!("[ERROR] Couldn't add game to file '{}'", filename);
+        }
+        is_saved
+    }
+
+    // Legal moves of the current position in UCI notation, e.g. "e7e8q"
+    pub fn legal_moves_uci(&self, attack_info: &AttackInfo, zobrist_info: &ZobristInfo) -> Vec<String> {
+        let board = self.boards.last().unwrap();
+        legal_moves(board, attack_info, zobrist_info).iter().map(|&mv| moves::to_uci(mv)).collect()
+    }
+
+    // Legal moves of the current position in SAN, e.g. "Nbd2" or "Qh4#"
+    pub fn legal_moves_san(&self, attack_info: &AttackInfo, zobrist_info: &ZobristInfo) -> Vec<String> {
+        let board = self.boards.last().unwrap();
+        legal_moves(board, attack_info, zobrist_info).iter()
+            .map(|&mv| pgn::move_to_san(mv, board, attack_info, zobrist_info))
+            .collect()
+    }
+
     // The returned boolean value tells whether or not the inputted move has been made successfully
     pub fn make_move(&mut self, mv: Move, attack_info: &AttackInfo, zobrist_info: &ZobristInfo) -> bool {
         let current = if let Some(b) = self.boards.last() { b } else {
@@ -161,23 +390,35 @@ impl Game {
 
         if moves::make(&mut next_board, attack_info, zobrist_info, mv, MoveFlag::AllMoves) {
             is_legal = true;
-            self.moves.push(mv);
-            self.state = Self::set_state(attack_info, zobrist_info, &next_board, &self.boards);
-            self.boards.push(next_board);
+            let check = next_board.is_in_check(attack_info, next_board.state.xside);
+            let no_legal_moves = legal_moves(&next_board, attack_info, zobrist_info).is_empty();
+            self.state = Self::set_state(&next_board, &self.boards, check, no_legal_moves);
+            self.push_move(mv, next_board, GivenCheck { check, mate: check && no_legal_moves });
         } else {
             is_legal = false;
-            eprintln!("[WARN] Illegal move! {}", mv.to_str().trim());
+            eprintln!("[WARN] Illegal move! {}", moves::to_uci(mv));
         }
         is_legal
     }
 
-    fn set_state(attack_info: &AttackInfo, zobrist_info: &ZobristInfo, current: &Board, boards: &[Board]) -> GameState {
+    fn push_move(&mut self, mv: Move, next_board: Board, given: GivenCheck) {
+        self.moves.push(mv);
+        self.scores.push(None);
+        self.searches.push(None);
+        self.checks.push(given);
+        self.boards.push(next_board);
+    }
+
+    fn start_state(board: &Board, attack_info: &AttackInfo, zobrist_info: &ZobristInfo) -> GameState {
+        let check = board.is_in_check(attack_info, board.state.xside);
+        let no_legal_moves = legal_moves(board, attack_info, zobrist_info).is_empty();
+        Self::set_state(board, &[], check, no_legal_moves)
+    }
+
+    // 'check' and 'no_legal_moves' describe the side to move in 'current'
+    fn set_state(current: &Board, boards: &[Board], check: bool, no_legal_moves: bool) -> GameState {
         // Check for draw by fifty move rule
-        //   - units[0] -> all the white pieces
-        //   - units[1] -> all the black pieces
-        //   - Since kings can't be captured, if both sides only have one piece
-        //     then that means that only kings are left on the board
-        if current.state.half_moves >= 100 {
+        if current.halfmove_clock() >= 100 {
             return GameState::DrawByFiftyMoveRule;
         }
         // Check for draw by insufficient material
@@ -186,19 +427,9 @@ impl Game {
         }
 
         // Check for draw by checkmate or stalemate
-        let board = &mut current.clone();
-        let mut ml = MoveList::new();
-        move_gen::generate_all(board, attack_info, &mut ml);
-        // Remove illegal moves from the move list
-        for i in (0..ml.moves.len()).rev() {
-            let clone = board.clone();
-            if !moves::make(board, attack_info, zobrist_info, ml.moves[i], MoveFlag::AllMoves) {
-                ml.moves.remove(i);
-            }
-            *board = clone;
-        }
-        if ml.moves.len() == 0 {
-            if board.is_in_check(attack_info, board.state.xside) {
+        let board = current;
+        if no_legal_moves {
+            if check {
                 if board.state.xside == PieceColor::Light {
                     return GameState::LightWinByCheckmate;
                 } else {
@@ -209,54 +440,191 @@ impl Game {
             }
         }
 
-        // Check for draw by three fold repetition
-        let mut repetition_count = 0;
-        let (curr_key, curr_lock) = (board.state.key, board.state.lock);
-        for b in boards {
-            if curr_key == b.state.key && curr_lock == b.state.lock {
-                repetition_count += 1;
-                if repetition_count == 3 {
-                    return GameState::DrawByThreefoldRepetition;
-                }
-            }
+        // Check for draw by three fold repetition, where 'boards' doesn't have the current
+        // position yet
+        if occurrences(board, boards) + 1 >= 3 {
+            return GameState::DrawByThreefoldRepetition;
         }
 
         GameState::Ongoing
     }
 }
 
+// Number of times the position of 'board' appears in 'boards'
+fn occurrences(board: &Board, boards: &[Board]) -> usize {
+    let (key, lock) = (board.state.key, board.state.lock);
+    boards.iter().filter(|b| b.state.key == key && b.state.lock == lock).count()
+}
+
+// Moves of the side to move that don't leave its own king in check
+pub fn legal_moves(board: &Board, attack_info: &AttackInfo, zobrist_info: &ZobristInfo) -> Vec<Move> {
+    let mut ml = MoveList::new();
+    move_gen::generate_all(board, attack_info, &mut ml);
+    ml.moves.retain(|&mv| {
+        let mut clone = board.clone();
+        moves::make(&mut clone, attack_info, zobrist_info, mv, MoveFlag::AllMoves)
+    });
+    ml.moves
+}
+
+// Only positions where neither side can ever mate, whatever the moves, are drawn. Anything with
+// a chance of mate, however unlikely (e.g. KN vs kn, where a knight can block its own king in), is
+// played on.
 fn insufficient_material(b: &Board) -> bool {
-    if b.pos.units[0].count_ones() == 1 && b.pos.units[1].count_ones() == 1 {
+    // Kings can't be captured, so a side with only one piece has nothing but its king left
+    match (b.count_color(PieceColor::Light), b.count_color(PieceColor::Dark)) {
+        // K vs k
+        (1, 1) => true,
+        // KN vs k and KB vs k
+        (2, 1) => b.count(Piece::LN) == 1 || b.count(Piece::LB) == 1,
+        // K vs kn and K vs kb
+        (1, 2) => b.count(Piece::DN) == 1 || b.count(Piece::DB) == 1,
+        // KB vs kb is only a draw if both bishops are on the same color of squares
+        (2, 2) if b.count(Piece::LB) == 1 && b.count(Piece::DB) == 1 => {
+            let white_bishop = b.pos.piece[Piece::LB as usize].lsb();
+            let black_bishop = b.pos.piece[Piece::DB as usize].lsb();
+            (ROW!(white_bishop) + COL!(white_bishop)) % 2 == (ROW!(black_bishop) + COL!(black_bishop)) % 2
+        },
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chess::attack;
+    use chess::board::Board;
+    use chess::moves;
+    use chess::zobrist;
+
+    use crate::game::{insufficient_material, Game, GameState};
+
+    #[test]
+    fn list_legal_moves() {
+        let attack_info = attack::shared_attack_info();
+        let zobrist_info = zobrist::shared_zobrist_info();
+        let game = Game::new("White", "Black", attack_info, zobrist_info);
+        // Same as perft(1) of the starting position
+        let uci = game.legal_moves_uci(attack_info, zobrist_info);
+        let san = game.legal_moves_san(attack_info, zobrist_info);
+        assert_eq!(uci.len(), 20);
+        assert_eq!(san.len(), 20);
+        assert!(uci.contains(&"g1f3".to_string()));
+        assert!(san.contains(&"Nf3".to_string()) && san.contains(&"e4".to_string()));
+
+        // Both rooks can reach d1, and Re8 is a back-rank mate
+        let game = Game::from_fen("White", "Black", "6k1/5ppp/8/8/8/8/5PPP/R3R1K1 w - - 0 1", attack_info, zobrist_info);
+        let san = game.legal_moves_san(attack_info, zobrist_info);
+        assert!(san.contains(&"Rad1".to_string()) && san.contains(&"Red1".to_string()));
+        assert!(san.contains(&"Re8#".to_string()));
+    }
+
+    #[test]
+    fn insufficient_material_draws() {
+        let zobrist_info = zobrist::shared_zobrist_info();
+        let is_draw = |fen: &str| insufficient_material(&Board::from_fen(fen, zobrist_info));
         // K vs k
-        return true;
-    }
-    if (b.pos.units[0].count_ones() == 2 && b.pos.piece[1].count_ones() == 1 && b.pos.units[1].count_ones() == 1)
-        || (b.pos.units[1].count_ones() == 2 && b.pos.piece[7].count_ones() == 1 && b.pos.units[0].count_ones() == 1)
-    {
-        // (KN vs k) and (K vs kn)
-        return true;
-    }
-    if (b.pos.units[0].count_ones() == 2 && b.pos.piece[2].count_ones() == 1 && b.pos.units[1].count_ones() == 1)
-        || (b.pos.units[1].count_ones() == 2 && b.pos.piece[8].count_ones() == 1 && b.pos.units[0].count_ones() == 1)
-    {
-        // (KB vs k) and (K vs kb)
-        return true;
-    }
-    if b.pos.units[0].count_ones() == 2 && b.pos.piece[1].count_ones() == 1
-        && b.pos.units[1].count_ones() == 2 && b.pos.piece[7].count_ones() == 1 {
-        // KN vs kn
-        return true;
-    }
-    if b.pos.units[0].count_ones() == 2 && b.pos.piece[2].count_ones() == 1
-        && b.pos.units[1].count_ones() == 2 && b.pos.piece[8].count_ones() == 1 {
-        // KB vs kb
-        let white_bishop = b.pos.piece[2].lsb();
-        let (wr, wf) = (ROW!(white_bishop), COL!(white_bishop));
-        let black_bishop = b.pos.piece[8].lsb();
-        let (br, bf) = (ROW!(black_bishop), COL!(black_bishop));
-        // If both bishops are the same color and there are only 1 bishops per side,
-        // it's a draw due to insufficient material
-        return (wr + wf) % 2 == (br + bf) % 2;
-    }
-    false
+        assert!(is_draw("8/8/4k3/8/8/3K4/8/8 w - - 0 1"));
+        // KN vs k and K vs kn
+        assert!(is_draw("8/8/4k3/8/8/3K4/8/6N1 w - - 0 1"));
+        assert!(is_draw("8/8/4k3/8/8/3K4/8/6n1 w - - 0 1"));
+        // KB vs k and K vs kb
+        assert!(is_draw("8/8/4k3/8/8/3K4/8/5B2 w - - 0 1"));
+        assert!(is_draw("8/8/4k3/8/8/3K4/8/5b2 w - - 0 1"));
+        // KB vs kb with bishops on the same color, then on different colors
+        assert!(is_draw("8/8/4k3/3b4/8/3K4/8/5B2 w - - 0 1"));
+        assert!(!is_draw("8/8/4k3/2b5/8/3K4/8/5B2 w - - 0 1"));
+        // Enough material to mate
+        assert!(!is_draw("8/8/4k3/8/8/3K4/8/6R1 w - - 0 1"));
+        assert!(!is_draw("8/8/4k3/8/8/3K4/6P1/8 w - - 0 1"));
+        assert!(!is_draw("8/8/4k3/8/8/3K4/8/5BN1 w - - 0 1"));
+        assert!(!is_draw("8/8/4k3/8/8/3K4/8/4B1B1 w - - 0 1"));
+        // KN vs kn can end in mate
+        assert!(!is_draw("8/8/4k3/2n5/8/3K4/8/6N1 w - - 0 1"));
+        assert!(!is_draw("8/8/4k3/2n5/8/3K4/8/5B2 w - - 0 1"));
+    }
+
+    #[test]
+    fn winnable_endings_are_played_on() {
+        let attack_info = attack::shared_attack_info();
+        let zobrist_info = zobrist::shared_zobrist_info();
+        let state_after = |fen: &str, uci: &str| {
+            let mut game = Game::from_fen("White", "Black", fen, attack_info, zobrist_info);
+            let mv = moves::from_uci(uci, game.board_after_last_move().unwrap(), attack_info).unwrap();
+            assert!(game.make_move(mv, attack_info, zobrist_info));
+            game.state()
+        };
+        // KBN vs k
+        assert_eq!(state_after("8/8/4k3/8/8/3K4/8/5BN1 w - - 0 1", "d3d4"), GameState::Ongoing);
+        // Bishops on opposite colors, on the same side or on different sides
+        assert_eq!(state_after("8/8/4k3/8/8/3K4/8/4B1B1 w - - 0 1", "d3d4"), GameState::Ongoing);
+        assert_eq!(state_after("8/8/4k3/2b5/8/3K4/8/5B2 w - - 0 1", "d3c2"), GameState::Ongoing);
+        // Bishops on the same color can't mate
+        assert_eq!(state_after("8/8/4k3/3b4/8/3K4/8/5B2 w - - 0 1", "d3c3"), GameState::DrawByInsufficientMaterial);
+    }
+
+    #[test]
+    fn finished_positions_start_finished() {
+        let attack_info = attack::shared_attack_info();
+        let zobrist_info = zobrist::shared_zobrist_info();
+        let state_of = |fen: &str| Game::from_fen("White", "Black", fen, attack_info, zobrist_info).state();
+        // Fool's mate
+        assert_eq!(state_of("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3"), GameState::DarkWinByCheckmate);
+        assert_eq!(state_of("6k1/5ppp/8/8/8/8/5PPP/4R1K1 b - - 0 1"), GameState::Ongoing);
+        assert_eq!(state_of("4R1k1/5ppp/8/8/8/8/5PPP/6K1 b - - 0 1"), GameState::LightWinByCheckmate);
+        assert_eq!(state_of("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1"), GameState::DrawByStalemate);
+        assert_eq!(state_of("8/8/4k3/8/8/3K4/8/8 w - - 0 1"), GameState::DrawByInsufficientMaterial);
+        assert_eq!(state_of("8/8/4k3/8/8/3K4/8/6R1 w - - 100 80"), GameState::DrawByFiftyMoveRule);
+
+        let mut game = Game::new("White", "Black", attack_info, zobrist_info);
+        assert!(game.is_ongoing());
+        game.set_start_pos("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", attack_info, zobrist_info);
+        assert_eq!(game.state(), GameState::DrawByStalemate);
+    }
+
+    #[test]
+    fn count_repetitions() {
+        let attack_info = attack::shared_attack_info();
+        let zobrist_info = zobrist::shared_zobrist_info();
+        let mut game = Game::new("White", "Black", attack_info, zobrist_info);
+        assert_eq!(game.repetition_count(), 1);
+        let knight_moves = ["g1f3", "g8f6", "f3g1", "f6g8"];
+        for (i, uci) in knight_moves.iter().chain(knight_moves.iter()).enumerate() {
+            let mv = moves::from_uci(uci, game.board_after_last_move().unwrap(), attack_info).unwrap();
+            assert!(game.make_move(mv, attack_info, zobrist_info));
+            // Every position comes back 4 moves later
+            let expected = (i + 1) / 4 + 1;
+            assert_eq!(game.repetition_count(), expected, "{}", uci);
+            assert_eq!(game.is_ongoing(), expected < 3);
+        }
+        assert_eq!(game.state(), GameState::DrawByThreefoldRepetition);
+        // Earlier positions only count the times they occurred before
+        assert_eq!(game.position_count(0), 1);
+        assert_eq!(game.position_count(4), 2);
+        assert_eq!(game.position_count(5), 2);
+        assert_eq!(game.position_count(9), 0);
+    }
+
+    #[test]
+    fn moves_and_boards_stay_in_step() {
+        let attack_info = attack::shared_attack_info();
+        let zobrist_info = zobrist::shared_zobrist_info();
+        let mut game = Game::new("White", "Black", attack_info, zobrist_info);
+        assert!(game.is_consistent());
+        for uci in ["e2e4", "e7e5", "g1f3"] {
+            let mv = moves::from_uci(uci, game.board_after_last_move().unwrap(), attack_info).unwrap();
+            assert!(game.make_move(mv, attack_info, zobrist_info));
+            game.set_last_score(Some("cp 20".to_string()));
+        }
+        assert!(game.is_consistent());
+        assert_eq!(game.move_count(), 3);
+
+        // A new start position drops the moves, and their scores with them
+        game.set_start_pos("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1", attack_info, zobrist_info);
+        assert!(game.is_consistent());
+        assert_eq!(game.move_count(), 0);
+        assert_eq!(game.score_at(0), None);
+        let mv = moves::from_uci("e2e4", game.board_after_last_move().unwrap(), attack_info).unwrap();
+        assert!(game.make_move(mv, attack_info, zobrist_info));
+        assert!(game.is_consistent() && game.move_count() == 1 && game.score_at(0).is_none());
+    }
 }
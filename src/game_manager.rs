@@ -1,115 +1,1096 @@
 use chess::attack::AttackInfo;
-use chess::consts::{Piece, Sq};
-use chess::moves::Move;
-use chess::move_gen::{self, MoveList};
+use chess::fen;
+use chess::moves::{self, Move};
 use chess::zobrist::ZobristInfo;
 
-use crate::comm::EngineComm;
-use crate::game::Game;
+use chess::consts::PieceColor;
+
+use crate::clock::{ChessClock, RealTime, TimeControl, TimeSource};
+use crate::comm::{BestMove, EngineComm, Score, SearchInfo};
+use crate::eco::EcoTable;
+use crate::error::GuiError;
+use crate::game::{Game, GameState};
+use crate::match_state::MatchState;
+use crate::paths::Paths;
+use crate::pgn;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use std::thread;
+use std::time::Duration;
+
+// Results of a match, where wins are indexed by engine and draws by the way the game was drawn
+// (in the same order as 'GameState::DRAWS')
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Standings {
+    pub wins: [u32; 2],
+    pub draws: [u32; GameState::DRAWS.len()],
+    // Results of the rounds, i.e. of the two games played from each position, by the engine
+    // that scored more points in them
+    pub pair_wins: [u32; 2],
+    pub pair_draws: u32,
+}
+
+impl Standings {
+    pub fn games(&self) -> u32 {
+        self.wins[0] + self.wins[1] + self.draws()
+    }
+
+    pub fn draws(&self) -> u32 {
+        self.draws.iter().sum()
+    }
+
+    // Number of games that ended in 'state', which should be one of the draws
+    pub fn draws_by(&self, state: GameState) -> u32 {
+        GameState::DRAWS.iter().position(|&s| s == state).map_or(0, |i| self.draws[i])
+    }
+
+    fn add_draw(&mut self, state: GameState) {
+        let i = GameState::DRAWS.iter().position(|&s| s == state)
+            .expect("Every draw should be listed in 'GameState::DRAWS'");
+        self.draws[i] += 1;
+    }
+
+    pub fn score(&self, engine_index: usize) -> f32 {
+        self.wins[engine_index] as f32 + 0.5 * self.draws() as f32
+    }
+
+    pub fn pairs(&self) -> u32 {
+        self.pair_wins[0] + self.pair_wins[1] + self.pair_draws
+    }
+
+    // Records a round where the first engine scored 'points' out of 2
+    fn add_pair(&mut self, points: f32) {
+        if points > 1.0 {
+            self.pair_wins[0] += 1;
+        } else if points < 1.0 {
+            self.pair_wins[1] += 1;
+        } else {
+            self.pair_draws += 1;
+        }
+    }
+
+    // Elo difference of the first engine over the second one, along with the margin of its 95%
+    // confidence interval. There's no estimate while one of the engines has all the points.
+    pub fn elo_difference(&self) -> Option<(f32, f32)> {
+        let games = self.games() as f64;
+        let score = self.score(0) as f64 / games;
+        if games == 0.0 || score <= 0.0 || score >= 1.0 {
+            return None;
+        }
+        let (wins, losses, draws) = (self.wins[0] as f64 / games, self.wins[1] as f64 / games, self.draws() as f64 / games);
+        let variance = wins * (1.0 - score).powi(2) + losses * score.powi(2) + draws * (0.5 - score).powi(2);
+        let deviation = 1.96 * (variance / games).sqrt();
+        let elo = |score: f64| 400.0 * (score / (1.0 - score)).log10();
+        let low = elo((score - deviation).max(f64::EPSILON));
+        let high = elo((score + deviation).min(1.0 - f64::EPSILON));
+        Some((elo(score) as f32, ((high - low) / 2.0) as f32))
+    }
+
+    // Likelihood of superiority: the chance that the first engine is the stronger one, going by
+    // the decisive games alone. There's none while every game was drawn.
+    pub fn likelihood_of_superiority(&self) -> Option<f32> {
+        let (wins, losses) = (self.wins[0] as f64, self.wins[1] as f64);
+        if wins + losses == 0.0 {
+            return None;
+        }
+        Some((0.5 * (1.0 + erf((wins - losses) / (2.0 * (wins + losses)).sqrt()))) as f32)
+    }
+
+    // The Elo difference and LOS in one line, e.g. "Elo difference: +34.9 +/- 111.4, LOS: 73.6 %".
+    // When one engine has all the points, the difference is infinite.
+    pub fn elo_summary(&self) -> Option<String> {
+        if self.games() == 0 {
+            return None;
+        }
+        let mut summary = match self.elo_difference() {
+            Some((elo, margin)) => format!("Elo difference: {:+.1} +/- {:.1}", elo, margin),
+            None if self.score(0) > self.score(1) => "Elo difference: +inf".to_string(),
+            None => "Elo difference: -inf".to_string(),
+        };
+        if let Some(los) = self.likelihood_of_superiority() {
+            summary.push_str(&format!(", LOS: {:.1} %", 100.0 * los));
+        }
+        Some(summary)
+    }
+}
+
+// Error function, with an error below 1.5e-7 (Abramowitz and Stegun, 7.1.26)
+fn erf(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.3275911 * x.abs());
+    let poly = t * (0.254829592 + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    let y = 1.0 - poly * (-x * x).exp();
+    if x < 0.0 { -y } else { y }
+}
+
+// When a match is over, besides running out of positions to play from
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StopCondition {
+    pub max_games: Option<u32>,
+    // Rounds of two games from the same position, with the engines swapping colors
+    pub max_rounds: Option<u32>,
+    // Points one engine has to be ahead by to win the match
+    pub win_margin: Option<f32>,
+    // Test that ends the match once it can tell which of its hypotheses holds
+    pub sprt: Option<Sprt>,
+}
+
+impl StopCondition {
+    pub fn is_met(&self, standings: &Standings) -> bool {
+        let games_played = self.max_games.map_or(false, |n| standings.games() >= n);
+        let rounds_played = self.max_rounds.map_or(false, |n| standings.pairs() >= n);
+        let lead = (standings.score(0) - standings.score(1)).abs();
+        let decided = self.win_margin.map_or(false, |margin| lead >= margin);
+        let tested = self.sprt.map_or(false, |sprt| sprt.result(standings).is_some());
+        games_played || rounds_played || decided || tested
+    }
+}
+
+// Sequential probability ratio test of whether the first engine is 'elo0' (H0) or 'elo1' (H1)
+// Elo stronger than the second one, e.g. to decide whether a patch should be accepted. 'alpha'
+// and 'beta' are the chances of wrongly accepting H1 and H0.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sprt {
+    pub elo0: f32,
+    pub elo1: f32,
+    pub alpha: f32,
+    pub beta: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SprtResult {
+    H0Accepted,
+    H1Accepted,
+}
+
+impl Sprt {
+    const DEFAULT_ERROR: f32 = 0.05;
+
+    // Reads "<elo0>,<elo1>[,<alpha>,<beta>]", e.g. "0,5" or "0,5,0.05,0.1"
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let invalid = || format!("Invalid SPRT '{}', expected '<elo0>,<elo1>[,<alpha>,<beta>]'", s);
+        let values: Vec<f32> = s.split(',').map(|v| v.trim().parse().ok()).collect::<Option<_>>().ok_or_else(invalid)?;
+        let (elo0, elo1, alpha, beta) = match values[..] {
+            [elo0, elo1] => (elo0, elo1, Self::DEFAULT_ERROR, Self::DEFAULT_ERROR),
+            [elo0, elo1, alpha, beta] => (elo0, elo1, alpha, beta),
+            _ => return Err(invalid()),
+        };
+        if !(elo0.is_finite() && elo1.is_finite() && elo0 < elo1) {
+            return Err(format!("Invalid SPRT bounds: elo0 ({}) has to be below elo1 ({})", elo0, elo1));
+        }
+        if !(alpha > 0.0 && alpha < 0.5 && beta > 0.0 && beta < 0.5) {
+            return Err(format!("Invalid SPRT error rates: {} and {}, expected them between 0 and 0.5", alpha, beta));
+        }
+        Ok(Self { elo0, elo1, alpha, beta })
+    }
+
+    // Log-likelihood ratio of H1 over H0, using the normal approximation of the score. A result
+    // that didn't happen yet counts as half a game, so that e.g. a run of wins has a variance.
+    pub fn llr(&self, standings: &Standings) -> f32 {
+        if standings.games() == 0 {
+            return 0.0;
+        }
+        let counts = [standings.wins[0], standings.draws(), standings.wins[1]].map(|n| f64::max(n as f64, 0.5));
+        let games: f64 = counts.iter().sum();
+        let (wins, draws) = (counts[0] / games, counts[1] / games);
+        let score = wins + draws / 2.0;
+        let variance = (wins + draws / 4.0 - score * score) / games;
+        let expected_score = |elo: f32| 1.0 / (1.0 + 10f64.powf(-elo as f64 / 400.0));
+        let (score0, score1) = (expected_score(self.elo0), expected_score(self.elo1));
+        ((score1 - score0) * (2.0 * score - score0 - score1) / (2.0 * variance)) as f32
+    }
+
+    // The LLR below which H0 is accepted, and above which H1 is
+    pub fn bounds(&self) -> (f32, f32) {
+        let (alpha, beta) = (self.alpha as f64, self.beta as f64);
+        ((beta / (1.0 - alpha)).ln() as f32, ((1.0 - beta) / alpha).ln() as f32)
+    }
+
+    pub fn result(&self, standings: &Standings) -> Option<SprtResult> {
+        let llr = self.llr(standings);
+        let (lower, upper) = self.bounds();
+        if llr <= lower {
+            Some(SprtResult::H0Accepted)
+        } else if llr >= upper {
+            Some(SprtResult::H1Accepted)
+        } else {
+            None
+        }
+    }
+
+    // e.g. "SPRT [0, 5]: LLR 1.23 (-2.94, 2.94), H1 accepted"
+    pub fn summary(&self, standings: &Standings) -> String {
+        let (lower, upper) = self.bounds();
+        let result = match self.result(standings) {
+            Some(SprtResult::H0Accepted) => "H0 accepted",
+            Some(SprtResult::H1Accepted) => "H1 accepted",
+            None => "running",
+        };
+        format!(
+            "SPRT [{}, {}]: LLR {:.2} ({:.2}, {:.2}), {}",
+            self.elo0, self.elo1, self.llr(standings), lower, upper, result
+        )
+    }
+}
+
+// Which results a game can be called with before it's played out
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum AdjudicationPolicy {
+    // Every game is played until it ends by the rules
+    #[default]
+    Off,
+    DrawOnly,
+    WinOnly,
+    Both,
+}
+
+impl AdjudicationPolicy {
+    const NAMES: [(AdjudicationPolicy, &'static str); 4] = [
+        (AdjudicationPolicy::Off, "off"),
+        (AdjudicationPolicy::DrawOnly, "draw"),
+        (AdjudicationPolicy::WinOnly, "win"),
+        (AdjudicationPolicy::Both, "both"),
+    ];
+
+    pub fn from_name(name: &str) -> Option<AdjudicationPolicy> {
+        Self::NAMES.iter().find(|(_, n)| *n == name).map(|(policy, _)| *policy)
+    }
+
+    pub fn next(self) -> AdjudicationPolicy {
+        let ind = Self::NAMES.iter().position(|(policy, _)| *policy == self).unwrap();
+        Self::NAMES[(ind + 1) % Self::NAMES.len()].0
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            AdjudicationPolicy::Off => "Games are played out",
+            AdjudicationPolicy::DrawOnly => "Drawn games are adjudicated",
+            AdjudicationPolicy::WinOnly => "Won games are adjudicated",
+            AdjudicationPolicy::Both => "Drawn and won games are adjudicated",
+        }
+    }
+}
+
+// Calls a game from the scores the engines report, once they agree on the result for long
+// enough. Scores are in centipawns, and a mate score is above any of them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Adjudication {
+    pub policy: AdjudicationPolicy,
+    // A game is a draw once this many moves in a row were scored within 'draw_score' of 0, from
+    // move number 'draw_after' on
+    pub draw_score: i32,
+    pub draw_plies: usize,
+    pub draw_after: usize,
+    // A game is won once this many moves in a row gave the same side a score of 'win_score'
+    pub win_score: i32,
+    pub win_plies: usize,
+}
+
+impl Default for Adjudication {
+    fn default() -> Self {
+        Self { policy: AdjudicationPolicy::Off, draw_score: 10, draw_plies: 8, draw_after: 40, win_score: 1000, win_plies: 6 }
+    }
+}
+
+impl Adjudication {
+    const MATE_SCORE: i32 = 100_000;
+
+    // Reads "<policy>[:<name>=<value>,...]", e.g. "both" or "draw:draw_score=5,draw_plies=10"
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let (policy, thresholds) = s.split_once(':').unwrap_or((s, ""));
+        let policy = AdjudicationPolicy::from_name(policy.trim())
+            .ok_or(format!("Invalid adjudication '{}', expected 'off', 'draw', 'win' or 'both'", policy))?;
+        let mut adjudication = Self { policy, ..Self::default() };
+        for threshold in thresholds.split(',').filter(|t| !t.trim().is_empty()) {
+            let (name, value) = threshold.split_once('=').unwrap_or((threshold, ""));
+            let (name, value) = (name.trim(), value.trim());
+            let is_valid = match name {
+                "draw_score" => value.parse().map(|v| adjudication.draw_score = v).is_ok() && adjudication.draw_score >= 0,
+                "draw_plies" => value.parse().map(|v| adjudication.draw_plies = v).is_ok() && adjudication.draw_plies > 0,
+                "draw_after" => value.parse().map(|v| adjudication.draw_after = v).is_ok(),
+                "win_score" => value.parse().map(|v| adjudication.win_score = v).is_ok() && adjudication.win_score > 0,
+                "win_plies" => value.parse().map(|v| adjudication.win_plies = v).is_ok() && adjudication.win_plies > 0,
+                _ => return Err(format!("Unknown adjudication threshold '{}'", name)),
+            };
+            if !is_valid {
+                return Err(format!("Invalid value for '{}': '{}'", name, value));
+            }
+        }
+        Ok(adjudication)
+    }
+
+    // Result the game should end with, if the policy allows calling it at this point
+    pub fn verdict(&self, game: &Game) -> Option<Option<PieceColor>> {
+        if !game.is_ongoing() { return None; }
+        let (draws, wins) = match self.policy {
+            AdjudicationPolicy::Off => return None,
+            AdjudicationPolicy::DrawOnly => (true, false),
+            AdjudicationPolicy::WinOnly => (false, true),
+            AdjudicationPolicy::Both => (true, true),
+        };
+        // Scores of the last moves from white's point of view, most recent first. A move without
+        // a score, e.g. one a person played, ends the streak.
+        let scores = (0..game.move_count()).rev().map_while(|ind| white_score(game, ind));
+        if wins {
+            let last: Vec<i32> = scores.clone().take(self.win_plies).collect();
+            if last.len() == self.win_plies {
+                if last.iter().all(|&score| score >= self.win_score) {
+                    return Some(Some(PieceColor::Light));
+                }
+                if last.iter().all(|&score| score <= -self.win_score) {
+                    return Some(Some(PieceColor::Dark));
+                }
+            }
+        }
+        let move_number = game.board_after_last_move().map_or(0, |board| board.full_moves() as usize);
+        if draws && move_number >= self.draw_after {
+            let drawn = scores.take(self.draw_plies).filter(|score| score.abs() <= self.draw_score).count();
+            if drawn == self.draw_plies {
+                return Some(None);
+            }
+        }
+        None
+    }
+}
+
+// Score of the move at 'ind' from white's point of view, where a mate in fewer moves is worth more
+fn white_score(game: &Game, ind: usize) -> Option<i32> {
+    let score = match Score::parse(game.score_at(ind)?)? {
+        Score::Cp(cp) => cp,
+        // A mate in 0 means the engine is the one that's mated
+        Score::Mate(moves) if moves > 0 => Adjudication::MATE_SCORE - moves,
+        Score::Mate(moves) => -Adjudication::MATE_SCORE - moves,
+    };
+    let engine_is_white = game.board_before_move(ind)?.is_white_to_move();
+    Some(if engine_is_white { score } else { -score })
+}
+
+// How a match is played, as given on the command line
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MatchSettings {
+    pub stop_condition: StopCondition,
+    pub adjudication: Adjudication,
+    pub time_control: TimeControl,
+    pub seed: u64,
+    // Depth every move is searched to, instead of searching for a fixed time
+    pub depth: Option<u32>,
+    // Continue the match that was saved on the last exit
+    pub resume: bool,
+    // Color a person plays against the first engine
+    pub human: Option<PieceColor>,
+    // Let the engines think on their opponent's time
+    pub ponder: bool,
+}
+
+// What limits each search of the engines
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SearchMode {
+    Movetime,
+    // Searches can take any amount of time, so engines don't lose on time in this mode
+    Depth,
+}
+
+// Time an engine spent searching each of its moves over the whole match
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MoveTimes {
+    pub count: u32,
+    pub total: Duration,
+    pub min: Duration,
+    pub max: Duration,
+}
+
+impl MoveTimes {
+    fn record(&mut self, time: Duration) {
+        if self.count == 0 || time < self.min {
+            self.min = time;
+        }
+        self.max = self.max.max(time);
+        self.total += time;
+        self.count += 1;
+    }
+
+    pub fn average(&self) -> Duration {
+        if self.count == 0 { Duration::ZERO } else { self.total / self.count }
+    }
+}
 
 pub struct GameManager {
     engines: [EngineComm; 2],
-    // time left is stored in milliseconds
-    time_left: [f32; 2],
-    increment: Option<u32>,
+    // Names the engines play under, indexed like 'engines'
+    names: [String; 2],
+    // time left is indexed by engine (not by color)
+    clock: ChessClock,
+    // When the engine to move was told to start searching
+    search_start: Duration,
+    // Time the engine to move was charged for its current search so far, i.e. before the game
+    // was last paused
+    search_time: Duration,
+    move_times: [MoveTimes; 2],
+    // Games of the match that were added to the match PGN before the current one
     game_history: Vec<Game>,
+    // Number of games started before the current one, including those without any moves
+    game_count: usize,
     game: Game,
     playing: bool,
     white_engine: usize,
+    // Color a person plays instead of the second engine, against the first one. The person
+    // keeps that color in every game, rather than switching sides after each game.
+    human: Option<PieceColor>,
+    // Points the first engine scored in the first game of the current round, once it has ended
+    round_points: Option<f32>,
+    // Points the first engine scored in the round that the current game finished, if it did
+    finished_round: Option<f32>,
+    // Every game of the match is added to this file once it's replaced by the next one
+    pgn_file: String,
+    standings: Standings,
+    stop_condition: StopCondition,
+    adjudication: Adjudication,
+    movetime: Duration,
+    // An engine forfeits if it hasn't sent its move after this many times its movetime
+    watchdog_factor: f32,
+    depth: u32,
+    search_mode: SearchMode,
+    // Whether an engine searches the reply it expects while its opponent is thinking. This is
+    // only done between engines that search for a fixed time.
+    ponder: bool,
+    time_control: TimeControl,
+    openings: EcoTable,
+    // Every random choice is made with this, so a match can be repeated by using the same seed
+    rng: StdRng,
 }
 
 const FIRST: usize = 0;
 const SECOND: usize = 1;
 
-const SECONDS_PER_MOVE: f32 = 1.0;
+const MATCH_PGN_FILE: &str = "match.pgn";
+const HUMAN_NAME: &str = "Human";
+// How often 'step' checks whether the engine has sent its move
+const STEP_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+// Engines with the same name, e.g. in self-play, are numbered so their games can be told apart
+fn player_names(name_a: &str, name_b: &str) -> [String; 2] {
+    if name_a == name_b {
+        [format!("{} #1", name_a), format!("{} #2", name_b)]
+    } else {
+        [name_a.to_string(), name_b.to_string()]
+    }
+}
+
+// Loads the list of positions (one FEN per line) that the games of a match are played from.
+// Blank lines are skipped.
+pub fn load_fens(path: &str) -> Result<Vec<String>, GuiError> {
+    let content = std::fs::read_to_string(path).map_err(GuiError::io(path))?;
+    // Files saved by some editors start with a byte order mark which isn't part of the FEN
+    Ok(parse_fens(content.trim_start_matches('\u{feff}')))
+}
+
+fn parse_fens(content: &str) -> Vec<String> {
+    content.lines().map(str::trim).filter(|line| !line.is_empty()).map(String::from).collect()
+}
+
+// Loads the positions at the end of the openings in a PGN, e.g. of an opening suite where every
+// game is the first few moves of an opening
+pub fn load_book(path: &str, attack_info: &AttackInfo, zobrist_info: &ZobristInfo) -> Result<Vec<String>, GuiError> {
+    let text = std::fs::read_to_string(path).map_err(GuiError::io(path))?;
+    let openings = pgn::load(&text, attack_info, zobrist_info)
+        .map_err(|reason| GuiError::Book { path: path.to_string(), reason })?;
+    let fens = book_positions(&openings);
+    if fens.is_empty() {
+        return Err(GuiError::Book { path: path.to_string(), reason: "It has no positions to play from".to_string() });
+    }
+    Ok(fens)
+}
+
+// An opening that already ended the game, e.g. with a checkmate, can't be played from
+fn book_positions(openings: &[Game]) -> Vec<String> {
+    let (playable, finished): (Vec<&Game>, Vec<&Game>) = openings.iter().partition(|game| game.is_ongoing());
+    if !finished.is_empty() {
+        eprintln!("[WARN] Skipping {} openings of the book that end the game", finished.len());
+    }
+    playable.iter().map(|game| game.current_fen()).collect()
+}
+
+// Positions the games of a match are played from: those of the opening book if one was given,
+// or else the list of FENs
+pub fn load_positions(paths: &Paths, attack_info: &AttackInfo, zobrist_info: &ZobristInfo) -> Result<Vec<String>, GuiError> {
+    match paths.book() {
+        Some(book) => load_book(&book, attack_info, zobrist_info),
+        None => Ok(playable_fens(load_fens(&paths.fens())?, attack_info, zobrist_info)),
+    }
+}
+
+// Like the openings of a book, a position that already ended the game can't be played from. One
+// that can't be read is kept, so that it's reported when its game is started.
+fn playable_fens(fens: Vec<String>, attack_info: &AttackInfo, zobrist_info: &ZobristInfo) -> Vec<String> {
+    let (playable, finished): (Vec<String>, Vec<String>) = fens.into_iter().partition(|fen| {
+        fen::try_parse(fen, zobrist_info).is_err()
+            || Game::from_fen("?", "?", fen, attack_info, zobrist_info).is_ongoing()
+    });
+    if !finished.is_empty() {
+        eprintln!("[WARN] Skipping {} positions of the list that end the game", finished.len());
+    }
+    playable
+}
+
+// Position that the game after 'game_count' games is played from. The match is played in rounds
+// of two games from the same position, once with each engine as white, before moving on to the
+// next position.
+fn fen_for_game(fens: &[String], game_count: usize) -> Option<&str> {
+    fens.get(game_count / 2).map(String::as_str)
+}
+
+// Engine that plays white in the game after 'game_count' games: the first engine in the first
+// game of each round, and the second one in the rematch
+fn white_for_game(game_count: usize) -> usize {
+    if game_count % 2 == 0 { FIRST } else { SECOND }
+}
+
+// Engine slot that plays white when a person plays 'human', who takes the second engine's place
+fn human_white_engine(human: Option<PieceColor>) -> Option<usize> {
+    human.map(|side| if side == PieceColor::Light { SECOND } else { FIRST })
+}
+
+fn pick_fen<'a>(fens: &'a [String], rng: &mut impl Rng) -> Option<&'a str> {
+    fens.choose(rng).map(String::as_str)
+}
 
 impl GameManager {
-    // Default starting time for a game is 1 min per side (expressed here in milliseconds)
-    const DEFAULT_START_TIME: f32 = (1 * 60 * 1000) as f32;
+    const DEFAULT_MOVETIME: Duration = Duration::from_secs(1);
+    const DEFAULT_DEPTH: u32 = 8;
+    const DEFAULT_WATCHDOG_FACTOR: f32 = 2.0;
+    // A search to a fixed depth is stopped, and the engine forfeits, once it hasn't sent
+    // anything for this long
+    const DEPTH_SILENCE_LIMIT: Duration = Duration::from_secs(60);
 
-    pub fn new(engine_a: EngineComm, engine_b: EngineComm, zobrist_info: &ZobristInfo) -> Self {
-        let game = Game::new(engine_a.name(), engine_b.name(), zobrist_info);
+    pub fn new(engine_a: EngineComm, engine_b: EngineComm, attack_info: &AttackInfo, zobrist_info: &ZobristInfo) -> Self {
+        Self::with_time_source(engine_a, engine_b, attack_info, zobrist_info, Box::new(RealTime::new()))
+    }
+
+    pub fn with_time_source(
+        engine_a: EngineComm, engine_b: EngineComm, attack_info: &AttackInfo, zobrist_info: &ZobristInfo,
+        time_source: Box<dyn TimeSource>
+    ) -> Self {
+        let names = player_names(engine_a.name(), engine_b.name());
+        let time_control = TimeControl::default();
+        let mut game = Game::new(&names[FIRST], &names[SECOND], attack_info, zobrist_info);
+        game.set_time_control(time_control);
         Self {
             engines: [engine_a, engine_b],
-            time_left: [Self::DEFAULT_START_TIME, Self::DEFAULT_START_TIME],
-            increment: None,
+            names,
+            clock: ChessClock::new(time_source, time_control.base, time_control.increment),
+            search_start: Duration::ZERO,
+            search_time: Duration::ZERO,
+            move_times: [MoveTimes::default(); 2],
             game_history: vec![],
+            game_count: 0,
             game,
             white_engine: FIRST,
+            human: None,
+            round_points: None,
+            finished_round: None,
             playing: false,
+            pgn_file: MATCH_PGN_FILE.to_string(),
+            standings: Standings::default(),
+            stop_condition: StopCondition::default(),
+            adjudication: Adjudication::default(),
+            movetime: Self::DEFAULT_MOVETIME,
+            watchdog_factor: Self::DEFAULT_WATCHDOG_FACTOR,
+            depth: Self::DEFAULT_DEPTH,
+            search_mode: SearchMode::Movetime,
+            ponder: false,
+            time_control,
+            openings: EcoTable::default(),
+            rng: StdRng::seed_from_u64(0),
         }
     }
 
-    fn switch_sides(&mut self) {
-        self.white_engine ^= 1;
+    // Makes the engine at 'engine_index' play white in the current game. This can only be done
+    // before the first move of the game is played; returns whether the sides were set.
+    pub fn set_white(&mut self, engine_index: usize) -> bool {
+        assert!(engine_index == FIRST || engine_index == SECOND);
+        if self.game.move_count() > 0 {
+            return false;
+        }
+        self.white_engine = engine_index;
+        // The person plays whichever color the second engine was given
+        if self.human.is_some() {
+            self.human = Some(if engine_index == SECOND { PieceColor::Light } else { PieceColor::Dark });
+        }
+        let white = &self.names[self.white_engine];
+        let black = &self.names[self.white_engine^1];
+        self.game.set_names(white, black);
+        self.clock.reset();
+        true
     }
 
-    pub fn update_time_left(&mut self, frame_time: f32) {
-        if !self.playing { return; }
-        let tl = if self.game.is_white_to_move() {
-            &mut self.time_left[FIRST]
-        } else {
-            &mut self.time_left[SECOND]
-        };
-        *tl -= frame_time * 1000.0;
-        if *tl <= 0.0 {
-            *tl = 0.0;
+    pub fn white_engine(&self) -> usize {
+        self.white_engine
+    }
+
+    // Lets a person play 'side' against the first engine, or gives the second engine its place
+    // back with None. Like 'set_white', this can only be done before the first move of a game.
+    pub fn set_human(&mut self, side: Option<PieceColor>) -> bool {
+        if self.game.move_count() > 0 {
+            return false;
+        }
+        // A search of the second engine for the first move would otherwise be left running
+        self.stop();
+        self.human = side;
+        self.names = player_names(self.engines[FIRST].name(), self.engines[SECOND].name());
+        if side.is_some() {
+            self.names[SECOND] = HUMAN_NAME.to_string();
         }
+        let white_engine = human_white_engine(side).unwrap_or(self.white_engine);
+        self.set_white(white_engine)
     }
 
-    fn add_increment_to_time(&mut self) {
-        if let Some(inc) = self.increment {
-            let tl = if self.game.is_white_to_move() {
-                &mut self.time_left[FIRST]
-            } else {
-                &mut self.time_left[SECOND]
-            };
-            *tl += inc as f32;
+    pub fn human(&self) -> Option<PieceColor> {
+        self.human
+    }
+
+    // Whether the move of the current game is up to the person playing
+    pub fn is_human_turn(&self) -> bool {
+        let side = if self.game.is_white_to_move() { PieceColor::Light } else { PieceColor::Dark };
+        self.game.is_ongoing() && self.human == Some(side)
+    }
+
+    // Time the engines are told to search for each move. Takes effect from the next search.
+    pub fn set_movetime(&mut self, movetime: Duration) {
+        self.movetime = movetime;
+    }
+
+    pub fn movetime(&self) -> Duration {
+        self.movetime
+    }
+
+    pub fn set_watchdog_factor(&mut self, watchdog_factor: f32) {
+        assert!(watchdog_factor > 1.0);
+        self.watchdog_factor = watchdog_factor;
+    }
+
+    // Depth the engines are told to search to in 'SearchMode::Depth'
+    pub fn set_depth(&mut self, depth: u32) {
+        self.depth = depth;
+    }
+
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    // Takes effect from the next search
+    pub fn set_search_mode(&mut self, search_mode: SearchMode) {
+        self.search_mode = search_mode;
+    }
+
+    pub fn search_mode(&self) -> SearchMode {
+        self.search_mode
+    }
+
+    pub fn apply_settings(&mut self, settings: &MatchSettings) {
+        self.set_stop_condition(settings.stop_condition);
+        self.set_adjudication(settings.adjudication);
+        self.set_time_control(settings.time_control);
+        self.set_seed(settings.seed);
+        if let Some(depth) = settings.depth {
+            self.set_depth(depth);
+            self.set_search_mode(SearchMode::Depth);
+        }
+        self.set_human(settings.human);
+        self.set_ponder(settings.ponder);
+    }
+
+    // Engines that have a 'Ponder' option are told about it, since some of them manage their
+    // time differently when they can ponder
+    pub fn set_ponder(&mut self, ponder: bool) {
+        self.ponder = ponder;
+        for engine in &mut self.engines {
+            if engine.has_option("Ponder") {
+                engine.set_option("Ponder", &ponder.to_string());
+            }
+        }
+    }
+
+    // Restarts the clock of the current game, and is used for every following game
+    pub fn set_time_control(&mut self, time_control: TimeControl) {
+        self.time_control = time_control;
+        self.clock.set_time_control(time_control);
+        self.game.set_time_control(time_control);
+    }
+
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    pub fn random_fen<'a>(&mut self, fens: &'a [String]) -> Option<&'a str> {
+        pick_fen(fens, &mut self.rng)
+    }
+
+    // Table used to name the opening of each game
+    pub fn set_openings(&mut self, openings: EcoTable) {
+        self.openings = openings;
+        self.update_opening();
+    }
+
+    // Keeps the deepest opening found so far, since the game eventually leaves the known lines
+    fn update_opening(&mut self) {
+        let opening = self.game.board_after_last_move().and_then(|board| self.openings.find(board));
+        if let Some(opening) = opening {
+            self.game.set_opening(opening.clone());
         }
     }
 
     pub fn toggle_playing(&mut self) {
+        // A finished game, e.g. one set up from a checkmate, can't be played on
+        if !self.playing && !self.game.is_ongoing() {
+            return;
+        }
+        if !self.playing {
+            self.stop_analysis();
+        }
         self.playing = !self.playing;
+        // The engine keeps searching while the game is paused, but it's only charged for the
+        // time it spent searching while the game was being played
+        if !self.playing {
+            self.search_time += self.clock.stop();
+        } else if self.engines[self.side()].is_searching() {
+            self.clock.start(self.side());
+        }
+    }
+
+    // Returns true when the current game has just ended
+    pub fn check_state(&mut self) -> bool {
+        if !self.game.is_ongoing() && self.playing {
+            self.playing = false;
+            // The engine that made the last move could be pondering on a reply that won't come
+            for engine in &mut self.engines {
+                if engine.pondered_move().is_some() {
+                    engine.stop();
+                }
+            }
+            self.record_result();
+            return true;
+        }
+        false
+    }
+
+    fn record_result(&mut self) {
+        let state = self.game.state();
+        let mut points = 0.5;
+        if state.is_draw() {
+            self.standings.add_draw(state);
+        } else if let Some(color) = state.winner() {
+            let winner = if color == PieceColor::Light { self.white_engine } else { self.white_engine^1 };
+            self.standings.wins[winner] += 1;
+            points = if winner == FIRST { 1.0 } else { 0.0 };
+        }
+        // The round is scored once both of its games have ended
+        if self.game_count % 2 == 0 {
+            self.round_points = Some(points);
+        } else if let Some(first_points) = self.round_points.take() {
+            self.standings.add_pair(first_points + points);
+            self.finished_round = Some(first_points + points);
+        }
+    }
+
+    // Score of the round that the current game finished, e.g. "Round 3: A 1.5 - 0.5 B"
+    pub fn round_summary(&self) -> Option<String> {
+        let points = self.finished_round?;
+        Some(format!(
+            "Round {}: {} {:.1} - {:.1} {}",
+            self.game_count / 2 + 1, self.names[FIRST], points, 2.0 - points, self.names[SECOND]
+        ))
+    }
+
+    pub fn standings(&self) -> Standings {
+        self.standings
+    }
+
+    pub fn set_stop_condition(&mut self, stop_condition: StopCondition) {
+        self.stop_condition = stop_condition;
     }
 
-    pub fn check_state(&mut self) {
-        if !self.game.is_ongoing() && self.playing { self.playing = false; }
+    pub fn set_adjudication(&mut self, adjudication: Adjudication) {
+        self.adjudication = adjudication;
     }
 
-    pub fn start_new_game(&mut self, fens: &String, zobrist_info: &ZobristInfo) {
-        self.switch_sides();
-        let new_white = self.engines[self.white_engine].name();
-        let new_black = self.engines[self.white_engine^1].name();
-        let game_count = self.game_history.len();
-        let new_game;
-        // After switching the sides and playing the game both as white and black, a new
-        // position is loaded
-        if game_count % 2 == 0 {
-            if let Some(fen) = fens.lines().nth(game_count) {
-                new_game = Game::from_fen(new_white, new_black, fen, zobrist_info);
+    pub fn adjudication(&self) -> Adjudication {
+        self.adjudication
+    }
+
+    pub fn sprt_summary(&self) -> Option<String> {
+        self.stop_condition.sprt.map(|sprt| sprt.summary(&self.standings))
+    }
+
+    pub fn is_match_over(&self) -> bool {
+        self.stop_condition.is_met(&self.standings)
+    }
+
+    pub fn print_standings(&self) {
+        let standings = self.standings;
+        println!(
+            "Score of {} vs {}: {} - {} - {} [{:.1} - {:.1}] ({} games)",
+            self.engine_name(0), self.engine_name(1),
+            standings.wins[0], standings.wins[1], standings.draws(),
+            standings.score(0), standings.score(1),
+            standings.games()
+        );
+        if standings.draws() > 0 {
+            let causes: Vec<String> = GameState::DRAWS.iter()
+                .filter(|&&state| standings.draws_by(state) > 0)
+                .map(|&state| format!("{} by {}", standings.draws_by(state), draw_cause(state)))
+                .collect();
+            println!("Draws: {}", causes.join(", "));
+        }
+        if standings.pairs() > 0 {
+            println!(
+                "Rounds: {} - {} - {} ({} rounds)",
+                standings.pair_wins[0], standings.pair_wins[1], standings.pair_draws, standings.pairs()
+            );
+        }
+        if let Some(summary) = standings.elo_summary() {
+            println!("{}", summary);
+        }
+        if let Some(summary) = self.sprt_summary() {
+            println!("{}", summary);
+        }
+    }
+
+    pub fn move_times(&self, engine_index: usize) -> MoveTimes {
+        self.move_times[engine_index]
+    }
+
+    pub fn engine_name(&self, engine_index: usize) -> &String {
+        &self.names[engine_index]
+    }
+
+    // Adds the current game to the match PGN, e.g. when the match ends with it
+    pub fn save_current_game(&self, attack_info: &AttackInfo, zobrist_info: &ZobristInfo) {
+        if self.game.move_count() > 0 {
+            self.game.append_to(&self.pgn_file, attack_info, zobrist_info);
+        }
+    }
+
+    // Adds the current game to the match PGN when the match is closed, if it has ended. An
+    // unfinished game is kept in the match state instead, and continued when the match is
+    // resumed, so that it isn't added a second time once it's over.
+    pub fn save_finished_game(&self, attack_info: &AttackInfo, zobrist_info: &ZobristInfo) {
+        if !self.game.is_ongoing() {
+            self.save_current_game(attack_info, zobrist_info);
+        }
+    }
+
+    // Everything needed to continue the match later on, where 'engines' are the paths the engines
+    // were started from. It should be saved after 'save_finished_game'.
+    pub fn match_state(&self, engines: [String; 2]) -> MatchState {
+        MatchState {
+            engines,
+            standings: self.standings,
+            game_count: self.game_count,
+            saved_games: self.game_history.len(),
+            current_saved: !self.game.is_ongoing() && self.game.move_count() > 0,
+            current_over: !self.game.is_ongoing(),
+            white_engine: self.white_engine,
+            round_points: self.round_points,
+            start_fen: self.game.start_fen().clone(),
+            moves: if self.game.is_ongoing() {
+                (0..self.game.move_count()).filter_map(|i| self.game.move_at(i)).map(|&mv| moves::to_uci(mv)).collect()
+            } else {
+                vec![]
+            },
+        }
+    }
+
+    // Continues a match saved with 'match_state'. The games that were already played are read
+    // back from the match PGN. An unfinished game is continued from its moves, with the clocks
+    // started over, while a finished one is followed by the next game of the match.
+    pub fn resume(
+        &mut self, state: &MatchState, fens: &[String], attack_info: &AttackInfo, zobrist_info: &ZobristInfo
+    ) -> Result<(), GuiError> {
+        let start_fen = fen::try_parse(&state.start_fen, zobrist_info)
+            .map(|board| fen::gen_fen(&board))
+            .map_err(|reason| GuiError::Fen { fen: state.start_fen.clone(), reason })?;
+        // The match PGN can also hold games of earlier matches, so the games of this match are
+        // the last ones in it
+        let text = std::fs::read_to_string(&self.pgn_file).unwrap_or_default();
+        let mut games = pgn::load(&text, attack_info, zobrist_info)
+            .map_err(|e| GuiError::Resume(format!("Couldn't read the games of the match from '{}': {}", self.pgn_file, e)))?;
+        let current_game = if state.current_saved { games.pop() } else { None };
+        if games.len() < state.saved_games {
+            return Err(GuiError::Resume(format!(
+                "'{}' has {} games, but the saved match has {}",
+                self.pgn_file, games.len(), state.saved_games
+            )));
+        }
+        self.stop();
+        self.game_history = games.split_off(games.len() - state.saved_games);
+        self.game_count = state.game_count;
+        self.standings = state.standings;
+        self.white_engine = state.white_engine;
+        self.round_points = state.round_points;
+        let white = &self.names[self.white_engine];
+        let black = &self.names[self.white_engine^1];
+        self.game = Game::from_fen(white, black, &start_fen, attack_info, zobrist_info);
+        self.game.set_time_control(self.time_control);
+        for uci in &state.moves {
+            let mv = self.game.board_after_last_move().and_then(|board| moves::from_uci(uci, board, attack_info));
+            if !mv.is_some_and(|mv| self.game.make_move(mv, attack_info, zobrist_info)) {
+                return Err(GuiError::Resume(format!("'{}' isn't a legal move of the unfinished game", uci)));
+            }
+        }
+        self.update_opening();
+        self.clock.reset();
+        if state.current_over {
+            self.game_history.extend(current_game);
+            // The current game is empty, so it only counts towards 'game_count' when replaced
+            if !self.start_new_game(fens, attack_info, zobrist_info) {
+                return Err(GuiError::Resume("There are no games left to play in it".to_string()));
+            }
+        }
+        Ok(())
+    }
+
+    // Stops playing, including any search that is still going on, so that its result isn't
+    // mistaken for a move in the next game
+    pub fn stop(&mut self) {
+        self.playing = false;
+        self.clock.stop();
+        for engine in &mut self.engines {
+            if engine.is_searching() {
+                engine.stop();
+            }
+        }
+    }
+
+    // Lets the first engine analyze the position until the analysis is stopped or the game is
+    // resumed
+    pub fn start_analysis(&mut self, fen: &str) {
+        self.stop();
+        let engine = &mut self.engines[FIRST];
+        engine.fen(fen);
+        engine.go_infinite();
+    }
+
+    pub fn stop_analysis(&mut self) {
+        let engine = &mut self.engines[FIRST];
+        if engine.is_analyzing() {
+            engine.stop();
+        }
+    }
+
+    pub fn is_analyzing(&self) -> bool {
+        self.engines[FIRST].is_analyzing()
+    }
+
+    // Latest info of the analysis, along with the name of the engine analyzing
+    pub fn poll_analysis(&mut self) -> (&String, &SearchInfo) {
+        let engine = &mut self.engines[FIRST];
+        engine.poll_analysis();
+        (engine.name(), engine.search_info())
+    }
+
+    // Returns false, and keeps the current game, if the match is over or there are no more
+    // positions to play from
+    pub fn start_new_game(&mut self, fens: &[String], attack_info: &AttackInfo, zobrist_info: &ZobristInfo) -> bool {
+        if self.is_match_over() {
+            eprintln!("[WARN] The match is over, no more games will be played");
+            return false;
+        }
+        let next_game = self.game_count + 1;
+        // The rematch is played from the position of the first game of the round, which could
+        // also have been set up by hand
+        let fen = if next_game % 2 == 0 {
+            if let Some(fen) = fen_for_game(fens, next_game) {
+                fen.to_string()
             } else {
-                eprintln!("[WARN] Couldn't load more positions to play from");
-                // Exiting from this process is only temporary and will need to be fixed in the
-                // future
-                std::process::exit(0);
+                eprintln!("[WARN] All {} positions of the list have been played", fens.len());
+                return false;
             }
         } else {
-            let fen = self.game.start_fen();
-            new_game = Game::from_fen(new_white, new_black, fen, zobrist_info);
+            self.game.start_fen().clone()
+        };
+        self.stop();
+        if next_game % 2 == 0 {
+            self.round_points = None;
         }
+        self.white_engine = human_white_engine(self.human).unwrap_or(white_for_game(next_game));
+        self.replace_game(next_game, &fen, attack_info, zobrist_info);
+        true
+    }
+
+    // Starts the match over from its first position, with the first engine as white. The score and
+    // the games played so far are dropped, though they stay in the match PGN. Returns false, and
+    // keeps the match going, if there are no positions to play from. It also starts the first
+    // game of a new match.
+    pub fn reset_match(&mut self, fens: &[String], attack_info: &AttackInfo, zobrist_info: &ZobristInfo) -> bool {
+        let fen = if let Some(fen) = fen_for_game(fens, 0) { fen } else {
+            eprintln!("[WARN] There are no positions to start a new match from");
+            return false;
+        };
+        self.stop();
+        self.save_current_game(attack_info, zobrist_info);
+        self.game_history.clear();
+        self.game_count = 0;
+        self.round_points = None;
+        self.finished_round = None;
+        self.standings = Standings::default();
+        self.move_times = [MoveTimes::default(); 2];
+        self.white_engine = human_white_engine(self.human).unwrap_or(FIRST);
+        self.game = self.new_game(fen, attack_info, zobrist_info);
+        self.update_opening();
+        self.clock.reset();
+        true
+    }
+
+    // Replaces the current game with one played from 'fen'. The FEN should already be checked,
+    // e.g. with 'fen::try_parse'. The game starts a new round, so the rematch is played from the
+    // same position with the colors swapped. A round that hasn't started yet is taken over.
+    pub fn start_game_from_fen(&mut self, fen: &str, attack_info: &AttackInfo, zobrist_info: &ZobristInfo) {
+        self.stop();
+        let game_count = if self.game_count % 2 == 0 && self.game.move_count() == 0 {
+            self.game_count
+        } else {
+            (self.game_count + 1).next_multiple_of(2)
+        };
+        self.round_points = None;
+        self.white_engine = human_white_engine(self.human).unwrap_or(white_for_game(game_count));
+        self.replace_game(game_count, fen, attack_info, zobrist_info);
+    }
+
+    // 'game_count' is the number of games before the new one
+    fn replace_game(&mut self, game_count: usize, fen: &str, attack_info: &AttackInfo, zobrist_info: &ZobristInfo) {
+        self.save_current_game(attack_info, zobrist_info);
+        let new_game = self.new_game(fen, attack_info, zobrist_info);
         let completed_game = std::mem::replace(&mut self.game, new_game);
-        self.game_history.push(completed_game);
+        if completed_game.move_count() > 0 {
+            self.game_history.push(completed_game);
+        }
+        self.game_count = game_count;
+        self.finished_round = None;
+        self.update_opening();
         // Reset the amount of time left
-        self.time_left[self.white_engine] = Self::DEFAULT_START_TIME;
-        self.time_left[self.white_engine^1] = Self::DEFAULT_START_TIME;
+        self.clock.reset();
+    }
+
+    fn new_game(&self, fen: &str, attack_info: &AttackInfo, zobrist_info: &ZobristInfo) -> Game {
+        let white = &self.names[self.white_engine];
+        let black = &self.names[self.white_engine^1];
+        let mut game = Game::from_fen(white, black, fen, attack_info, zobrist_info);
+        game.set_time_control(self.time_control);
+        game
     }
 
     pub fn current_move_count(&self) -> usize {
         self.game.move_count()
     }
 
+    // Time left (in milliseconds) for white and black
     pub fn time_left(&self) -> (f32, f32) {
-        (self.time_left[self.white_engine], self.time_left[self.white_engine^1])
+        (
+            self.clock.time_left(self.white_engine).as_secs_f32() * 1000.0,
+            self.clock.time_left(self.white_engine^1).as_secs_f32() * 1000.0
+        )
     }
 
     pub fn playing(&self) -> bool {
@@ -124,88 +1105,772 @@ impl GameManager {
         &self.game
     }
 
-    pub fn play(&mut self, frame_time: f32, attack_info: &AttackInfo, zobrist_info: &ZobristInfo) -> Option<Move> {
+    pub fn play(&mut self, attack_info: &AttackInfo, zobrist_info: &ZobristInfo) -> Option<Move> {
         if !self.playing { return None; }
-        if let Some(ref mv_str) = self.comm_with_engine(frame_time) {
-            let mut found_move = None;
-            if let Some(board) = self.game.board_after_last_move() {
-                let source = mv_str.get(0..2);
-                let target = mv_str.get(2..4);
-                let promoted = if let Some(ch) = mv_str.chars().nth(4) {
-                    let piece_char = if board.is_white_to_move() {
-                        ch.to_ascii_uppercase()
-                    } else { ch };
-                    Piece::from_char(piece_char)
-                } else { None };
-
-                let piece = if let Some(sq_str) = source {
-                    board.find_piece(Sq::from_str(sq_str) as usize)
-                } else { None };
-
-                if let Some(p) = piece {
-                    let mut ml = MoveList::new();
-                    move_gen::generate_by_piece(board, &attack_info, &mut ml, p);
-                    if source.is_some() && target.is_some() {
-                        found_move = ml.search(
-                            Sq::from_str(source.unwrap()),
-                            Sq::from_str(target.unwrap()),
-                            promoted
-                        );
-                    }
-                }
+        let best_move = self.comm_with_engine()?;
+        let side = self.side();
+        let mv = self.make_engine_move(&best_move, attack_info, zobrist_info)?;
+        if self.ponder {
+            self.start_pondering(side, mv, attack_info, zobrist_info);
+        }
+        Some(mv)
+    }
+
+    // Lets the engine that just played 'mv' search the reply it expects while its opponent is
+    // thinking. That's the ponder move the engine sent with its move, or else the next move of
+    // its line.
+    fn start_pondering(&mut self, side: usize, mv: Move, attack_info: &AttackInfo, zobrist_info: &ZobristInfo) {
+        if self.human.is_some() || self.search_mode != SearchMode::Movetime || !self.game.is_ongoing() {
+            return;
+        }
+        let engine = &self.engines[side];
+        let pv = &engine.search_info().pv;
+        let ponder_move = if let Some(ponder_move) = engine.ponder_move() { ponder_move } else {
+            if pv.len() < 2 || pv[0] != moves::to_uci(mv) { return; }
+            pv[1].clone()
+        };
+        // The line could be wrong, in which case there's nothing to ponder on
+        if !self.game.legal_moves_uci(attack_info, zobrist_info).contains(&ponder_move) { return; }
+        let fen = self.game.current_fen();
+        self.engines[side].ponder(&fen, &ponder_move, self.movetime.as_millis() as u64);
+    }
+
+    // Plays the next move of the current game right away, waiting for the engine instead of
+    // checking on it once per frame like 'play'. It doesn't matter whether the game is being
+    // played, which makes it a simple way to advance a game one move at a time, e.g. in headless
+    // matches and tests. The engine gets the same time, and the same watchdog, as in 'play'.
+    pub fn step(&mut self, attack_info: &AttackInfo, zobrist_info: &ZobristInfo) -> Option<Move> {
+        if !self.game.is_ongoing() || self.is_human_turn() { return None; }
+        self.stop_analysis();
+        let playing = std::mem::replace(&mut self.playing, true);
+        let mv = loop {
+            if let Some(mv) = self.play(attack_info, zobrist_info) {
+                break Some(mv);
+            }
+            // The engine lost on time or forfeited
+            if !self.game.is_ongoing() {
+                break None;
             }
-            if let Some(mv) = found_move {
-                if self.game.make_move(mv, &attack_info, &zobrist_info) {
-                    self.add_increment_to_time();
-                    return Some(mv);
+            thread::sleep(STEP_POLL_INTERVAL);
+        };
+        self.playing = playing;
+        mv
+    }
+
+    // Plays the move the engine to move sent, or makes it forfeit if the move isn't legal
+    fn make_engine_move(&mut self, best_move: &BestMove, attack_info: &AttackInfo, zobrist_info: &ZobristInfo) -> Option<Move> {
+        let side = self.side();
+        let mv_str = match best_move {
+            BestMove::Move(mv_str) => mv_str,
+            // A position without moves would have ended the game already, so the engine missed
+            // the moves it has
+            BestMove::NoMove => {
+                eprintln!("[WARN] '{}' sent no move, although it has legal moves", self.names[side]);
+                self.game.illegal_move(side == self.white_engine);
+                return None;
+            },
+        };
+        let found_move = self.game.board_after_last_move()
+            .and_then(|board| moves::from_uci(mv_str, board, attack_info));
+        if let Some(mv) = found_move {
+            if self.game.make_move(mv, attack_info, zobrist_info) {
+                let info = self.engines[side].search_info().clone();
+                self.game.set_last_score(info.score.clone());
+                self.game.set_last_search(info);
+                self.clock.add_increment(side);
+                self.update_opening();
+                if let Some(winner) = self.adjudication.verdict(&self.game) {
+                    self.game.adjudicate(winner);
                 }
+                return Some(mv);
             }
         }
+        // Asking again would most likely give the same move, so the engine forfeits instead
+        eprintln!("[WARN] '{}' sent an illegal move: '{}'", self.names[side], mv_str);
+        self.game.illegal_move(side == self.white_engine);
         None
     }
 
-    fn comm_with_engine(&mut self, frame_time: f32) -> Option<String> {
+    // Plays a move that the person entered, if it's their turn and the move is legal
+    pub fn play_human_move(&mut self, mv: Move, attack_info: &AttackInfo, zobrist_info: &ZobristInfo) -> bool {
+        if !self.playing || !self.is_human_turn() { return false; }
+        let side = self.side();
+        // Stopped before the move is made, so that the time it took is charged to the right side
+        self.clock.stop();
+        if self.search_mode == SearchMode::Movetime && self.clock.is_flagged(side) {
+            self.game.lost_on_time(side == self.white_engine);
+            return false;
+        }
+        if !self.game.make_move(mv, attack_info, zobrist_info) {
+            return false;
+        }
+        self.clock.add_increment(side);
+        self.update_opening();
+        true
+    }
+
+    // The person's clock runs while it's their turn. Like the engines, they can only lose on
+    // time when the moves are timed.
+    fn run_human_clock(&mut self) {
+        let side = self.side();
+        if !self.clock.is_running() {
+            self.clock.start(side);
+        } else if self.search_mode == SearchMode::Movetime && self.clock.is_flagged(side) {
+            self.clock.stop();
+            self.game.lost_on_time(side == self.white_engine);
+        }
+    }
+
+    fn comm_with_engine(&mut self) -> Option<BestMove> {
         if !self.game.is_ongoing() || !self.playing { return None; }
-        let engine: &mut EngineComm = &mut self.engines[self.side()];
+        if self.is_human_turn() {
+            self.run_human_clock();
+            return None;
+        }
+        let movetime = self.movetime;
+        let now = self.clock.now();
+        let side = self.side();
+        let engine: &mut EngineComm = &mut self.engines[side];
+        // If the engine guessed the move it was pondering on, its search goes on as the search for
+        // this move. Otherwise the search is thrown away and a new one is started.
+        if let Some(pondered) = engine.pondered_move() {
+            let last_move = self.game.last_move().map(|&mv| moves::to_uci(mv));
+            let hit = last_move.as_deref() == Some(pondered);
+            if hit && self.search_mode == SearchMode::Movetime {
+                engine.ponderhit();
+                self.search_start = now;
+                self.search_time = Duration::ZERO;
+                self.clock.start(side);
+                return None;
+            }
+            engine.stop();
+        }
         if !engine.is_searching() {
             engine.fen(&self.game.current_fen());
-            engine.search_movetime((SECONDS_PER_MOVE * 1000.0) as u64);
+            match self.search_mode {
+                SearchMode::Movetime => engine.search_movetime(movetime.as_millis() as u64),
+                SearchMode::Depth => engine.search_depth(self.depth),
+            }
+            self.search_start = now;
+            self.search_time = Duration::ZERO;
+            self.clock.start(side);
+            None
+        } else if self.search_mode == SearchMode::Depth {
+            // The clock keeps running to show the time used, but a deep search isn't lost on time
+            self.get_move_from_engine()
+        } else if self.clock.is_flagged(side) {
+            self.clock.stop();
+            engine.stop();
+            self.game.lost_on_time(side == self.white_engine);
             None
-        } else if !engine.search_time_over() {
-            engine.update_time_left(frame_time);
+        } else if now.saturating_sub(self.search_start) < movetime {
             None
         } else {
-            self.get_move_from_engine(frame_time)
+            self.get_move_from_engine()
         }
     }
 
 
-    fn get_move_from_engine(&mut self, frame_time: f32) -> Option<String> {
-        let mut retry_count = 0;
+    // Checks once per frame whether the engine has sent its move, so that the GUI keeps running
+    // while the engine takes its time
+    fn get_move_from_engine(&mut self) -> Option<BestMove> {
         let side = self.side();
+        let is_white = side == self.white_engine;
         let engine: &mut EngineComm = &mut self.engines[side];
-        while retry_count < 2 {
-            if self.time_left[side] <= 0.0 {
-                engine.stop();
-                self.game.lost_on_time(self.side() == self.white_engine);
+        // An engine can send its move and exit before the move has been read, so all of its
+        // output is read before it's taken to have crashed
+        let exited = !engine.is_alive();
+        if exited {
+            engine.read_remaining_output();
+        }
+        let best_move = match self.search_mode {
+            SearchMode::Movetime => engine.best_move(Duration::ZERO),
+            SearchMode::Depth => engine.poll_best_move(),
+        };
+        if let Some(best_move) = best_move {
+            // The engine is charged for the time it took to send its move, which could have
+            // been longer than the time it had left
+            self.finish_search(side);
+            if self.search_mode == SearchMode::Movetime && self.clock.is_flagged(side) {
+                self.game.lost_on_time(is_white);
                 return None;
             }
-            if let Some(best_move) = engine.best_move() {
-                assert!(best_move.len() == 4 || best_move.len() == 5, "Length: {}", best_move.len());
-                if best_move == "a8a8P" {
-                    retry_count += 1;
-                    eprintln!("Retry because of 'a8a8P'");
-                    continue;
-                }
-                // println!("[{}] '{}'", best_move.len(), &best_move);
-                return Some(best_move);
-            } else {
-                eprintln!("Retry because NO MOVE was sent by engine.");
-                retry_count += 1;
+            // Moves that aren't legal or not in UCI notation are rejected by 'play'
+            return Some(best_move);
+        }
+        // An engine that crashed will never send its move, so it forfeits right away
+        if exited {
+            self.forfeit_missing_move(side);
+            return None;
+        }
+        // A deep search can take any amount of time, so it's only taken to be stuck once the
+        // engine stops sending output
+        if self.search_mode == SearchMode::Depth {
+            if engine.silent_for() >= Self::DEPTH_SILENCE_LIMIT {
+                self.forfeit_missing_move(side);
+            }
+            return None;
+        }
+        let budget = self.movetime.mul_f32(self.watchdog_factor);
+        if self.clock.now().saturating_sub(self.search_start) < budget {
+            return None;
+        }
+        // Waiting any longer could stall the match, so the engine forfeits instead
+        self.forfeit_missing_move(side);
+        None
+    }
+
+    // The engine on move won't send its move. If it exited, it forfeits like an engine that sent
+    // a move it can't play. Otherwise it's stuck, so its search is stopped and it forfeits for
+    // not responding.
+    fn forfeit_missing_move(&mut self, side: usize) {
+        self.clock.stop();
+        let is_white = side == self.white_engine;
+        let budget = self.movetime.mul_f32(self.watchdog_factor);
+        let engine = &mut self.engines[side];
+        let exited = !engine.is_alive();
+        let problem = if exited {
+            "exited".to_string()
+        } else if self.search_mode == SearchMode::Depth {
+            engine.stop();
+            format!("sent nothing for {} s", Self::DEPTH_SILENCE_LIMIT.as_secs())
+        } else {
+            engine.stop();
+            format!("didn't send a move within {} ms", budget.as_millis())
+        };
+        eprintln!(
+            "[ERROR] '{}' {} and forfeits. Its last output was: '{}'",
+            self.names[side], problem, engine.last_output()
+        );
+        if exited {
+            self.game.illegal_move(is_white);
+        } else {
+            self.game.no_response(is_white);
+        }
+    }
+
+    fn finish_search(&mut self, side: usize) {
+        self.search_time += self.clock.stop();
+        self.move_times[side].record(self.search_time);
+    }
+
+}
+
+fn draw_cause(state: GameState) -> &'static str {
+    match state {
+        GameState::DrawByStalemate => "stalemate",
+        GameState::DrawByFiftyMoveRule => "fifty-move rule",
+        GameState::DrawByThreefoldRepetition => "repetition",
+        GameState::DrawByInsufficientMaterial => "insufficient material",
+        GameState::DrawByAdjudication => "adjudication",
+        _ => "other",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chess::attack;
+    use chess::consts::PieceColor;
+    use chess::fen;
+    use chess::moves;
+    use chess::zobrist;
+
+    use crate::comm::EngineComm;
+    use crate::game::{Game, GameState};
+    use crate::match_state::MatchState;
+    use crate::game_manager::{
+        self, Adjudication, AdjudicationPolicy, GameManager, MoveTimes, Sprt, SprtResult, Standings, StopCondition,
+    };
+    use crate::pgn;
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use std::time::Duration;
+
+    #[test]
+    fn fen_access_stays_in_bounds() {
+        let fens = game_manager::parse_fens("fen 1\n\nfen 2\r\n  fen 3  \n\n");
+        assert_eq!(fens, ["fen 1", "fen 2", "fen 3"]);
+
+        // Every position is played twice
+        let played: Vec<_> = (0..8).map(|i| game_manager::fen_for_game(&fens, i)).collect();
+        assert_eq!(played, [
+            Some("fen 1"), Some("fen 1"), Some("fen 2"), Some("fen 2"),
+            Some("fen 3"), Some("fen 3"), None, None,
+        ]);
+
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..1000 {
+            let fen = game_manager::pick_fen(&fens, &mut rng).unwrap();
+            assert!(fens.iter().any(|f| f == fen));
+        }
+        assert_eq!(game_manager::pick_fen(&[], &mut rng), None);
+    }
+
+    #[test]
+    fn book_positions_are_the_ends_of_the_openings() {
+        let attack_info = attack::shared_attack_info();
+        let zobrist_info = zobrist::shared_zobrist_info();
+        let book = "[Event \"?\"]\n\n1. e4 e5 2. Nf3 *\n\n\
+                    [FEN \"4k3/8/8/8/8/8/4P3/4K3 w - - 0 1\"]\n\n1. e4 Kd7 1/2-1/2\n\n\
+                    1. f3 e5 2. g4 Qh4# 0-1\n";
+        let openings = pgn::load(book, attack_info, zobrist_info).unwrap();
+        assert_eq!(game_manager::book_positions(&openings), [
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2",
+            "8/3k4/8/8/4P3/8/8/4K3 w - - 1 2",
+        ]);
+    }
+
+    #[test]
+    fn finished_fens_are_skipped() {
+        let attack_info = attack::shared_attack_info();
+        let zobrist_info = zobrist::shared_zobrist_info();
+        let fens = game_manager::parse_fens(
+            "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3\n\
+             4k3/8/8/8/8/8/4P3/4K3 w - - 0 1\n\
+             7k/5Q2/6K1/8/8/8/8/8 b - - 0 1\n\
+             not a fen\n"
+        );
+        assert_eq!(game_manager::playable_fens(fens, attack_info, zobrist_info), [
+            "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1",
+            "not a fen",
+        ]);
+    }
+
+    #[test]
+    fn adjudicate_from_the_scores() {
+        let attack_info = attack::shared_attack_info();
+        let zobrist_info = zobrist::shared_zobrist_info();
+        let moves = ["g1f3", "g8f6", "b1c3", "b8c6", "e2e3", "e7e6", "d2d3", "d7d6"];
+        // Scores are from the point of view of the engine that played the move
+        let play = |fen: &str, scores: &[&str]| {
+            let mut game = Game::from_fen("White", "Black", fen, attack_info, zobrist_info);
+            for (uci, score) in moves.iter().zip(scores) {
+                let mv = moves::from_uci(uci, game.board_after_last_move().unwrap(), attack_info).unwrap();
+                assert!(game.make_move(mv, attack_info, zobrist_info));
+                game.set_last_score(Some(score.to_string()).filter(|s| !s.is_empty()));
             }
+            game
+        };
+        let late = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 40";
+        let both = Adjudication { policy: AdjudicationPolicy::Both, ..Adjudication::default() };
+
+        let drawn = ["cp 5", "cp -3", "cp 0", "cp 10", "cp -10", "cp 2", "cp 4", "cp 1"];
+        assert_eq!(both.verdict(&play(late, &drawn[..7])), None);
+        assert_eq!(both.verdict(&play(late, &drawn)), Some(None));
+        assert_eq!(Adjudication::default().verdict(&play(late, &drawn)), None);
+        let wins_only = Adjudication { policy: AdjudicationPolicy::WinOnly, ..both };
+        assert_eq!(wins_only.verdict(&play(late, &drawn)), None);
+        // Too early in the game to call it a draw
+        assert_eq!(both.verdict(&play(fen::FEN_POSITIONS[1], &drawn)), None);
+
+        let white_wins = ["cp 1200", "cp -1100", "cp 1500", "mate -4", "mate 3", "mate -2"];
+        assert_eq!(wins_only.verdict(&play(fen::FEN_POSITIONS[1], &white_wins)), Some(Some(PieceColor::Light)));
+        let black_wins = ["cp -1200", "cp 1100", "mate -5", "cp 2000", "cp -1000", "mate 1"];
+        assert_eq!(both.verdict(&play(fen::FEN_POSITIONS[1], &black_wins)), Some(Some(PieceColor::Dark)));
+        // A move without a score breaks the streak
+        let unscored = ["cp 1200", "cp -1100", "", "cp -1100", "cp 1500", "cp -1100", "cp 1200"];
+        assert_eq!(both.verdict(&play(fen::FEN_POSITIONS[1], &unscored)), None);
+    }
+
+    #[test]
+    fn standings_count_draws_by_cause() {
+        let mut standings = Standings::default();
+        standings.wins[1] += 1;
+        standings.add_draw(GameState::DrawByThreefoldRepetition);
+        standings.add_draw(GameState::DrawByThreefoldRepetition);
+        standings.add_draw(GameState::DrawByStalemate);
+        assert_eq!(standings.draws(), 3);
+        assert_eq!(standings.games(), 4);
+        assert_eq!(standings.draws_by(GameState::DrawByThreefoldRepetition), 2);
+        assert_eq!(standings.draws_by(GameState::DrawByStalemate), 1);
+        assert_eq!(standings.draws_by(GameState::DrawByFiftyMoveRule), 0);
+        assert_eq!(standings.score(0), 1.5);
+        assert_eq!(standings.score(1), 2.5);
+    }
+
+    #[test]
+    fn number_identical_engine_names() {
+        assert_eq!(game_manager::player_names("Stockfish", "Stockfish"), ["Stockfish #1", "Stockfish #2"]);
+        assert_eq!(game_manager::player_names("Stockfish", "Haze"), ["Stockfish", "Haze"]);
+    }
+
+    #[test]
+    fn stop_after_games_or_margin() {
+        let mut standings = Standings::default();
+        standings.wins[0] = 3;
+        standings.add_draw(GameState::DrawByStalemate);
+        assert!(!StopCondition::default().is_met(&standings));
+
+        let by_games = StopCondition { max_games: Some(4), ..StopCondition::default() };
+        assert!(by_games.is_met(&standings));
+        let by_games = StopCondition { max_games: Some(5), ..StopCondition::default() };
+        assert!(!by_games.is_met(&standings));
+
+        let by_margin = StopCondition { win_margin: Some(3.0), ..StopCondition::default() };
+        assert!(by_margin.is_met(&standings));
+        let by_margin = StopCondition { win_margin: Some(3.5), ..StopCondition::default() };
+        assert!(!by_margin.is_met(&standings));
+        // Either engine can be the one ahead
+        standings.wins = [0, 4];
+        assert!(by_margin.is_met(&standings));
+    }
+
+    #[test]
+    fn rounds_pair_up_the_games() {
+        let fens = game_manager::parse_fens("fen 1\nfen 2\n");
+        // Each position is played by both engines as white, the first engine starting
+        let games: Vec<_> = (0..5).map(|i| (game_manager::fen_for_game(&fens, i), game_manager::white_for_game(i))).collect();
+        assert_eq!(games, [(Some("fen 1"), 0), (Some("fen 1"), 1), (Some("fen 2"), 0), (Some("fen 2"), 1), (None, 0)]);
+
+        let mut standings = Standings::default();
+        for points in [1.5, 1.0, 2.0, 0.5] {
+            standings.add_pair(points);
+        }
+        assert_eq!((standings.pair_wins, standings.pair_draws, standings.pairs()), ([2, 1], 1, 4));
+        let by_rounds = StopCondition { max_rounds: Some(4), ..StopCondition::default() };
+        assert!(by_rounds.is_met(&standings));
+        let by_rounds = StopCondition { max_rounds: Some(5), ..StopCondition::default() };
+        assert!(!by_rounds.is_met(&standings));
+    }
+
+    #[test]
+    fn human_takes_the_place_of_the_second_engine() {
+        // The first engine plays white against a person playing black, in every game
+        assert_eq!(game_manager::human_white_engine(Some(PieceColor::Dark)), Some(0));
+        assert_eq!(game_manager::human_white_engine(Some(PieceColor::Light)), Some(1));
+        assert_eq!(game_manager::human_white_engine(None), None);
+    }
+
+    #[test]
+    fn elo_difference_from_the_score() {
+        let mut standings = Standings::default();
+        assert_eq!(standings.elo_difference(), None);
+        standings.wins = [6, 4];
+        standings.draws[0] = 10;
+        let (elo, margin) = standings.elo_difference().unwrap();
+        assert!((elo - 34.9).abs() < 0.1, "{}", elo);
+        assert!((margin - 111.4).abs() < 0.1, "{}", margin);
+        // An even score is no difference at all
+        standings.wins = [5, 5];
+        assert_eq!(standings.elo_difference().map(|(elo, _)| elo), Some(0.0));
+        // Without a game lost, the difference has no upper bound
+        standings.wins = [3, 0];
+        standings.draws[0] = 0;
+        assert_eq!(standings.elo_difference(), None);
+    }
+
+    #[test]
+    fn sprt_accepts_either_hypothesis() {
+        let sprt = Sprt::parse("0,10").unwrap();
+        let (lower, upper) = sprt.bounds();
+        assert!((lower + 2.944).abs() < 0.001 && (upper - 2.944).abs() < 0.001, "{} {}", lower, upper);
+        let standings = |wins: [u32; 2], draws: u32| Standings { wins, draws: [draws, 0, 0, 0, 0], ..Standings::default() };
+
+        assert_eq!(sprt.llr(&standings([0, 0], 0)), 0.0);
+        let llr = sprt.llr(&standings([60, 40], 100));
+        assert!((llr - 1.005).abs() < 0.001, "{}", llr);
+        assert_eq!(sprt.result(&standings([60, 40], 100)), None);
+        let llr = sprt.llr(&standings([40, 60], 100));
+        assert!((llr + 1.343).abs() < 0.001, "{}", llr);
+        // A run of wins has a variance too
+        let llr = sprt.llr(&standings([10, 0], 0));
+        assert!((llr - 1.288).abs() < 0.001, "{}", llr);
+
+        let better = standings([600, 400], 1000);
+        assert_eq!(sprt.result(&better), Some(SprtResult::H1Accepted));
+        assert_eq!(sprt.summary(&better), "SPRT [0, 10]: LLR 10.05 (-2.94, 2.94), H1 accepted");
+        let worse = standings([400, 600], 1000);
+        assert_eq!(sprt.result(&worse), Some(SprtResult::H0Accepted));
+
+        let stop_condition = StopCondition { sprt: Some(sprt), ..StopCondition::default() };
+        assert!(stop_condition.is_met(&better) && stop_condition.is_met(&worse));
+        assert!(!stop_condition.is_met(&standings([60, 40], 100)));
+    }
+
+    #[test]
+    fn likelihood_of_superiority() {
+        let mut standings = Standings::default();
+        assert_eq!(standings.likelihood_of_superiority(), None);
+        assert_eq!(standings.elo_summary(), None);
+        // Only draws say nothing about which engine is stronger
+        standings.draws[0] = 4;
+        assert_eq!(standings.likelihood_of_superiority(), None);
+        assert_eq!(standings.elo_summary().unwrap(), "Elo difference: +0.0 +/- 0.0");
+
+        standings.wins = [6, 4];
+        let los = standings.likelihood_of_superiority().unwrap();
+        assert!((los - 0.7365).abs() < 0.001, "{}", los);
+        standings.wins = [5, 5];
+        assert_eq!(standings.likelihood_of_superiority(), Some(0.5));
+        standings.wins = [0, 3];
+        let los = standings.likelihood_of_superiority().unwrap();
+        assert!((los - 0.0416).abs() < 0.001, "{}", los);
+
+        // One engine with every point
+        standings.draws[0] = 0;
+        assert_eq!(standings.elo_summary().unwrap(), "Elo difference: -inf, LOS: 4.2 %");
+        standings.wins = [3, 0];
+        assert_eq!(standings.elo_summary().unwrap(), "Elo difference: +inf, LOS: 95.8 %");
+    }
+
+    #[test]
+    fn move_times_keep_running_stats() {
+        let mut times = MoveTimes::default();
+        assert_eq!(times.average(), Duration::ZERO);
+        for ms in [900, 1200, 1050] {
+            times.record(Duration::from_millis(ms));
+        }
+        assert_eq!(times.count, 3);
+        assert_eq!(times.min, Duration::from_millis(900));
+        assert_eq!(times.max, Duration::from_millis(1200));
+        assert_eq!(times.total, Duration::from_millis(3150));
+        assert_eq!(times.average(), Duration::from_millis(1050));
+    }
+
+    // Writes an engine that plays 'moves' in order, whatever the position is. A move can be
+    // followed by the reply it expects, e.g. "e2e4,e7e5", which it ponders on when asked to.
+    // "crash" makes it exit when it's asked for a move, and "slow" makes it think for a second
+    // before sending the next one. The commands it gets are logged to the path of the engine with
+    // ".log" added.
+    #[cfg(unix)]
+    fn mock_engine(name: &str, moves: &str) -> String {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = std::env::temp_dir().join(format!("engine-gui-mock-{}-{}", std::process::id(), name));
+        let path = path.to_string_lossy().into_owned();
+        let script = format!(
+            "#!/bin/sh\n\
+             set -- {}\n\
+             pondering=\n\
+             while read -r cmd; do\n\
+               echo \"$cmd\" >> '{}.log'\n\
+               case \"$cmd\" in\n\
+                 uci) echo 'id name {}'; echo 'option name Ponder type check default false'; echo 'uciok' ;;\n\
+                 isready) echo 'readyok' ;;\n\
+                 'go ponder'*) pondering=1 ;;\n\
+                 go*|ponderhit)\n\
+                   [ \"$1\" = crash ] && exit 1\n\
+                   [ \"$1\" = slow ] && {{ echo 'info depth 1'; sleep 1; shift; }}\n\
+                   pondering=\n\
+                   echo \"info depth 1 score cp 0 pv $(echo \"$1\" | tr , ' ')\"\n\
+                   echo \"bestmove ${{1%%,*}}\"\n\
+                   shift ;;\n\
+                 stop) [ -n \"$pondering\" ] && echo \"bestmove ${{1%%,*}}\"; pondering= ;;\n\
+                 quit) exit 0 ;;\n\
+               esac\n\
+             done\n",
+            moves, path, name
+        );
+        std::fs::write(&path, script).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn step_through_a_game() {
+        let attack_info = attack::shared_attack_info();
+        let zobrist_info = zobrist::shared_zobrist_info();
+        let paths = [mock_engine("White", "f2f3 g2g4"), mock_engine("Black", "e7e5 d8h4")];
+        let engine_a = EngineComm::new(&paths[0]).unwrap();
+        let engine_b = EngineComm::new(&paths[1]).unwrap();
+        let mut manager = GameManager::new(engine_a, engine_b, attack_info, zobrist_info);
+        manager.set_movetime(Duration::from_millis(100));
+
+        // The game doesn't have to be started, and each step plays exactly one move
+        let played: Vec<String> = (0..4).filter_map(|_| manager.step(attack_info, zobrist_info))
+            .map(moves::to_uci).collect();
+        assert_eq!(played, ["f2f3", "e7e5", "g2g4", "d8h4"]);
+        let game = manager.current_game();
+        assert_eq!(game.state(), GameState::DarkWinByCheckmate);
+        assert_eq!(game.score_at(3).map(String::as_str), Some("cp 0"));
+        assert_eq!(manager.step(attack_info, zobrist_info), None);
+        assert_eq!(manager.move_times(0).count, 2);
+
+        drop(manager);
+        for path in paths {
+            let _ = std::fs::remove_file(format!("{}.log", path));
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn ponder_on_the_expected_reply() {
+        let attack_info = attack::shared_attack_info();
+        let zobrist_info = zobrist::shared_zobrist_info();
+        // White guesses both of black's replies, and black guesses wrong, so it has to search again
+        let paths = [mock_engine("PonderWhite", "f2f3,e7e5 g2g4,d7d5"), mock_engine("PonderBlack", "e7e5,h2h3 d8h4")];
+        let engine_a = EngineComm::new(&paths[0]).unwrap();
+        let engine_b = EngineComm::new(&paths[1]).unwrap();
+        let mut manager = GameManager::new(engine_a, engine_b, attack_info, zobrist_info);
+        manager.set_movetime(Duration::from_millis(20));
+        manager.set_ponder(true);
+
+        manager.toggle_playing();
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while manager.current_game().is_ongoing() && std::time::Instant::now() < deadline {
+            manager.play(attack_info, zobrist_info);
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        manager.check_state();
+        assert_eq!(manager.current_game().state(), GameState::DarkWinByCheckmate);
+        drop(manager);
+
+        let logs = paths.clone().map(|path| std::fs::read_to_string(format!("{}.log", path)).unwrap());
+        assert!(logs[0].contains("setoption name Ponder value true"));
+        assert!(logs[0].contains("moves e7e5\ngo ponder movetime 20\nponderhit\n"));
+        // White was still pondering when it got mated
+        assert!(logs[0].contains("moves d7d5\ngo ponder movetime 20\nstop\n"));
+        assert!(logs[1].contains("moves h2h3\ngo ponder movetime 20\nstop\n"));
+        assert!(!logs[1].contains("ponderhit"));
+        for path in paths {
+            let _ = std::fs::remove_file(format!("{}.log", path));
+            let _ = std::fs::remove_file(path);
         }
-        eprintln!("[ERROR] Engine, '{}' couldn't give a legal move", engine.name());
-        return None;
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn games_set_up_by_hand_start_a_new_round() {
+        let attack_info = attack::shared_attack_info();
+        let zobrist_info = zobrist::shared_zobrist_info();
+        let paths = [mock_engine("RoundFirst", "e2e4 e2e4"), mock_engine("RoundSecond", "e7e5")];
+        let engine_a = EngineComm::new(&paths[0]).unwrap();
+        let engine_b = EngineComm::new(&paths[1]).unwrap();
+        let mut manager = GameManager::new(engine_a, engine_b, attack_info, zobrist_info);
+        manager.set_movetime(Duration::from_millis(100));
+        let pgn_file = format!("{}.pgn", paths[0]);
+        manager.pgn_file = pgn_file.clone();
+        let start_fen = fen::FEN_POSITIONS[1];
+
+        // Setting up the rematch of a round drops the points of its first game
+        manager.step(attack_info, zobrist_info);
+        assert!(manager.start_new_game(&[], attack_info, zobrist_info));
+        assert_eq!((manager.game_count, manager.white_engine()), (1, game_manager::SECOND));
+        manager.round_points = Some(1.0);
+        manager.start_game_from_fen(start_fen, attack_info, zobrist_info);
+        assert_eq!((manager.game_count, manager.white_engine()), (2, game_manager::FIRST));
+        assert_eq!(manager.round_points, None);
+
+        // A round without any moves yet is taken over
+        manager.start_game_from_fen(start_fen, attack_info, zobrist_info);
+        assert_eq!(manager.game_count, 2);
+        manager.step(attack_info, zobrist_info);
+        manager.start_game_from_fen(start_fen, attack_info, zobrist_info);
+        assert_eq!((manager.game_count, manager.white_engine()), (4, game_manager::FIRST));
+
+        drop(manager);
+        let _ = std::fs::remove_file(pgn_file);
+        for path in paths {
+            let _ = std::fs::remove_file(format!("{}.log", path));
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn continue_an_unfinished_game() {
+        let attack_info = attack::shared_attack_info();
+        let zobrist_info = zobrist::shared_zobrist_info();
+        let pgn_file = std::env::temp_dir().join(format!("engine-gui-resume-{}.pgn", std::process::id()));
+        let pgn_file = pgn_file.to_string_lossy().into_owned();
+        let engine_paths = [mock_engine("ResumeWhite", "f2f3"), mock_engine("ResumeBlack", "e7e5")];
+        // The engines are started again to continue the match, so they play the rest of the moves
+        let resumed_paths = [mock_engine("ResumedWhite", "g2g4"), mock_engine("ResumedBlack", "d8h4")];
+        let new_manager = |paths: &[String; 2]| {
+            let engine_a = EngineComm::new(&paths[0]).unwrap();
+            let engine_b = EngineComm::new(&paths[1]).unwrap();
+            let mut manager = GameManager::new(engine_a, engine_b, attack_info, zobrist_info);
+            manager.set_movetime(Duration::from_millis(100));
+            manager.pgn_file = pgn_file.clone();
+            manager
+        };
+
+        // The unfinished game isn't added to the match PGN, only its moves are kept
+        let mut manager = new_manager(&engine_paths);
+        manager.step(attack_info, zobrist_info);
+        manager.step(attack_info, zobrist_info);
+        manager.save_finished_game(attack_info, zobrist_info);
+        let state = manager.match_state(engine_paths.clone());
+        assert_eq!(state.moves, ["f2f3", "e7e5"]);
+        assert!(!std::path::Path::new(&pgn_file).exists());
+        drop(manager);
+
+        let mut manager = new_manager(&resumed_paths);
+        manager.resume(&state, &[], attack_info, zobrist_info).unwrap();
+        assert_eq!(manager.current_game().move_count(), 2);
+        manager.step(attack_info, zobrist_info);
+        manager.step(attack_info, zobrist_info);
+        assert_eq!(manager.current_game().state(), GameState::DarkWinByCheckmate);
+        manager.save_finished_game(attack_info, zobrist_info);
+        let state = manager.match_state(engine_paths.clone());
+        assert!(state.current_saved && state.moves.is_empty());
+        let games = pgn::load(&std::fs::read_to_string(&pgn_file).unwrap(), attack_info, zobrist_info).unwrap();
+        assert_eq!(games.len(), 1);
+        assert_eq!(games[0].move_count(), 4);
+        drop(manager);
+
+        let bad_state = MatchState { moves: vec!["e2e5".to_string()], current_over: false, ..state };
+        assert!(new_manager(&resumed_paths).resume(&bad_state, &[], attack_info, zobrist_info).is_err());
+
+        let _ = std::fs::remove_file(&pgn_file);
+        for path in engine_paths.iter().chain(&resumed_paths) {
+            let _ = std::fs::remove_file(format!("{}.log", path));
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn deep_searches_are_not_timed() {
+        let attack_info = attack::shared_attack_info();
+        let zobrist_info = zobrist::shared_zobrist_info();
+        let paths = [mock_engine("Slow", "slow e2e4"), mock_engine("Quick", "e7e5")];
+        let engine_a = EngineComm::new(&paths[0]).unwrap();
+        let engine_b = EngineComm::new(&paths[1]).unwrap();
+        let mut manager = GameManager::new(engine_a, engine_b, attack_info, zobrist_info);
+        // The search takes far longer than the watchdog allows a timed one
+        manager.set_movetime(Duration::from_millis(50));
+        manager.set_search_mode(game_manager::SearchMode::Depth);
+
+        let played = manager.step(attack_info, zobrist_info).map(moves::to_uci);
+        assert_eq!(played.as_deref(), Some("e2e4"));
+        assert!(manager.current_game().is_ongoing());
+
+        drop(manager);
+        for path in paths {
+            let _ = std::fs::remove_file(format!("{}.log", path));
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn forfeit_when_an_engine_exits() {
+        let attack_info = attack::shared_attack_info();
+        let zobrist_info = zobrist::shared_zobrist_info();
+        let paths = [mock_engine("Crashing", "crash"), mock_engine("Survivor", "e7e5")];
+        let engine_a = EngineComm::new(&paths[0]).unwrap();
+        let engine_b = EngineComm::new(&paths[1]).unwrap();
+        let mut manager = GameManager::new(engine_a, engine_b, attack_info, zobrist_info);
+        // The watchdog would end the game later, as a forfeit for not sending a move
+        manager.set_movetime(Duration::from_millis(300));
+
+        manager.toggle_playing();
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while manager.current_game().is_ongoing() && std::time::Instant::now() < deadline {
+            manager.play(attack_info, zobrist_info);
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert!(manager.check_state());
+        assert_eq!(manager.current_game().state(), GameState::LightIllegalMove);
+        drop(manager);
+        for path in paths {
+            let _ = std::fs::remove_file(format!("{}.log", path));
+            let _ = std::fs::remove_file(path);
+        }
+    }
 }
@@ -1,202 +1,33 @@
 use raylib::prelude::*;
 
 use chess::attack::AttackInfo;
-use chess::bb::BBUtil;
 use chess::board::Board;
-use chess::consts::{Piece, Sq};
+use chess::consts::{Piece, PieceColor, Sq};
 use chess::fen;
-use chess::moves::{Move, MoveUtil};
+use chess::moves::{self, Move, MoveUtil};
 use chess::move_gen::{self, MoveList};
 use chess::zobrist::ZobristInfo;
-use chess::{COL, ROW, SQ};
+use chess::{ROW, SQ};
 
-use crate::comm::EngineComm;
+use crate::comm::{self, EngineComm, EngineOptions, Score, SearchInfo};
+use crate::config::{Config, CONFIG_FILE};
+use crate::eco::{EcoTable, Opening, ECO_FILE};
+use crate::error::GuiError;
 use crate::game::{Game, GameState};
-use crate::utils::Button;
-use crate::game_manager::GameManager;
+use crate::utils::{Button, TextInput};
+use crate::game_manager::{self, Adjudication, GameManager, MatchSettings, SearchMode};
+use crate::match_state::{MatchState, MATCH_STATE_FILE};
+use crate::paths::Paths;
+use crate::pgn;
+use crate::render::{self, CoordStyle, Theme};
 
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 const BACKGROUND: Color = Color::new(30, 30, 30, 255);
 const PROMOTION_BACKGROUND: Color = Color::new(46, 46, 46, 220);
-
-const LIGHT_SQ_CLR: Color = Color::new(118, 150, 86, 255);
-const LIGHT_SELECTED_CLR: Color = Color::new(187, 204, 68, 255);
-const DARK_SQ_CLR: Color = Color::new(238, 238, 210, 255);
-const DARK_SELECTED_CLR: Color = Color::new(244, 246, 128, 255);
-
-// TODO: display checks
-fn draw_board(d: &mut RaylibDrawHandle, sec: &Rectangle, source: Option<Sq>, target: Option<Sq>) {
-    let mut cell_size = Vector2::one();
-    cell_size.scale(sec.width / 8.0);
-
-    for r in 0..8 {
-        for f in 0..8 {
-            let light_sq = (r + f) % 2 != 0;
-            let mut sq_clr = if light_sq { LIGHT_SQ_CLR } else { DARK_SQ_CLR };
-            if let Some(sq) = source {
-                let sq = sq as usize;
-                if sq == SQ!(r, f) {
-                    sq_clr = if (ROW!(sq) + COL!(sq)) % 2 != 0 { LIGHT_SELECTED_CLR } else { DARK_SELECTED_CLR };
-                }
-            }
-            if let Some(sq) = target {
-                let sq = sq as usize;
-                if sq == SQ!(r, f) {
-                    sq_clr = if (ROW!(sq) + COL!(sq)) % 2 != 0 { LIGHT_SELECTED_CLR } else { DARK_SELECTED_CLR };
-                }
-            }
-            /*
-            if let Some(sq) = b_ui.check {
-                let sq = sq as usize;
-                if sq == SQ!(r, f) {
-                    let check_clr = Color::new(189, 55, 55, 255);
-                    sq_clr = Color::color_alpha_blend(&sq_clr, &check_clr, &Color::new(255, 255, 255, 200));
-                }
-            }
-            */
-
-            d.draw_rectangle_v(
-                Vector2::new(
-                    sec.x + (f as f32) * cell_size.x,
-                    sec.y + (r as f32) * cell_size.y
-                ),
-                cell_size,
-                sq_clr
-            );
-        }
-    }
-}
-
-fn draw_coords(d: &mut RaylibDrawHandle, font: &Font, sec: &Rectangle) {
-    // File markings
-    let sq_size = sec.width / 8.0;
-    for f in 0..8 {
-        // row(r) = 7
-        let text_color = if (7+f) % 2 != 0 { DARK_SQ_CLR } else { LIGHT_SQ_CLR };
-        d.draw_text_ex(
-            font,
-            &format!("{}", (b'a' + f) as char),
-            Vector2::new(
-                sec.x + f as f32 * sq_size + (sq_size * 0.83),
-                sec.y + 0.965*sec.height
-            ),
-            font.baseSize as f32 * 0.5,
-            0.0,
-            text_color
-        );
-    }
-    // Row markings
-    for r in 0..8 {
-        // file(f) = 0
-        let text_color = if (r+0) % 2 != 0 { DARK_SQ_CLR } else { LIGHT_SQ_CLR };
-        d.draw_text_ex(
-            font,
-            &format!("{}", 8-r),
-            Vector2::new(
-                sec.x + 0.01*sec.width,
-                sec.y + r as f32 * sq_size + (0.01 * sec.height),
-            ),
-            font.baseSize as f32 * 0.5,
-            0.0,
-            text_color
-        );
-    }
-}
-
-fn draw_piece(d: &mut RaylibDrawHandle, tex: &Texture2D, target: Rectangle, piece: Piece) {
-    let (color, kind) = Piece::to_tuple(Some(piece));
-    let source_rect = Rectangle::new(
-        (kind as i32 * tex.width() / 6) as f32,
-        (color as i32 * tex.height() / 2) as f32,
-        (tex.width() / 6) as f32,
-        (tex.height() / 2) as f32,
-    );
-    d.draw_texture_pro(
-        &tex,
-        source_rect,
-        target,
-        Vector2::zero(),
-        0.0,
-        Color::WHITE,
-    );
-}
-
-fn piece_rect_on_board(sec: &Rectangle, sq: usize) -> Rectangle {
-    let min_side = f32::min(sec.width, sec.height);
-    let mut cell_size = Vector2::one();
-    cell_size.scale(min_side / 8.0);
-
-    let r = ROW!(sq);
-    let f = COL!(sq);
-    Rectangle::new(
-        sec.x + (f as f32) * cell_size.x,
-        sec.y + (r as f32) * cell_size.y,
-        cell_size.x,
-        cell_size.y
-    )
-}
-
-fn draw_markers(d: &mut RaylibDrawHandle, board: &Board, tex: &Texture2D, sec: &Rectangle, game_state: GameState) {
-    let light_king = board.pos.piece[Piece::LK as usize].lsb();
-    let dark_king = board.pos.piece[Piece::DK as usize].lsb();
-    let tex_ind = match game_state {
-        GameState::LightWinByCheckmate => Some((0, 1)),
-        GameState::DarkWinByCheckmate => Some((1, 0)),
-        GameState::LightLostOnTime => Some((6, 0)),
-        GameState::DarkLostOnTime => Some((0, 7)),
-        GameState::Ongoing => None,
-        _ => Some((2, 3))
-    };
-    if tex_ind.is_none() { return; }
-    let (l_ind, d_ind) = tex_ind.unwrap();
-    // This texture has 8 icons in it so each 'frame' has a width of 1/8 of the total width
-    let frame_width = tex.width() as f32 / 8.0;
-    let l_source_rect = Rectangle {
-        x: l_ind as f32 * frame_width,
-        y: 0.0,
-        width: frame_width,
-        height: tex.height() as f32
-    };
-    let target_width = (sec.width / 8.0) * 0.4;
-    let target_height = tex.height() as f32 * target_width/frame_width;
-    let l_target_rect = Rectangle {
-        x: sec.x + (COL!(light_king) as f32 * sec.width / 8.0) + (0.9 * sec.width / 8.0) - target_width / 2.0,
-        y: sec.y + (ROW!(light_king) as f32 * sec.height / 8.0) + (0.05 * sec.height / 8.0) - target_height / 2.0,
-        width: target_width,
-        height: target_height
-    };
-    d.draw_texture_pro(
-        &tex,
-        l_source_rect,
-        l_target_rect,
-        Vector2::zero(),
-        0.0,
-        Color::WHITE,
-    );
-
-    let d_source_rect = Rectangle {
-        x: d_ind as f32 * frame_width,
-        y: 0.0,
-        width: frame_width,
-        height: tex.height() as f32
-    };
-    let target_height = tex.height() as f32 * target_width/frame_width;
-    let d_target_rect = Rectangle {
-        x: sec.x + (COL!(dark_king) as f32 * sec.width / 8.0) + (0.9 * sec.width / 8.0) - target_width / 2.0,
-        y: sec.y + (ROW!(dark_king) as f32 * sec.height / 8.0) + (0.05 * sec.height / 8.0) - target_height / 2.0,
-        width: target_width,
-        height: target_height
-    };
-    d.draw_texture_pro(
-        &tex,
-        d_source_rect,
-        d_target_rect,
-        Vector2::zero(),
-        0.0,
-        Color::WHITE,
-    );
-}
+const PROMPT_BACKGROUND: Color = Color::new(46, 46, 46, 235);
+const HELP_BACKGROUND: Color = Color::new(20, 20, 20, 215);
+const GRAPH_BACKGROUND: Color = Color::new(22, 22, 22, 255);
 
 /* fn old_draw_players_name(d: &mut RaylibDrawHandle, font: &Font, sec: &Rectangle, light_name: &str, dark_name: &str) {
     let margin = Vector2::new(sec.width * 0.01, sec.height * 0.03);
@@ -269,44 +100,115 @@ fn draw_players_name(d: &mut RaylibDrawHandle, font: &Font, sec: &Rectangle, nam
     d.draw_text_ex(&font, &time_str, text_pos, font.baseSize as f32, 0.0, fg);
 }
 
-fn draw_moves(s: &mut impl RaylibDraw, sec: &mut Rectangle, font: &Font, game: &Game, current: usize) -> Rectangle {
-    let mut move_counter = 1;
+// The moves flow down the first column and wrap into the next one once the visible height of
+// the move list is filled. Only when every column is full does the list grow past the visible
+// height and rely on scrolling.
+struct MoveListLayout {
+    rows_per_column: usize,
+    column_width: f32,
+    gap: f32,
+    each_height: f32,
+}
+
+impl MoveListLayout {
+    const MAX_COLUMNS: usize = 3;
+    // Where white's and black's moves start, as a fraction of the column width
+    const WHITE_MOVE_X: f32 = 0.25;
+    const BLACK_MOVE_X: f32 = 0.675;
+
+    fn new(sec: &Rectangle, view_height: f32, font_size: f32, move_count: usize) -> Self {
+        let gap = font_size * 1.5;
+        let each_height = font_size * 2.0;
+        // A column needs enough room for the move number and two moves
+        let min_column_width = font_size * 8.0;
+        let columns = ((sec.width / min_column_width) as usize).clamp(1, Self::MAX_COLUMNS);
+        let visible_rows = (((view_height - gap) / each_height) as usize).max(1);
+        let total_rows = move_count.div_ceil(2);
+        let rows_per_column = visible_rows.max(total_rows.div_ceil(columns));
+        Self {
+            rows_per_column,
+            column_width: sec.width / columns as f32,
+            gap,
+            each_height,
+        }
+    }
+
+    // Top left corner of the column and the baseline 'y' of the text in the given row
+    fn row_pos(&self, sec: &Rectangle, row: usize) -> Vector2 {
+        let column = row / self.rows_per_column;
+        Vector2::new(
+            sec.x + column as f32 * self.column_width,
+            sec.y + (self.each_height * (row % self.rows_per_column) as f32) + self.gap,
+        )
+    }
+
+    // Index of the move under 'pos', if any. Each row covers the same area as its background,
+    // and the move number doesn't belong to either move.
+    fn move_at(&self, sec: &Rectangle, pos: Vector2, move_count: usize) -> Option<usize> {
+        let x = pos.x - sec.x;
+        let y = pos.y - sec.y - self.gap + (self.each_height - self.gap);
+        if x < 0.0 || y < 0.0 {
+            return None;
+        }
+        let row = (y / self.each_height) as usize;
+        if row >= self.rows_per_column {
+            return None;
+        }
+        let column = (x / self.column_width) as usize;
+        let x_in_column = x / self.column_width - column as f32;
+        let side = if x_in_column >= Self::BLACK_MOVE_X {
+            1
+        } else if x_in_column >= Self::WHITE_MOVE_X {
+            0
+        } else {
+            return None;
+        };
+        let i = 2 * (column * self.rows_per_column + row) + side;
+        (i < move_count).then_some(i)
+    }
+
+    fn content_height(&self) -> f32 {
+        self.each_height * self.rows_per_column as f32 + self.gap
+    }
+}
+
+fn draw_moves(
+    s: &mut impl RaylibDraw, sec: &mut Rectangle, view_height: f32, font: &Font, game: &Game,
+    current: usize
+) -> Rectangle {
+    let layout = MoveListLayout::new(sec, view_height, font.baseSize as f32, game.move_count());
+    sec.height = f32::max(view_height, layout.content_height());
+
     let mut x;
     let mut y = 0.0;
-    let gap = font.baseSize as f32 * 1.5;
-    let each_height = font.baseSize as f32 * 2.0;
-    let mut draw_bkgd = false;
     let mut curr_move_rect = Rectangle::default();
     // [ (move number) (gap 1) (white's move) (gap 2) (black's move) ]
     // [ (   0.05    ) ( 0.2 ) (   0.325    ) ( 0.1 ) (   0.325    ) ]
-    // for (i, b_info) in moves.iter().enumerate() {
     for i in 0..game.move_count() {
         let mv = game.move_at(i);
         if mv.is_none() { break; }
-        let mv = mv.unwrap().to_str();
-        let mv = mv.trim();
+        let mv = moves::to_uci(*mv.unwrap());
+        let mv = mv.as_str();
 
+        let row = i / 2;
+        let pos = layout.row_pos(sec, row);
+        let col_x = pos.x;
+        let width = layout.column_width;
         if i % 2 == 0 {
-            y = sec.y + (each_height * (i as f32)/2.0) + gap;
-            if draw_bkgd {
+            y = pos.y;
+            if row % 2 == 1 {
                 s.draw_rectangle_rec(
-                    Rectangle::new(sec.x, y - (each_height - gap), sec.width, each_height),
+                    Rectangle::new(col_x, y - (layout.each_height - layout.gap), width, layout.each_height),
                     MOVELIST_LIGHT_BKGD
                 );
             }
-            draw_bkgd = !draw_bkgd;
 
-            x = sec.x + (0.05*sec.width);
-            s.draw_text_ex(font, &move_counter.to_string(), Vector2::new(x, y),
+            x = col_x + (0.05*width);
+            s.draw_text_ex(font, &(row + 1).to_string(), Vector2::new(x, y),
                 font.baseSize as f32, 0.0, Color::GRAY);
-            move_counter += 1;
-
-            if (y + each_height) - sec.y > sec.height {
-                sec.height += gap;
-            }
-            x = sec.x + 0.25*sec.width;
+            x = col_x + MoveListLayout::WHITE_MOVE_X*width;
         } else {
-            x = sec.x + 0.675*sec.width;
+            x = col_x + MoveListLayout::BLACK_MOVE_X*width;
         }
         let curr_ind = current.saturating_sub(1);
         if i == curr_ind {
@@ -321,116 +223,214 @@ fn draw_moves(s: &mut impl RaylibDraw, sec: &mut Rectangle, font: &Font, game: &
 }
 
 /* ===================================== USER INPUT RELATED ===================================== */
-/*
+// Result of a click while the promotion picker is shown
+#[derive(Debug, PartialEq)]
+enum PromotionPick {
+    Pending,
+    Chosen(Piece),
+    Cancelled,
+}
+
+// Pieces offered by the promotion picker, from left to right. The picker is mirrored along with
+// the board, so the options keep the same order relative to the files.
+fn promotion_choices(side: PieceColor, flip: bool) -> [Piece; 4] {
+    let mut choices = if side == PieceColor::Light {
+        [Piece::LN, Piece::LB, Piece::LR, Piece::LQ]
+    } else {
+        [Piece::DN, Piece::DB, Piece::DR, Piece::DQ]
+    };
+    if flip {
+        choices.reverse();
+    }
+    choices
+}
+
+// Clicking outside of the picker cancels the promotion, so the move can't be left half entered
+fn pick_promotion(rl: &RaylibHandle, sec: &Rectangle, side: PieceColor, flip: bool) -> PromotionPick {
+    if !rl.is_mouse_button_pressed(MouseButton::MOUSE_LEFT_BUTTON) {
+        return PromotionPick::Pending;
+    }
+    let mouse_pos = rl.get_mouse_position();
+    if !sec.check_collision_point_rec(mouse_pos) {
+        return PromotionPick::Cancelled;
+    }
+    let col = ((mouse_pos.x - sec.x) / (sec.width / 4.0)) as usize;
+    PromotionPick::Chosen(promotion_choices(side, flip)[col.min(3)])
+}
+
+fn draw_promotion_picker(
+    d: &mut RaylibDrawHandle, tex: &Texture2D, sec: &Rectangle, side: PieceColor, flip: bool
+) {
+    d.draw_rectangle_rec(*sec, PROMOTION_BACKGROUND);
+    let cell_width = sec.width / 4.0;
+    for (i, piece) in promotion_choices(side, flip).iter().enumerate() {
+        let target = Rectangle::new(sec.x + i as f32 * cell_width, sec.y, cell_width, sec.height);
+        render::draw_piece(d, tex, target, *piece);
+    }
+}
+
+// Only a piece of the side to move can be the source of a move. When a human plays against an
+// engine ('human_side' is set), it also has to be one of the human's pieces.
+fn can_select(board: &Board, sq: usize, human_side: Option<PieceColor>) -> bool {
+    let side = board.side_to_move();
+    if human_side.map_or(false, |human| human != side) {
+        return false;
+    }
+    let piece = board.find_piece(sq);
+    piece.is_some() && Piece::to_tuple(piece).0 == side as usize
+}
+
+// Square under 'pos', if it's on the board. The board can be flipped, so it's worked out the
+// same way as where each square is displayed.
+fn sq_at(board_sec: &Rectangle, pos: Vector2, flip: bool) -> Option<Sq> {
+    if !board_sec.check_collision_point_rec(pos) {
+        return None;
+    }
+    let col = (((pos.x - board_sec.x) / (board_sec.width / 8.0)) as usize).min(7);
+    let row = (((pos.y - board_sec.y) / (board_sec.height / 8.0)) as usize).min(7);
+    Some(Sq::from_num(render::display_sq(SQ!(row, col), flip)))
+}
+
 fn handle_board_selected(
-    rl: &RaylibHandle, board: &Board, board_sec: &Rectangle, selected: &mut Option<Sq>
+    rl: &RaylibHandle, board: &Board, board_sec: &Rectangle, selected: &mut Option<Sq>,
+    flip: bool, human_side: Option<PieceColor>
 ) {
     if rl.is_mouse_button_pressed(MouseButton::MOUSE_LEFT_BUTTON) {
-        let mouse_pos = rl.get_mouse_position();
-        let mut temp_selected = None;
-        if board_sec.check_collision_point_rec(mouse_pos) {
-            let col = ((mouse_pos.x - board_sec.x) / (board_sec.width / 8.0)) as usize;
-            let row = ((mouse_pos.y - board_sec.y) / (board_sec.height / 8.0)) as usize;
-            temp_selected = Some(Sq::from_num(SQ!(row, col)));
-        } else {
+        let sq = if let Some(sq) = sq_at(board_sec, rl.get_mouse_position(), flip) { sq } else {
             *selected = None;
             return;
-        }
-        let sq = temp_selected.unwrap();
-        if let Some(piece) = board.find_piece(sq as usize) {
-            if selected.is_some() && piece as usize / 6 != board.state.side as usize {
-                return;
-            }
-        } else {
+        };
+        if !can_select(board, sq as usize, human_side) {
             return;
         }
-        if temp_selected == *selected {
+        if selected.map(|s| s as usize) == Some(sq as usize) {
             *selected = None;
             return;
         }
-        *selected = temp_selected;
+        *selected = Some(sq);
     }
 }
 
 fn handle_board_target(
     rl: &RaylibHandle, board: &Board, board_sec: &Rectangle, selected: &Option<Sq>,
-    target: &mut Option<Sq>, is_promotion: &mut bool
+    target: &mut Option<Sq>, is_promotion: &mut bool, flip: bool
 ) {
-    if selected.is_none() { return; }
+    let selected = if let Some(sq) = selected { *sq } else { return; };
     if *is_promotion || target.is_some() { return; }
     if rl.is_mouse_button_pressed(MouseButton::MOUSE_LEFT_BUTTON) {
-        let mouse_pos = rl.get_mouse_position();
-        let mut temp_selected = None;
-        if board_sec.check_collision_point_rec(mouse_pos) {
-            let col = ((mouse_pos.x - board_sec.x) / (board_sec.width / 8.0)) as usize;
-            let row = ((mouse_pos.y - board_sec.y) / (board_sec.height / 8.0)) as usize;
-            temp_selected = Some(Sq::from_num(SQ!(row, col)));
-        }
-        if temp_selected == *selected { return; }
-        *target = temp_selected;
-        let piece = board.find_piece(selected.unwrap() as usize);
-        if piece.is_none() { return; }
-        let piece = piece.unwrap();
-        let sq = temp_selected.unwrap();
-        if (piece == Piece::LP || piece == Piece::DP)
+        // Clicks off the board were already handled by dropping the selection
+        let sq = if let Some(sq) = sq_at(board_sec, rl.get_mouse_position(), flip) { sq } else { return; };
+        if sq as usize == selected as usize { return; }
+        *target = Some(sq);
+        let piece = board.find_piece(selected as usize);
+        if (piece == Some(Piece::LP) || piece == Some(Piece::DP))
             && (ROW!(sq as usize) == 0 || ROW!(sq as usize) == 7) {
             *is_promotion = true;
         }
     }
 }
 
-fn update_player(
-    rl: &RaylibHandle, board: &mut Board, attack_info: &AttackInfo,
-    boundary: &Rectangle, promoted_boundary: &Rectangle, selected: &mut Option<Sq>, target: &mut Option<Sq>,
-    is_promotion: &mut bool, promoted_piece: &mut Option<Piece>
-) {
-    if *is_promotion {
-        let mouse_pos = rl.get_mouse_position();
-        if rl.is_mouse_button_pressed(MouseButton::MOUSE_LEFT_BUTTON)
-        && promoted_boundary.check_collision_point_rec(mouse_pos) {
-            let mut piece = (mouse_pos.x / (promoted_boundary.width / 4.0)).trunc() as usize;
-            if board.state.side == PieceColor::Dark {
-                piece += 6;
-            }
-            *promoted_piece = match piece {
-                1 => Some(Piece::LN),
-                2 => Some(Piece::LB),
-                3 => Some(Piece::LR),
-                4 => Some(Piece::LQ),
-                7 => Some(Piece::DN),
-                8 => Some(Piece::DB),
-                9 => Some(Piece::DR),
-                10 => Some(Piece::DQ),
-                _ => None
-            };
-            *is_promotion = false;
+/* ===================================== USER INPUT RELATED ===================================== */
+
+// Actions that throw away unfinished play, which have to be requested twice
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Confirmation {
+    NewGame,
+    NewMatch,
+}
+
+// How the board is turned, which decides the side of everything that follows the board (see
+// 'GUI::flip')
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Orientation {
+    // Turned by hand
+    Fixed,
+    AlwaysWhite,
+    // Faces the side to move, which is easier to follow when watching the engines
+    FollowSideToMove,
+}
+
+impl Orientation {
+    const NAMES: [(Orientation, &'static str); 3] = [
+        (Orientation::Fixed, "fixed"),
+        (Orientation::AlwaysWhite, "white"),
+        (Orientation::FollowSideToMove, "side_to_move"),
+    ];
+
+    fn from_name(name: &str) -> Option<Orientation> {
+        Self::NAMES.iter().find(|(_, n)| *n == name).map(|(orientation, _)| *orientation)
+    }
+
+    fn name(self) -> &'static str {
+        Self::NAMES.iter().find(|(orientation, _)| *orientation == self).unwrap().1
+    }
+
+    fn next(self) -> Orientation {
+        let ind = Self::NAMES.iter().position(|(orientation, _)| *orientation == self).unwrap();
+        Self::NAMES[(ind + 1) % Self::NAMES.len()].0
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Orientation::Fixed => "Board stays as it is",
+            Orientation::AlwaysWhite => "White is always at the bottom",
+            Orientation::FollowSideToMove => "Board faces the side to move",
+        }
+    }
+
+    // Whether the board is shown from black's side, where 'flip' is how it was turned by hand
+    fn is_flipped(self, flip: bool, side_to_move: PieceColor) -> bool {
+        match self {
+            Orientation::Fixed => flip,
+            Orientation::AlwaysWhite => false,
+            Orientation::FollowSideToMove => side_to_move == PieceColor::Dark,
         }
     }
-    handle_board_selected(rl, board, boundary, selected);
-    handle_board_target(rl, board, boundary, &selected, target, is_promotion);
 }
-*/
-/* ===================================== USER INPUT RELATED ===================================== */
 
-fn get_move_from_engine(frame_time: f32, current_fen: &str, engine: &mut EngineComm) -> Option<String> {
-    let mut retry_count = 0;
-    while retry_count < 2 {
-        if let Some(best_move) = engine.best_move() {
-            assert!(best_move.len() == 4 || best_move.len() == 5, "Length: {}", best_move.len());
-            if best_move == "a8a8P" {
-                retry_count += 1;
-                println!("Retry because of 'a8a8P'");
-                continue;
-            }
-            // println!("[{}] '{}'", best_move.len(), &best_move);
-            return Some(best_move);
-        } else {
-            println!("Retry because NO MOVE was sent by engine. ");
-            retry_count += 1;
+impl Default for Orientation {
+    fn default() -> Self {
+        Orientation::Fixed
+    }
+}
+
+// Perft divide of the displayed position, shown over the board to debug the move generator
+struct PerftOverlay {
+    depth: u32,
+    // Position and depth that 'counts' are for, so that they're only worked out again when
+    // either one changes
+    counted: Option<(String, u32)>,
+    // Each legal move in UCI notation along with its node count, sorted by move
+    counts: Vec<(String, u64)>,
+}
+
+impl PerftOverlay {
+    const DEFAULT_DEPTH: u32 = 2;
+    // Counting is done between frames, so the window stops while it runs. In a release build
+    // that's a fraction of a second at depth 4, even in busy positions, but can be over ten
+    // seconds at depth 5.
+    const MAX_DEPTH: u32 = 4;
+
+    // Works out the counts of 'board', unless they're already known, and prints them like the
+    // 'divide' command does
+    fn update(&mut self, board: &Board, attack_info: &AttackInfo, zobrist_info: &ZobristInfo) {
+        let key = (fen::gen_fen(board), self.depth);
+        if self.counted.as_ref() == Some(&key) { return; }
+        self.counts = move_gen::perft_divide(board, self.depth, attack_info, zobrist_info).into_iter()
+            .map(|(mv, nodes)| (moves::to_uci(mv), nodes))
+            .collect();
+        self.counts.sort();
+        println!("Perft divide of '{}' at depth {}:", key.0, key.1);
+        for (mv, nodes) in &self.counts {
+            println!("{}: {}", mv, nodes);
         }
+        println!("Total: {}", self.total());
+        self.counted = Some(key);
+    }
+
+    fn total(&self) -> u64 {
+        self.counts.iter().map(|(_, nodes)| nodes).sum()
     }
-    eprintln!("[ERROR] Engine, '{}' couldn't give a legal move", engine.name());
-    return None;
 }
 
 struct GUI {
@@ -438,6 +438,19 @@ struct GUI {
     target: Option<Sq>,
     is_promotion: bool,
     promoted_piece: Option<Piece>,
+    // The built-in themes followed by the ones defined in the config, and the one in use
+    themes: Vec<Theme>,
+    theme_ind: usize,
+    // Shows the board from black's side, when it's turned by hand. Everything tied to a side
+    // follows the way the board is turned (see 'flipped'): the squares and their coordinates, the
+    // pieces and their animations, the order of the promotion choices, and the name and clock
+    // boxes, so that the side at the bottom of the board also has its name at the bottom.
+    flip: bool,
+    orientation: Orientation,
+    // Side to move in the displayed position. It only changes once the move leading to it has
+    // been animated, so the board doesn't turn while a piece is still moving.
+    side_to_move: PieceColor,
+    coord_style: CoordStyle,
 
     // Sections on the screen
     board_sec: Rectangle,
@@ -450,7 +463,28 @@ struct GUI {
     move_list_rect: Rectangle,
     curr_move_rect: Rectangle,
     move_btns_rect: Rectangle,
+    graph_sec: Rectangle,
     follow_move_list: bool,
+    centering_move: bool,
+    snap_to_move: bool,
+    // Set when a new game or match was requested while the current one is still going on
+    confirm: Option<Confirmation>,
+    // Position being analyzed and the latest info about it
+    analysis_fen: String,
+    analysis: Option<(String, SearchInfo)>,
+    // Short message shown over the board, along with when it was shown
+    toast: Option<(String, Instant)>,
+    // Set while the moves of the game are being stepped through on a timer
+    auto_replay: bool,
+    last_replay_step: Instant,
+    show_help: bool,
+    // Shows the index of every square, for debugging
+    show_sq_indices: bool,
+    // Shows how deep the engines searched for each move, which takes up room in the info panel
+    show_depth_graph: bool,
+    perft: Option<PerftOverlay>,
+    // A FEN to play from, typed over the board while the input has focus
+    fen_input: TextInput,
 }
 
 impl GUI {
@@ -460,6 +494,12 @@ impl GUI {
             target: None,
             is_promotion: false,
             promoted_piece: None,
+            themes: Theme::BUILT_IN.to_vec(),
+            theme_ind: 0,
+            flip: false,
+            orientation: Orientation::default(),
+            side_to_move: PieceColor::Light,
+            coord_style: CoordStyle::default(),
 
             // Sections on the screen
             board_sec: Rectangle::default(),
@@ -472,10 +512,38 @@ impl GUI {
             move_list_rect: Rectangle::default(),
             curr_move_rect: Rectangle::default(),
             move_btns_rect: Rectangle::default(),
+            graph_sec: Rectangle::default(),
             follow_move_list: true,
+            centering_move: false,
+            snap_to_move: false,
+            confirm: None,
+            analysis_fen: String::new(),
+            analysis: None,
+            toast: None,
+            auto_replay: false,
+            last_replay_step: Instant::now(),
+            show_help: false,
+            show_sq_indices: false,
+            show_depth_graph: false,
+            perft: None,
+            fen_input: TextInput::new("Type a FEN and press Enter", 100),
         }
     }
 
+    fn theme(&self) -> &Theme {
+        &self.themes[self.theme_ind]
+    }
+
+    fn flipped(&self) -> bool {
+        self.orientation.is_flipped(self.flip, self.side_to_move)
+    }
+
+    // Turns the board so that 'side' is at the bottom, and keeps it that way
+    fn face_side(&mut self, side: PieceColor) {
+        self.orientation = Orientation::Fixed;
+        self.flip = side == PieceColor::Dark;
+    }
+
     fn init_sections(&mut self, width: i32, height: i32) {
         let size = Vector2::new(width as f32, height as f32);
         let margin = Vector2::new(size.x * 0.01, size.y * 0.03);
@@ -485,25 +553,47 @@ impl GUI {
 
     fn update_sections(&mut self, size: Vector2, margin: Vector2) {
         let min_side = f32::min((size.x - 2.0*margin.x) * 0.7, 0.85 * (size.y - 2.0*margin.y));
-        self.board_sec = Rectangle {
+        // The board along with the space for coordinates drawn next to it
+        let board_area = Rectangle {
             x: margin.x,
             y: margin.y + (size.y - 2.0*margin.y)/2.0  - min_side/2.0,
             width: min_side,
             height: min_side
         };
-        let name_height = self.board_sec.y - margin.y;
-        self.white_name_sec = Rectangle {
-            x: self.board_sec.x,
-            y: self.board_sec.y + self.board_sec.height + self.board_sec.y/2.0 - name_height/2.0,
+        let gutter = self.coord_style.gutter(min_side);
+        self.board_sec = match self.coord_style {
+            CoordStyle::Inside => board_area,
+            CoordStyle::Outside => Rectangle {
+                x: board_area.x + gutter,
+                width: min_side - gutter,
+                height: min_side - gutter,
+                ..board_area
+            },
+            CoordStyle::AllEdges => Rectangle {
+                x: board_area.x + gutter,
+                y: board_area.y + gutter,
+                width: min_side - 2.0*gutter,
+                height: min_side - 2.0*gutter,
+            },
+        };
+        let name_height = board_area.y - margin.y;
+        let bottom_name_sec = Rectangle {
+            x: board_area.x,
+            y: board_area.y + board_area.height + board_area.y/2.0 - name_height/2.0,
             width: min_side,
             height: name_height,
         };
-        self.black_name_sec = Rectangle {
-            x: self.board_sec.x,
-            y: self.board_sec.y/2.0 - name_height/2.0,
+        let top_name_sec = Rectangle {
+            x: board_area.x,
+            y: board_area.y/2.0 - name_height/2.0,
             width: min_side,
             height: name_height,
         };
+        (self.white_name_sec, self.black_name_sec) = if self.flipped() {
+            (top_name_sec, bottom_name_sec)
+        } else {
+            (bottom_name_sec, top_name_sec)
+        };
         let promoted_height = self.board_sec.height * 0.15;
         let promoted_width = 4.0 * promoted_height;
         self.promotion_sec = Rectangle {
@@ -512,11 +602,18 @@ impl GUI {
             width: promoted_width,
             height: promoted_height,
         };
+        let input_height = self.board_sec.height * 0.08;
+        self.fen_input.set_rect(Rectangle {
+            x: self.board_sec.x + self.board_sec.width * 0.05,
+            y: self.board_sec.y + (self.board_sec.height / 2.0) - (input_height / 2.0),
+            width: self.board_sec.width * 0.9,
+            height: input_height,
+        });
 
         self.info_sec = Rectangle {
-            x: self.board_sec.x + self.board_sec.width + margin.x,
+            x: board_area.x + board_area.width + margin.x,
             y: margin.y,
-            width: size.x - (self.board_sec.x + self.board_sec.width + 2.0*margin.x),
+            width: size.x - (board_area.x + board_area.width + 2.0*margin.x),
             height: size.y - 2.0*margin.y,
         };
         self.move_list_sec = Rectangle {
@@ -533,6 +630,14 @@ impl GUI {
             height: self.info_sec.height - self.move_list_sec.height,
             ..self.move_list_sec
         };
+        // Just above the author box at the bottom of the panel
+        let graph_height = 0.12 * self.info_sec.height;
+        self.graph_sec = Rectangle {
+            x: self.info_sec.x + margin.x,
+            y: self.info_sec.y + 0.9*self.info_sec.height - graph_height - margin.y,
+            width: self.info_sec.width - 2.0*margin.x,
+            height: graph_height,
+        };
         /* self.move_list_sec = Rectangle {
             x: self.info_sec.x,
             y: self.info_sec.y + height,
@@ -546,6 +651,10 @@ impl GUI {
         }; */
     }
 
+    // How fast the move list scrolls to the current move (the fraction of the remaining distance
+    // covered per second)
+    const SCROLL_SPEED: f32 = 10.0;
+
     fn handle_scrolling(&mut self, rl: &RaylibHandle) {
         let wheel_move = rl.get_mouse_wheel_move();
         self.move_list_rect.y += wheel_move * 100.0;
@@ -555,25 +664,489 @@ impl GUI {
 
         let sec = &mut self.move_list_sec;
         let rect = &mut self.move_list_rect;
+        let curr = &self.curr_move_rect;
 
-        if self.follow_move_list {
-            if self.curr_move_rect.y + self.curr_move_rect.height < sec.y {
-                rect.y += sec.height;
+        // Once the current move goes out of view, the list is scrolled until the move is centered
+        if self.follow_move_list && curr.height > 0.0 {
+            let fully_visible = curr.y >= sec.y && curr.y + curr.height <= sec.y + sec.height;
+            if !fully_visible {
+                self.centering_move = true;
             }
-            if self.curr_move_rect.y + self.curr_move_rect.height > sec.y + sec.height {
-                rect.y -= sec.height;
+            if self.centering_move {
+                let offset = (sec.y + sec.height/2.0) - (curr.y + curr.height/2.0);
+                if self.snap_to_move {
+                    rect.y += offset;
+                } else {
+                    // Only cover part of the distance every frame, so that the list glides
+                    rect.y += offset * f32::min(1.0, Self::SCROLL_SPEED * rl.get_frame_time());
+                }
+                if offset.abs() < 1.0 || self.snap_to_move {
+                    self.centering_move = false;
+                }
             }
         }
+        self.snap_to_move = false;
 
+        // Keep the list from scrolling past its content
+        let mut clamped = false;
         if rect.y + (rect.height - sec.height) < sec.y {
             rect.y = sec.y - (rect.height - sec.height);
+            clamped = true;
         }
 
         if rect.y > sec.y {
             rect.y = sec.y;
+            clamped = true;
+        }
+        if clamped {
+            self.centering_move = false;
+        }
+    }
+
+    // Drops a move that was only partly entered, e.g. with the promotion picker still showing.
+    // Returns whether there was one.
+    fn cancel_move_input(&mut self) -> bool {
+        let pending = self.selected.is_some() || self.target.is_some() || self.is_promotion;
+        self.selected = None;
+        self.target = None;
+        self.is_promotion = false;
+        self.promoted_piece = None;
+        pending
+    }
+
+    // Takes the clicks of the person playing 'human_side', who is to move in 'board'. A piece is
+    // picked first, then the square it goes to and, for a promotion, the piece it becomes.
+    fn update_player(&mut self, rl: &RaylibHandle, board: &Board, human_side: Option<PieceColor>) {
+        if self.is_promotion {
+            match pick_promotion(rl, &self.promotion_sec, board.side_to_move(), self.flipped()) {
+                PromotionPick::Chosen(piece) => {
+                    self.promoted_piece = Some(piece);
+                    self.is_promotion = false;
+                },
+                PromotionPick::Cancelled => {
+                    self.cancel_move_input();
+                },
+                PromotionPick::Pending => {},
+            }
+            // The click was meant for the picker, not for the board
+            return;
+        }
+        let flip = self.flipped();
+        handle_board_selected(rl, board, &self.board_sec, &mut self.selected, flip, human_side);
+        handle_board_target(rl, board, &self.board_sec, &self.selected, &mut self.target, &mut self.is_promotion, flip);
+    }
+
+    // The move that was entered, in UCI notation, once it's complete. The input is cleared for
+    // the next move, whether or not the move turns out to be legal.
+    fn take_entered_move(&mut self) -> Option<String> {
+        let (source, target) = (self.selected?, self.target?);
+        if self.is_promotion {
+            return None;
+        }
+        let mut uci = format!("{}{}", Sq::to_string(source), Sq::to_string(target));
+        if let Some(piece) = self.promoted_piece {
+            uci.push(Piece::to_char(Some(piece)).to_ascii_lowercase());
+        }
+        self.cancel_move_input();
+        Some(uci)
+    }
+
+    // Follows the current move again after scrolling through the list by hand
+    fn jump_to_current_move(&mut self) {
+        self.follow_move_list = true;
+        self.centering_move = true;
+        self.snap_to_move = true;
+    }
+
+}
+
+// e.g. "Move 24, Black to move"
+fn move_number_text(board: &Board) -> String {
+    let side = if board.side_to_move() == PieceColor::Light { "White" } else { "Black" };
+    format!("Move {}, {} to move", board.full_moves(), side)
+}
+
+// Position of the game that an animation board index refers to. The game can be replaced before
+// the index is updated, in which case its latest position is shown.
+fn board_at(game: &Game, ind: usize) -> &Board {
+    game.board_at(ind).or_else(|| game.board_after_last_move()).unwrap()
+}
+
+// Slides the piece of a move from the position 'board' to 'target', which are indices like those
+// of 'board_at'. A move played while another one is still sliding waits for it to finish. Only
+// the latest move waits, so when moves come in faster than they can be shown, the board skips
+// ahead to the position before the latest one instead of falling behind the game.
+struct BoardAnimation {
+    board: usize,
+    target: Option<usize>,
+    mv: Option<Move>,
+    started: Instant,
+    // Latest move that was played, along with the position after it
+    pending: Option<(Move, usize)>,
+}
+
+impl BoardAnimation {
+    const DURATION: Duration = Duration::from_millis(200);
+
+    fn new(board: usize) -> Self {
+        Self { board, target: None, mv: None, started: Instant::now(), pending: None }
+    }
+
+    // Position whose pieces are drawn, apart from the one that is moving
+    fn board(&self) -> usize {
+        self.board
+    }
+
+    // Position the board will show once every animation has finished
+    fn latest(&self) -> usize {
+        self.pending.map(|(_, target)| target).or(self.target).unwrap_or(self.board)
+    }
+
+    fn mv(&self) -> Option<Move> {
+        self.mv
+    }
+
+    // Shows 'mv' being played from 'board', dropping any animation that was going on, e.g. when
+    // stepping through the moves. Without a move, 'board' is shown right away.
+    fn start(&mut self, mv: Option<Move>, board: usize, target: Option<usize>, now: Instant) {
+        self.board = board;
+        self.target = target.filter(|_| mv.is_some());
+        self.mv = mv;
+        self.started = now;
+        self.pending = None;
+    }
+
+    // Shows the position 'target' after 'mv' was played from 'board' when going through the
+    // moves. Only a step of one move slides the piece; a jump (e.g. to the first move) swaps the
+    // position at once, since sliding the last move into it would look like an unrelated move.
+    fn go_to(&mut self, mv: Option<Move>, board: usize, target: Option<usize>, now: Instant) {
+        let shown = target.unwrap_or(board);
+        if shown.abs_diff(self.latest()) > 1 {
+            self.start(None, shown, None, now);
+        } else {
+            self.start(mv, board, target, now);
+        }
+    }
+
+    // A move was played in the game, leading to the position 'target'
+    fn push(&mut self, mv: Move, target: usize, now: Instant) {
+        if self.mv.is_some() {
+            self.pending = Some((mv, target));
+        } else {
+            self.start(Some(mv), target - 1, Some(target), now);
+        }
+    }
+
+    // Returns how far along the moving piece is, from 0 to 1, or nothing once there's no move to
+    // animate. A finished move lands on its target, and the move that was waiting starts.
+    fn update(&mut self, now: Instant) -> Option<f32> {
+        self.mv?;
+        let progress = now.saturating_duration_since(self.started).as_secs_f32() / Self::DURATION.as_secs_f32();
+        if progress < 1.0 {
+            return Some(progress);
+        }
+        self.board = self.target.take().unwrap_or(self.board);
+        self.mv = None;
+        let (mv, target) = self.pending.take()?;
+        self.start(Some(mv), target - 1, Some(target), now);
+        Some(0.0)
+    }
+
+    // While the game is being played, the board ends up on its latest position, even if the game
+    // was replaced in the middle of an animation
+    fn follow(&mut self, latest: usize) {
+        if self.mv.is_none() && self.board != latest {
+            self.board = latest;
+        }
+    }
+}
+
+fn draw_move_number(d: &mut RaylibDrawHandle, font: &Font, pos: Vector2, board: &Board) {
+    d.draw_text_ex(font, &move_number_text(board), pos, font.baseSize as f32 * 0.75, 0.0, Color::RAYWHITE);
+}
+
+// Shows how close the game is to a draw by the fifty move rule. The board keeps count of
+// plies, so it's halved to get the number of moves.
+// Also warns about a position that came back, before it's repeated a third time
+fn draw_fifty_move_counter(d: &mut RaylibDrawHandle, font: &Font, pos: Vector2, board: &Board, repetitions: usize) {
+    let mut text = format!("50-move: {}/50", board.halfmove_clock() / 2);
+    if repetitions > 1 {
+        text.push_str(&format!(", repetition: {}/3", repetitions));
+    }
+    d.draw_text_ex(font, &text, pos, font.baseSize as f32 * 0.75, 0.0, Color::RAYWHITE);
+}
+
+fn draw_opening(d: &mut RaylibDrawHandle, font: &Font, pos: Vector2, opening: Option<&Opening>) {
+    let text = match opening {
+        Some(opening) => format!("{}: {}", opening.eco, opening.name),
+        None => "Opening: unknown".to_string(),
+    };
+    d.draw_text_ex(font, &text, pos, font.baseSize as f32 * 0.75, 0.0, Color::RAYWHITE);
+}
+
+fn draw_game_over_text(d: &mut RaylibDrawHandle, font: &Font, pos: Vector2, state: GameState) {
+    if state == GameState::Ongoing { return; }
+    d.draw_text_ex(font, state.description(), pos, font.baseSize as f32 * 0.9, 0.0, Color::GOLD);
+}
+
+// Turns a UCI score (e.g. "cp -25" or "mate 3"), given from the point of view of the engine, into
+// a score from white's point of view (e.g. "+0.25" or "#3"). Also returns the side that has a
+// forced mate, if the engine found one.
+fn format_score(score: &str, engine_is_white: bool) -> (String, Option<&'static str>) {
+    let sign = if engine_is_white { 1 } else { -1 };
+    match Score::parse(score) {
+        Some(Score::Cp(cp)) => (format!("{:+.2}", (sign * cp) as f32 / 100.0), None),
+        Some(Score::Mate(moves)) => {
+            let moves = sign * moves;
+            let mating_side = if moves > 0 { "White" } else { "Black" };
+            (format!("#{}", moves), Some(mating_side))
+        },
+        None => (score.to_string(), None),
+    }
+}
+
+// One line to share the current position along with the engine's assessment of it, e.g.
+// "<fen> | eval +0.25 | Alpha vs Beta"
+fn position_summary(game: &Game) -> String {
+    let last_move = game.move_count().checked_sub(1);
+    let score = last_move.and_then(|ind| {
+        let score = game.score_at(ind)?;
+        let engine_is_white = game.board_before_move(ind).map_or(true, |b| b.is_white_to_move());
+        Some(format_score(score, engine_is_white).0)
+    });
+    format!(
+        "{} | eval {} | {} vs {}",
+        game.current_fen(), score.as_deref().unwrap_or("-"), game.white_name(), game.black_name()
+    )
+}
+
+fn draw_eval(d: &mut RaylibDrawHandle, font: &Font, pos: Vector2, score: Option<&String>, engine_is_white: bool) {
+    let font_size = font.baseSize as f32 * 0.75;
+    let (text, mating_side) = match score {
+        Some(score) => format_score(score, engine_is_white),
+        None => ("-".to_string(), None),
+    };
+    let text = format!("Eval: {}", text);
+    d.draw_text_ex(font, &text, pos, font_size, 0.0, Color::RAYWHITE);
+    if let Some(side) = mating_side {
+        let text_dim = text::measure_text_ex(font, &text, font_size, 0.0);
+        let pos = Vector2::new(pos.x + text_dim.x + font_size, pos.y);
+        d.draw_text_ex(font, &format!("{} has a forced mate", side), pos, font_size, 0.0, Color::GOLD);
+    }
+}
+
+// The line is cut after the last move that fits in 'width'
+fn draw_pv(d: &mut RaylibDrawHandle, font: &Font, pos: Vector2, pv: &str, width: f32) {
+    let font_size = font.baseSize as f32 * 0.6;
+    let mut text = "PV:".to_string();
+    for san in pv.split_whitespace() {
+        let longer = format!("{} {}", text, san);
+        if text::measure_text_ex(font, &longer, font_size, 0.0).x > width {
+            break;
         }
+        text = longer;
     }
+    d.draw_text_ex(font, &text, pos, font_size, 0.0, Color::LIGHTGRAY);
+}
 
+fn draw_analysis(
+    d: &mut RaylibDrawHandle, font: &Font, pos: Vector2, engine_name: &str, info: &SearchInfo,
+    pv: &str, white_to_move: bool
+) {
+    let font_size = font.baseSize as f32 * 0.75;
+    let mut header = format!("{} (analysis)", engine_name);
+    if let Some(depth) = info.depth {
+        header.push_str(&format!(" - depth {}", depth));
+    }
+    if let Some(score) = &info.score {
+        header.push_str(&format!(", {}", format_score(score, white_to_move).0));
+    }
+    d.draw_text_ex(font, &header, pos, font_size, 0.0, Color::RAYWHITE);
+    let pv_pos = Vector2::new(pos.x, pos.y + font_size * 1.2);
+    d.draw_text_ex(font, pv, pv_pos, font_size, 0.0, Color::LIGHTGRAY);
+}
+
+// Depth, node count and time of a search, e.g. "depth 12, 5031 nodes, 10 ms"
+fn describe_search(info: &SearchInfo) -> String {
+    let mut parts = vec![];
+    if let Some(depth) = info.depth {
+        parts.push(format!("depth {}", depth));
+    }
+    if let Some(nodes) = info.nodes {
+        parts.push(format!("{} nodes", nodes));
+    }
+    if let Some(time_ms) = info.time_ms {
+        parts.push(format!("{} ms", time_ms));
+    }
+    if parts.is_empty() { "-".to_string() } else { parts.join(", ") }
+}
+
+// Depth the engines reached for each move of the game, as a line for each side, with the move
+// that is displayed marked and its search described above the lines
+fn draw_depth_graph(d: &mut RaylibDrawHandle, font: &Font, sec: &Rectangle, game: &Game, move_index: usize) {
+    d.draw_rectangle_rec(*sec, GRAPH_BACKGROUND);
+    let font_size = font.baseSize as f32 * 0.6;
+    let move_count = game.move_count();
+    if move_count == 0 {
+        d.draw_text_ex(font, "Search depth: -", Vector2::new(sec.x, sec.y), font_size, 0.0, Color::LIGHTGRAY);
+        return;
+    }
+    let displayed = move_index.min(move_count - 1);
+    let text = format!("Search depth: {}", game.search_at(displayed).map_or("-".to_string(), describe_search));
+    d.draw_text_ex(font, &text, Vector2::new(sec.x, sec.y), font_size, 0.0, Color::LIGHTGRAY);
+
+    let depths: Vec<Option<u32>> = (0..move_count).map(|i| game.search_at(i).and_then(|info| info.depth)).collect();
+    let max_depth = depths.iter().flatten().copied().max().unwrap_or(0).max(1);
+    let lines_top = sec.y + font_size * 1.3;
+    let lines_height = sec.y + sec.height - lines_top;
+    let x = |i: usize| sec.x + sec.width * (i as f32 + 0.5) / move_count as f32;
+    let y = |depth: u32| lines_top + lines_height * (1.0 - depth as f32 / max_depth as f32);
+    d.draw_line_ex(Vector2::new(x(displayed), lines_top), Vector2::new(x(displayed), sec.y + sec.height), 1.0, Color::GOLD);
+    let white_moved_first = game.board_at(0).map_or(true, |board| board.is_white_to_move());
+    for side in 0..2 {
+        let color = if (side == 0) == white_moved_first { Color::RAYWHITE } else { Color::GRAY };
+        let mut prev = None;
+        for i in (side..move_count).step_by(2) {
+            // A move without a depth breaks the line
+            let point = depths[i].map(|depth| Vector2::new(x(i), y(depth)));
+            if let (Some(from), Some(to)) = (prev, point) {
+                d.draw_line_ex(from, to, 2.0, color);
+            } else if let Some(to) = point {
+                d.draw_circle_v(to, 2.0, color);
+            }
+            prev = point;
+        }
+    }
+}
+
+fn draw_prompt(d: &mut RaylibDrawHandle, font: &Font, sec: &Rectangle, lines: &[&str]) {
+    let font_size = font.baseSize as f32 * 0.8;
+    let line_height = font_size * 1.2;
+    let rect = Rectangle {
+        x: sec.x + sec.width * 0.1,
+        y: sec.y + sec.height / 2.0 - line_height * (lines.len() as f32 + 1.0) / 2.0,
+        width: sec.width * 0.8,
+        height: line_height * (lines.len() as f32 + 1.0),
+    };
+    d.draw_rectangle_rounded(rect, 0.15, 10, PROMPT_BACKGROUND);
+    for (i, line) in lines.iter().enumerate() {
+        let text_dim = text::measure_text_ex(font, line, font_size, 0.0);
+        let pos = Vector2::new(
+            rect.x + rect.width / 2.0 - text_dim.x / 2.0,
+            rect.y + line_height * (i as f32 + 0.5) + (line_height - text_dim.y) / 2.0,
+        );
+        d.draw_text_ex(font, line, pos, font_size, 0.0, Color::RAYWHITE);
+    }
+}
+
+// Keys of the GUI along with what they do, which are listed in the help overlay
+const KEY_BINDINGS: [(&str, &str); 27] = [
+    ("Space", "Play or pause the game"),
+    ("Left / Right", "Go to the previous or next move"),
+    ("Up / Down", "Go to the first or last move"),
+    ("C", "Scroll the move list to the current move"),
+    ("R", "Replay the moves of the game (while paused)"),
+    ("N", "Start the next game of the match"),
+    ("M", "Start the match over"),
+    ("P", "Play from a random position of the list"),
+    ("V", "Play from the FEN in the clipboard"),
+    ("L", "Type a FEN to play from (Enter to play it, Esc to cancel)"),
+    ("F", "Copy the FEN of the current position, or of the move under the mouse"),
+    ("E", "Copy the position with its eval and players"),
+    ("A", "Start or stop analyzing the displayed position"),
+    ("D", "Switch between searching for a fixed time and depth"),
+    ("J", "Adjudicate no games, draws, wins or both from the engines' scores"),
+    ("W", "Swap the engines' sides (before the first move)"),
+    ("U", "Play white, black or neither yourself (before the first move)"),
+    ("X", "Flip the board"),
+    ("O", "Keep the board fixed, white at the bottom, or facing the side to move"),
+    ("T", "Switch to the next board theme"),
+    ("G", "Show or hide the graph of the engines' search depth"),
+    ("I", "Show the index of every square (for debugging)"),
+    ("Z", "Show the perft divide of the displayed position"),
+    ("+ / -", "Change the perft depth (while it's shown)"),
+    ("H / ?", "Show or hide this help"),
+    ("Right click", "Cancel the move being entered"),
+    ("Esc", "Cancel the move being entered, or else quit"),
+];
+
+fn draw_help(d: &mut RaylibDrawHandle, font: &Font, bold_font: &Font, sec: &Rectangle) {
+    d.draw_rectangle_rec(*sec, HELP_BACKGROUND);
+    let line_count = KEY_BINDINGS.len() as f32 + 2.0;
+    let font_size = f32::min(font.baseSize as f32 * 0.7, sec.height / (line_count * 1.3));
+    let line_height = font_size * 1.3;
+    let key_width = KEY_BINDINGS.iter()
+        .map(|(key, _)| text::measure_text_ex(bold_font, key, font_size, 0.0).x)
+        .fold(0.0, f32::max);
+    let x = sec.x + sec.width * 0.08;
+    let mut y = sec.y + sec.height / 2.0 - line_height * KEY_BINDINGS.len() as f32 / 2.0;
+    for (key, description) in KEY_BINDINGS {
+        d.draw_text_ex(bold_font, key, Vector2::new(x, y), font_size, 0.0, Color::RAYWHITE);
+        let pos = Vector2::new(x + key_width + font_size, y);
+        d.draw_text_ex(font, description, pos, font_size, 0.0, Color::LIGHTGRAY);
+        y += line_height;
+    }
+}
+
+// The counts are laid out in as many columns as it takes to fit them on the board
+fn draw_perft(d: &mut RaylibDrawHandle, font: &Font, bold_font: &Font, sec: &Rectangle, perft: &PerftOverlay) {
+    d.draw_rectangle_rec(*sec, HELP_BACKGROUND);
+    let font_size = font.baseSize as f32 * 0.55;
+    let line_height = font_size * 1.25;
+    let padding = sec.width * 0.04;
+    let header = format!("Perft divide at depth {} (+/- to change): {} nodes", perft.depth, perft.total());
+    d.draw_text_ex(bold_font, &header, Vector2::new(sec.x + padding, sec.y + padding), font_size, 0.0, Color::RAYWHITE);
+    let top = sec.y + padding + 2.0 * line_height;
+    let rows = (((sec.y + sec.height - padding - top) / line_height) as usize).max(1);
+    let columns = perft.counts.len().div_ceil(rows).max(1);
+    let column_width = (sec.width - 2.0 * padding) / columns as f32;
+    for (i, (mv, nodes)) in perft.counts.iter().enumerate() {
+        let pos = Vector2::new(
+            sec.x + padding + (i / rows) as f32 * column_width,
+            top + (i % rows) as f32 * line_height,
+        );
+        d.draw_text_ex(font, &format!("{}: {}", mv, nodes), pos, font_size, 0.0, Color::LIGHTGRAY);
+    }
+}
+
+const TOAST_DURATION: Duration = Duration::from_secs(3);
+
+fn draw_toast(d: &mut RaylibDrawHandle, font: &Font, sec: &Rectangle, text: &str) {
+    let font_size = font.baseSize as f32 * 0.7;
+    let text_dim = text::measure_text_ex(font, text, font_size, 0.0);
+    let padding = Vector2::new(font_size, font_size * 0.5);
+    let rect = Rectangle {
+        x: sec.x + sec.width / 2.0 - text_dim.x / 2.0 - padding.x,
+        y: sec.y + sec.height * 0.9 - text_dim.y - 2.0 * padding.y,
+        width: text_dim.x + 2.0 * padding.x,
+        height: text_dim.y + 2.0 * padding.y,
+    };
+    d.draw_rectangle_rounded(rect, 0.3, 10, PROMPT_BACKGROUND);
+    d.draw_text_ex(font, text, Vector2::new(rect.x + padding.x, rect.y + padding.y), font_size, 0.0, Color::RAYWHITE);
+}
+
+// Shown next to the mouse, and moved to its left when it would go past the right edge of 'bounds'
+fn draw_tooltip(d: &mut RaylibDrawHandle, font: &Font, bounds: &Rectangle, mouse_pos: Vector2, text: &str) {
+    let font_size = font.baseSize as f32 * 0.6;
+    let text_dim = text::measure_text_ex(font, text, font_size, 0.0);
+    let padding = Vector2::new(font_size * 0.6, font_size * 0.4);
+    let (width, height) = (text_dim.x + 2.0 * padding.x, text_dim.y + 2.0 * padding.y);
+    let offset = font_size;
+    let x = if mouse_pos.x + offset + width > bounds.x + bounds.width {
+        mouse_pos.x - offset - width
+    } else {
+        mouse_pos.x + offset
+    };
+    let rect = Rectangle { x: x.max(bounds.x), y: mouse_pos.y + offset, width, height };
+    d.draw_rectangle_rounded(rect, 0.3, 10, PROMPT_BACKGROUND);
+    d.draw_text_ex(font, text, Vector2::new(rect.x + padding.x, rect.y + padding.y), font_size, 0.0, Color::RAYWHITE);
+}
+
+const FONT_RELOAD_DELAY: Duration = Duration::from_millis(250);
+
+// Size the move list font is loaded at, which follows the width of the panel it's in
+fn move_list_font_size(panel_width: f32) -> i32 {
+    ((panel_width * 0.04) as i32).clamp(14, 40)
 }
 
 const MOVELIST_LIGHT_BKGD: Color = Color::new(28, 28, 28, 255);
@@ -589,75 +1162,191 @@ enum MoveButtonType {
     Last
 }
 
+// Stand-in for the move button icons when their texture couldn't be loaded
+fn draw_move_btn_shape(d: &mut RaylibDrawHandle, kind: &MoveButtonType, playing: bool, target: Rectangle) {
+    let color = Color::RAYWHITE;
+    let (x, y, w, h) = (target.x + target.width * 0.25, target.y + target.height * 0.25, target.width * 0.5, target.height * 0.5);
+    let bar_width = w * 0.2;
+    // Arrow pointing left or right, filling the given part of the icon
+    let arrow = |d: &mut RaylibDrawHandle, x: f32, w: f32, left: bool| {
+        let (top, bottom, mid) = (y, y + h, y + h / 2.0);
+        if left {
+            d.draw_triangle(Vector2::new(x + w, top), Vector2::new(x, mid), Vector2::new(x + w, bottom), color);
+        } else {
+            d.draw_triangle(Vector2::new(x, top), Vector2::new(x, bottom), Vector2::new(x + w, mid), color);
+        }
+    };
+    match kind {
+        MoveButtonType::First => {
+            d.draw_rectangle_rec(Rectangle::new(x, y, bar_width, h), color);
+            arrow(d, x + bar_width, w - bar_width, true);
+        },
+        MoveButtonType::Previous => arrow(d, x, w, true),
+        MoveButtonType::PlayPause if playing => {
+            d.draw_rectangle_rec(Rectangle::new(x + w * 0.15, y, w * 0.25, h), color);
+            d.draw_rectangle_rec(Rectangle::new(x + w * 0.6, y, w * 0.25, h), color);
+        },
+        MoveButtonType::PlayPause | MoveButtonType::Next => arrow(d, x, w, false),
+        MoveButtonType::Last => {
+            arrow(d, x, w - bar_width, false);
+            d.draw_rectangle_rec(Rectangle::new(x + w - bar_width, y, bar_width, h), color);
+        },
+    }
+}
+
+// Engine name along with its author, if the engine reported one
+fn engine_label(engine: &EngineComm) -> String {
+    if engine.author().is_empty() {
+        engine.name().clone()
+    } else {
+        format!("{} by {}", engine.name(), engine.author())
+    }
+}
+
+// Piece sets that can be chosen in the config, along with their textures in the assets directory
+const PIECE_SETS: [(&str, &str); 3] = [
+    ("chesscom", "chesscom-pieces/chesscom_pieces.png"),
+    ("alpha", "lichess-pieces/alpha-pieces.png"),
+    ("cburnett", "lichess-pieces/cburnett-pieces.png"),
+];
+
+// Path of an asset that the GUI can do without, warning about it when it's missing
+fn recoverable_asset(paths: &Paths, name: &str) -> Option<String> {
+    paths.asset(name).map_err(|e| eprintln!("[WARN] {}", e)).ok()
+}
+
 const AUTHOR_TEXT: &str = "Developed by Michael T. Abayneh, 2024 (MIT License)";
 
-pub fn gui_main(engine_a_path: String, engine_b_path: Option<String>) -> Result<(), String> {
+pub fn gui_main(
+    engine_a_path: String, engine_b_path: Option<String>, engine_options: [EngineOptions; 2],
+    settings: MatchSettings, paths: &Paths
+) -> Result<(), GuiError> {
     let attack_info = AttackInfo::new();
     let zobrist_info = ZobristInfo::new();
 
-    // Load in a list of fens
-    let fens = if let Ok(content) = std::fs::read_to_string("fens.txt") {
-        content
-    } else {
-        eprintln!("[ERROR] Couldn't load fens from 'fens.txt'");
-        // Exiting due to the failure of reading fens from a file is temporary.
-        // This is only needed for testing
-        std::process::exit(0);
-    };
+    // Positions the games are played from
+    let fens = game_manager::load_positions(paths, &attack_info, &zobrist_info)?;
 
-    let engine_a = EngineComm::new(&engine_a_path);
-    let engine_b = if let Some(b_path) = engine_b_path {
-        EngineComm::new(&b_path)
-    } else {
-        EngineComm::new(&engine_a_path)
-    };
+    let (engine_a, engine_b) = comm::start_engines(&engine_a_path, engine_b_path.as_deref(), &engine_options)
+        .map_err(GuiError::EngineInit)?;
+    let engine_paths = [engine_a_path.clone(), engine_b_path.unwrap_or(engine_a_path)];
 
-    if engine_a.is_err() || engine_b.is_err() {
-        return Err("Failed to establish communication with specified engine(s) ".to_string());
-    }
-    let engine_a = engine_a.unwrap();
-    let engine_b = engine_b.unwrap();
+    let title = format!("Chess Engine GUI - {} vs {}", engine_label(&engine_a), engine_label(&engine_b));
+    let mut manager = GameManager::new(engine_a, engine_b, &attack_info, &zobrist_info);
 
-    let mut manager = GameManager::new(engine_a, engine_b, &zobrist_info);
+    let mut config = Config::load(CONFIG_FILE);
+    manager.set_movetime(Duration::from_millis(config.movetime_ms));
+    manager.set_watchdog_factor(config.watchdog_factor);
+    manager.apply_settings(&settings);
+    match EcoTable::load(&paths.optional_asset(ECO_FILE), &attack_info, &zobrist_info) {
+        Ok(openings) => manager.set_openings(openings),
+        Err(e) => eprintln!("[WARN] {}", e),
+    }
+    if settings.resume {
+        let state = MatchState::load(MATCH_STATE_FILE)?;
+        state.check_engines(&engine_paths)?;
+        manager.resume(&state, &fens, &attack_info, &zobrist_info)?;
+    } else {
+        // The first round starts from the first position, with the first engine as white
+        manager.reset_match(&fens, &attack_info, &zobrist_info);
+    }
+    let mut themes = Theme::BUILT_IN.to_vec();
+    for spec in &config.custom_themes {
+        match Theme::parse(spec) {
+            Ok(theme) if themes.iter().any(|t| t.name == theme.name) => {
+                eprintln!("[WARN] Ignoring custom theme '{}', as there is already a theme with that name", theme.name);
+            },
+            Ok(theme) => themes.push(theme),
+            Err(e) => eprintln!("[WARN] Ignoring invalid custom theme: {}", e),
+        }
+    }
+    let theme_ind = themes.iter().position(|theme| theme.name == config.theme).unwrap_or_else(|| {
+        eprintln!("[WARN] Unknown theme '{}', using the default one", config.theme);
+        0
+    });
+    let piece_set = PIECE_SETS.iter().find(|(name, _)| *name == config.piece_set).unwrap_or_else(|| {
+        eprintln!("[WARN] Unknown piece set '{}', using the default one", config.piece_set);
+        &PIECE_SETS[0]
+    });
+
+    // Every asset is checked before the window is opened. Only the pieces and the regular font
+    // are needed; the icons are drawn with shapes and the other fonts replaced by the regular
+    // one when they are missing.
+    let piece_tex_path = paths.asset(piece_set.1)?;
+    let regular_font_path = paths.asset("fonts/Inter-Regular.ttf")?;
+    let game_end_tex_path = recoverable_asset(paths, "chesscom-pieces/game-end-icons.png");
+    let btn_icons_path = recoverable_asset(paths, "move-player-icons.png");
+    let medium_font_path = recoverable_asset(paths, "fonts/Inter-Medium.ttf").unwrap_or(regular_font_path.clone());
+    let bold_font_path = recoverable_asset(paths, "fonts/Inter-Bold.ttf").unwrap_or(regular_font_path.clone());
 
     // Rendering initializations
     let (mut rl, thread) = raylib::init()
-        .size(1000, 600)
-        .title("Chess Engine GUI")
+        .size(config.window_width.max(1000), config.window_height.max(600))
+        .title(&title)
         .resizable()
         .msaa_4x()
         .build();
 
     rl.set_window_min_size(1000, 600);
     rl.set_target_fps(60);
+    // Escape is handled in the loop, since it also cancels a move that is being entered
+    rl.set_exit_key(None);
+    if let Some((x, y)) = config.window_pos {
+        rl.set_window_position(x, y);
+    }
 
     // Loading all the necessary textures
-    let piece_tex = rl.load_texture(&thread, "assets/chesscom-pieces/chesscom_pieces.png")?;
+    let piece_tex = rl.load_texture(&thread, &piece_tex_path).map_err(GuiError::asset(&piece_tex_path))?;
     piece_tex.set_texture_filter(&thread, TextureFilter::TEXTURE_FILTER_BILINEAR);
-    let game_end_tex = rl.load_texture(&thread, "assets/chesscom-pieces/game-end-icons.png")?;
-    game_end_tex.set_texture_filter(&thread, TextureFilter::TEXTURE_FILTER_BILINEAR);
-    let btn_icons = rl.load_texture(&thread, "assets/move-player-icons.png")?;
-    btn_icons.set_texture_filter(&thread, TextureFilter::TEXTURE_FILTER_BILINEAR);
+    let load_icons = |rl: &mut RaylibHandle, path: Option<String>| {
+        let tex = rl.load_texture(&thread, &path?)
+            .map_err(|e| eprintln!("[WARN] {}, drawing shapes instead", e)).ok()?;
+        tex.set_texture_filter(&thread, TextureFilter::TEXTURE_FILTER_BILINEAR);
+        Some(tex)
+    };
+    let game_end_tex = load_icons(&mut rl, game_end_tex_path);
+    let btn_icons = load_icons(&mut rl, btn_icons_path);
 
     // Load all the needed fonts
-    let font = rl.load_font(&thread, "assets/fonts/Inter-Regular.ttf")?;
-    let move_list_font = rl.load_font_ex(&thread, "assets/fonts/Inter-Medium.ttf", (rl.get_screen_width() as f32 * 0.02) as i32, FontLoadEx::Default(0))?;
-    let bold_font = rl.load_font(&thread, "assets/fonts/Inter-Bold.ttf")?;
+    let font = rl.load_font(&thread, &regular_font_path).map_err(GuiError::asset(&regular_font_path))?;
+    let load_move_list_font = |rl: &mut RaylibHandle, size: i32| {
+        rl.load_font_ex(&thread, &medium_font_path, size, FontLoadEx::Default(0))
+            .or_else(|_| rl.load_font_ex(&thread, &regular_font_path, size, FontLoadEx::Default(0)))
+    };
+    let bold_font = rl.load_font(&thread, &bold_font_path)
+        .or_else(|_| rl.load_font(&thread, &regular_font_path))
+        .map_err(GuiError::asset(&bold_font_path))?;
     // TODO: figure out how to load the 'author_font' without losing the visual quality of the
     // text, i.e. get blurry. The issue seems to be with font sizes less than or equal to 20 and
     // the author text should be smaller than that so...
-    let author_font = rl.load_font_ex(&thread, "assets/fonts/Inter-Regular.ttf", 20, FontLoadEx::Default(0))?;
+    let author_font = rl.load_font_ex(&thread, &regular_font_path, 20, FontLoadEx::Default(0))
+        .map_err(GuiError::asset(&regular_font_path))?;
 
     let mut gui = GUI::new();
+    gui.themes = themes;
+    gui.theme_ind = theme_ind;
+    gui.flip = config.flip;
+    gui.orientation = Orientation::from_name(&config.orientation).unwrap_or_else(|| {
+        eprintln!("[WARN] Unknown board orientation '{}', using the default one", config.orientation);
+        Orientation::default()
+    });
+    // The person playing has their pieces at the bottom
+    if let Some(side) = manager.human() {
+        gui.face_side(side);
+    }
+    gui.coord_style = CoordStyle::from_name(&config.coord_style).unwrap_or_else(|| {
+        eprintln!("[WARN] Unknown coordinate style '{}', using the default one", config.coord_style);
+        CoordStyle::default()
+    });
+    let replay_interval = Duration::from_millis(config.replay_interval_ms);
     gui.init_sections(rl.get_screen_width(), rl.get_screen_height());
+    let mut loaded_font_size = move_list_font_size(gui.info_sec.width);
+    let mut move_list_font = load_move_list_font(&mut rl, loaded_font_size).map_err(GuiError::asset(&medium_font_path))?;
+    // When the window was last resized, until the move list font is reloaded for its new size
+    let mut resized_at = None;
 
     // Move Animations
-    let mut anim_start_time = Instant::now();
-    let mut anim_mv: Option<Move> = None;
-    let mut is_animating = false;
-    let mut anim_board = manager.current_game().board_after_last_move().cloned().unwrap();
-    let mut anim_target_board = None;
-    let anim_duration_secs = 0.2;
+    let mut anim = BoardAnimation::new(manager.current_move_count());
 
     let mut move_index: usize = 0;
     let mut new_input = false;
@@ -670,8 +1359,60 @@ pub fn gui_main(engine_a_path: String, engine_b_path: Option<String>) -> Result<
         let mouse_pos = rl.get_mouse_position();
         let size = Vector2::new(rl.get_screen_width() as f32, rl.get_screen_height() as f32);
         let margin = Vector2::new(size.x * 0.01, size.y * 0.03);
+        gui.side_to_move = board_at(manager.current_game(), anim.board()).side_to_move();
         gui.update_sections(size, margin);
         gui.handle_scrolling(&rl);
+        // While a FEN is being typed, the keys go to it instead of their usual use
+        let typing = gui.fen_input.is_focused();
+        let mut typed_fen = None;
+        if typing {
+            gui.fen_input.update(&mut rl, |text| typed_fen = Some(text.to_string()));
+        }
+        // Escape quits only when there's no move being entered to cancel
+        if !typing && rl.is_key_pressed(KeyboardKey::KEY_ESCAPE) && !gui.cancel_move_input() {
+            break;
+        }
+        if rl.is_mouse_button_pressed(MouseButton::MOUSE_RIGHT_BUTTON) {
+            gui.cancel_move_input();
+        }
+        // The person's move is entered on the latest position of the game, while it's being played
+        let mut entered_move = None;
+        if manager.playing() && manager.is_human_turn()
+            && anim.latest() == manager.current_move_count() {
+            let board = board_at(manager.current_game(), manager.current_move_count());
+            gui.update_player(&rl, board, manager.human());
+            entered_move = gui.take_entered_move().and_then(|uci| moves::from_uci(&uci, board, &attack_info));
+        } else {
+            gui.cancel_move_input();
+        }
+
+        // Loading a font is slow, so the move list font is only reloaded once the window has kept
+        // the same size for a moment, and only if the text would be noticeably bigger or smaller
+        if rl.is_window_resized() {
+            resized_at = Some(Instant::now());
+        }
+        if resized_at.map_or(false, |time| time.elapsed() >= FONT_RELOAD_DELAY) {
+            resized_at = None;
+            let font_size = move_list_font_size(gui.info_sec.width);
+            if (font_size - loaded_font_size).abs() >= 2 {
+                match load_move_list_font(&mut rl, font_size) {
+                    Ok(font) => {
+                        move_list_font = font;
+                        loaded_font_size = font_size;
+                    },
+                    Err(e) => eprintln!("[WARN] Couldn't reload the move list font: {}", e),
+                }
+            }
+        }
+
+        // Move of the list under the mouse, whose FEN is shown next to it
+        let hovered_move = if gui.move_list_sec.check_collision_point_rec(mouse_pos) {
+            let move_count = manager.current_move_count();
+            let layout = MoveListLayout::new(&gui.move_list_rect, gui.move_list_sec.height, move_list_font.baseSize as f32, move_count);
+            layout.move_at(&gui.move_list_rect, mouse_pos, move_count)
+        } else {
+            None
+        };
 
         // Move buttons
         let mut move_btns = {
@@ -715,7 +1456,24 @@ pub fn gui_main(engine_a_path: String, engine_b_path: Option<String>) -> Result<
             }
         }
 
-        if rl.is_key_pressed(KeyboardKey::KEY_SPACE) {
+        if let Some(text) = typed_fen {
+            match fen::try_parse(text.trim(), &zobrist_info) {
+                Ok(board) => {
+                    gui.confirm = None;
+                    manager.start_game_from_fen(&fen::gen_fen(&board), &attack_info, &zobrist_info);
+                    move_index = 0;
+                    new_input = true;
+                },
+                Err(e) => {
+                    // The FEN stays, so that it can be fixed
+                    gui.toast = Some((format!("Invalid FEN: {}", e), Instant::now()));
+                    gui.fen_input.focus();
+                },
+            }
+        } else if typing {
+            // The keys were meant for the FEN being typed
+        } else if rl.is_key_pressed(KeyboardKey::KEY_SPACE) {
+            gui.confirm = None;
             manager.toggle_playing();
             if manager.playing() && !gui.follow_move_list {
                 gui.follow_move_list = true;
@@ -742,103 +1500,293 @@ pub fn gui_main(engine_a_path: String, engine_b_path: Option<String>) -> Result<
             new_input = true;
         } else if rl.is_key_pressed(KeyboardKey::KEY_F) {
             let game = manager.current_game();
-            let current_fen = game.current_fen();
+            let current_fen = match hovered_move {
+                Some(i) => fen::gen_fen(game.board_after_move(i).unwrap()),
+                None => game.current_fen(),
+            };
             if rl.set_clipboard_text(&current_fen).is_err() {
                 eprintln!("[ERROR] Failed to copy clipboard to fen");
+            } else if hovered_move.is_some() {
+                gui.toast = Some(("Copied the FEN of the move".to_string(), Instant::now()));
+            }
+        } else if rl.is_key_pressed(KeyboardKey::KEY_E) {
+            if rl.set_clipboard_text(&position_summary(manager.current_game())).is_err() {
+                eprintln!("[ERROR] Failed to copy the position and its eval to the clipboard");
+            }
+        } else if rl.is_key_pressed(KeyboardKey::KEY_V) {
+            let text = rl.get_clipboard_text().unwrap_or_default();
+            match fen::try_parse(text.trim(), &zobrist_info) {
+                Ok(board) => {
+                    gui.confirm = None;
+                    manager.start_game_from_fen(&fen::gen_fen(&board), &attack_info, &zobrist_info);
+                    move_index = 0;
+                    new_input = true;
+                },
+                Err(e) => {
+                    eprintln!("[WARN] Couldn't set up the position from the clipboard: {}", e);
+                    gui.toast = Some(("Clipboard doesn't contain a valid FEN".to_string(), Instant::now()));
+                },
+            }
+        } else if rl.is_key_pressed(KeyboardKey::KEY_L) {
+            // Starts from the current position, which is often only a few edits away
+            gui.fen_input.set_value(&manager.current_game().current_fen());
+            gui.fen_input.focus();
+        } else if rl.is_key_pressed(KeyboardKey::KEY_P) {
+            // Plays from a random position of the list as the start of a new round
+            let fen = manager.random_fen(&fens).unwrap_or_default();
+            match fen::try_parse(fen, &zobrist_info) {
+                Ok(_) => {
+                    gui.confirm = None;
+                    manager.start_game_from_fen(fen, &attack_info, &zobrist_info);
+                    move_index = 0;
+                    new_input = true;
+                },
+                Err(e) => eprintln!("[WARN] Couldn't play from the position '{}': {}", fen, e),
             }
         } else if rl.is_key_pressed(KeyboardKey::KEY_N) {
-            manager.start_new_game(&fens, &zobrist_info);
-            move_index = 0;
+            let game = manager.current_game();
+            if game.is_ongoing() && game.move_count() > 0 && gui.confirm != Some(Confirmation::NewGame) {
+                // Don't throw away a game that is still going on without asking first
+                manager.stop();
+                gui.confirm = Some(Confirmation::NewGame);
+            } else {
+                gui.confirm = None;
+                if manager.start_new_game(&fens, &attack_info, &zobrist_info) {
+                    move_index = 0;
+                    new_input = true;
+                } else if manager.is_match_over() {
+                    gui.toast = Some(("The match is over".to_string(), Instant::now()));
+                }
+            }
+        } else if rl.is_key_pressed(KeyboardKey::KEY_M) {
+            let started = manager.standings().games() > 0 || manager.current_move_count() > 0;
+            if started && !manager.is_match_over() && gui.confirm != Some(Confirmation::NewMatch) {
+                manager.stop();
+                gui.confirm = Some(Confirmation::NewMatch);
+            } else {
+                gui.confirm = None;
+                if manager.reset_match(&fens, &attack_info, &zobrist_info) {
+                    move_index = 0;
+                    new_input = true;
+                    gui.toast = Some(("Started a new match".to_string(), Instant::now()));
+                }
+            }
+        } else if rl.is_key_pressed(KeyboardKey::KEY_C) {
+            gui.jump_to_current_move();
+        } else if rl.is_key_pressed(KeyboardKey::KEY_A) {
+            if manager.is_analyzing() {
+                manager.stop_analysis();
+            } else {
+                let board = board_at(manager.current_game(), anim.latest());
+                gui.analysis_fen = fen::gen_fen(board);
+                manager.start_analysis(&gui.analysis_fen);
+            }
+        } else if rl.is_key_pressed(KeyboardKey::KEY_R) {
+            if gui.auto_replay {
+                gui.auto_replay = false;
+            } else if manager.playing() {
+                eprintln!("[WARN] The game can only be replayed while it's paused");
+            } else if manager.current_move_count() > 0 {
+                // Replaying from the last move would end right away, so start over instead
+                if move_index + 1 >= manager.current_move_count() {
+                    move_index = 0;
+                    new_input = true;
+                }
+                gui.auto_replay = true;
+                gui.last_replay_step = Instant::now();
+            }
+        } else if rl.is_key_pressed(KeyboardKey::KEY_X) {
+            // Turning the board by hand keeps it that way
+            gui.flip = !gui.flipped();
+            gui.orientation = Orientation::Fixed;
+        } else if rl.is_key_pressed(KeyboardKey::KEY_O) {
+            gui.orientation = gui.orientation.next();
+            gui.toast = Some((gui.orientation.description().to_string(), Instant::now()));
+        } else if rl.is_key_pressed(KeyboardKey::KEY_W) {
+            if !manager.set_white(manager.white_engine() ^ 1) {
+                eprintln!("[WARN] Sides can only be swapped before the first move of a game");
+            } else if let Some(side) = manager.human() {
+                gui.face_side(side);
+            }
+        } else if rl.is_key_pressed(KeyboardKey::KEY_U) {
+            // Goes from the engines playing each other to the person playing white, then black
+            let (human, text) = match manager.human() {
+                None => (Some(PieceColor::Light), "You play white"),
+                Some(PieceColor::Light) => (Some(PieceColor::Dark), "You play black"),
+                _ => (None, "The engines play each other"),
+            };
+            if manager.set_human(human) {
+                if let Some(side) = human {
+                    gui.face_side(side);
+                }
+                gui.toast = Some((text.to_string(), Instant::now()));
+            } else {
+                eprintln!("[WARN] Sides can only be changed before the first move of a game");
+            }
+        } else if rl.is_key_pressed(KeyboardKey::KEY_D) {
+            // Switches between searching for a fixed time and to a fixed depth, from the next move
+            let text = if manager.search_mode() == SearchMode::Movetime {
+                manager.set_search_mode(SearchMode::Depth);
+                format!("Searching to depth {}", manager.depth())
+            } else {
+                manager.set_search_mode(SearchMode::Movetime);
+                format!("Searching for {} ms per move", manager.movetime().as_millis())
+            };
+            gui.toast = Some((text, Instant::now()));
+        } else if rl.is_key_pressed(KeyboardKey::KEY_J) {
+            // Applies from the next move of the current game
+            let adjudication = manager.adjudication();
+            let policy = adjudication.policy.next();
+            manager.set_adjudication(Adjudication { policy, ..adjudication });
+            gui.toast = Some((policy.description().to_string(), Instant::now()));
+        } else if rl.is_key_pressed(KeyboardKey::KEY_H) || rl.is_key_pressed(KeyboardKey::KEY_SLASH) {
+            gui.show_help = !gui.show_help;
+        } else if rl.is_key_pressed(KeyboardKey::KEY_T) {
+            gui.theme_ind = (gui.theme_ind + 1) % gui.themes.len();
+            gui.toast = Some((format!("Theme: {}", gui.theme().name), Instant::now()));
+        } else if rl.is_key_pressed(KeyboardKey::KEY_G) {
+            gui.show_depth_graph = !gui.show_depth_graph;
+        } else if rl.is_key_pressed(KeyboardKey::KEY_Z) {
+            gui.perft = if gui.perft.is_some() { None } else {
+                Some(PerftOverlay { depth: PerftOverlay::DEFAULT_DEPTH, counted: None, counts: vec![] })
+            };
+        } else if gui.perft.is_some() && (rl.is_key_pressed(KeyboardKey::KEY_EQUAL) || rl.is_key_pressed(KeyboardKey::KEY_KP_ADD)) {
+            if let Some(perft) = &mut gui.perft {
+                perft.depth = (perft.depth + 1).min(PerftOverlay::MAX_DEPTH);
+            }
+        } else if gui.perft.is_some() && (rl.is_key_pressed(KeyboardKey::KEY_MINUS) || rl.is_key_pressed(KeyboardKey::KEY_KP_SUBTRACT)) {
+            if let Some(perft) = &mut gui.perft {
+                perft.depth = (perft.depth - 1).max(1);
+            }
+        } else if rl.is_key_pressed(KeyboardKey::KEY_I) {
+            gui.show_sq_indices = !gui.show_sq_indices;
         }
 
-        manager.check_state();
-        manager.update_time_left(rl.get_frame_time());
-        if let Some(mv) = manager.play(rl.get_frame_time(), &attack_info, &zobrist_info) {
-            move_index += 1;
+        // The analysis follows the position that is being displayed
+        if manager.is_analyzing() {
+            let board = board_at(manager.current_game(), anim.latest());
+            let fen = fen::gen_fen(board);
+            if fen != gui.analysis_fen {
+                manager.start_analysis(&fen);
+                gui.analysis_fen = fen;
+            }
+            let (name, info) = manager.poll_analysis();
+            gui.analysis = Some((name.clone(), info.clone()));
+        } else {
+            gui.analysis = None;
+        }
 
-            is_animating = true;
-            anim_start_time = Instant::now();
-            anim_mv = Some(mv);
-            let game = manager.current_game();
-            anim_target_board = game.board_after_last_move().cloned();
+        if let Some(perft) = &mut gui.perft {
+            let board = board_at(manager.current_game(), anim.latest());
+            perft.update(board, &attack_info, &zobrist_info);
         }
 
-        /* ==================== RENDER PHASE ==================== */
-        fn draw_pieces(d: &mut RaylibDrawHandle, skip_sq: Option<Sq>, tex: &Texture2D, board: &Board, sec: &Rectangle) {
-            for r in 0..8 {
-                for f in 0..8 {
-                    let sq = SQ!(r, f);
-                    if let Some(s_sq) = skip_sq {
-                        if s_sq as usize == sq {
-                            continue;
-                        }
-                    }
-                    if let Some(piece) = board.find_piece(sq) {
-                        draw_piece(d, tex, piece_rect_on_board(sec, sq), piece);
-                    }
-                }
+        // Steps through the game the same way as the 'Next' button
+        if gui.auto_replay && manager.playing() {
+            gui.auto_replay = false;
+        } else if gui.auto_replay && gui.last_replay_step.elapsed() >= replay_interval {
+            gui.last_replay_step = Instant::now();
+            if move_index + 1 < manager.current_move_count() {
+                move_index += 1;
+            } else if config.replay_loop {
+                move_index = 0;
+            } else {
+                gui.auto_replay = false;
             }
+            new_input = gui.auto_replay;
+            gui.follow_move_list = true;
         }
 
-        fn anim_piece(d: &mut RaylibDrawHandle, boundary: &Rectangle, tex: &Texture2D, mv: Move, t: f32) {
-            let source_rect = piece_rect_on_board(boundary, mv.source() as usize);
-            let target_rect = piece_rect_on_board(boundary, mv.target() as usize);
-            let piece = mv.piece();
-            let source_vec = Vector2::new(source_rect.x, source_rect.y);
-            let target_vec = Vector2::new(target_rect.x, target_rect.y);
-            let anim_pos = source_vec.lerp(target_vec, t as f32);
-            let anim_rect = Rectangle::new(anim_pos.x, anim_pos.y, source_rect.width, source_rect.height);
-            draw_piece(d, tex, anim_rect, piece);
+        if manager.check_state() {
+            if let Some(summary) = manager.round_summary() {
+                println!("{}", summary);
+                gui.toast = Some((summary, Instant::now()));
+            }
+            if manager.is_match_over() {
+                println!("Match over");
+                manager.print_standings();
+                gui.toast = Some(("The match is over".to_string(), Instant::now()));
+            }
+        }
+        let played = match entered_move {
+            Some(mv) => manager.play_human_move(mv, &attack_info, &zobrist_info).then_some(mv),
+            None => manager.play(&attack_info, &zobrist_info),
+        };
+        if let Some(mv) = played {
+            move_index += 1;
+            anim.push(mv, manager.current_move_count(), Instant::now());
+        } else if manager.playing() {
+            anim.follow(manager.current_move_count());
         }
 
+        /* ==================== RENDER PHASE ==================== */
         let game = manager.current_game();
 
         let mut d = rl.begin_drawing(&thread);
         d.clear_background(BACKGROUND);
 
         if !manager.playing() && new_input {
-            anim_mv = game.move_at(move_index).copied();
-            anim_board = game.board_before_move(move_index).cloned().unwrap();
-            anim_target_board = game.board_after_move(move_index).cloned();
+            let target_board = game.board_after_move(move_index).map(|_| move_index + 1);
+            anim.go_to(game.move_at(move_index).copied(), move_index, target_board, Instant::now());
             new_input = false;
-            is_animating = true;
         }
 
-        if let Some(mv) = anim_mv {
+        let anim_t = anim.update(Instant::now());
+        if let Some(mv) = anim.mv() {
             source = Some(mv.source());
             target = Some(mv.target());
         };
-        draw_board(&mut d, &gui.board_sec, source, target);
+        let flip = gui.flipped();
+        render::draw_board(&mut d, gui.theme(), &gui.board_sec, flip, source, target, gui.selected);
 
-        d.draw_rectangle_lines_ex(gui.board_sec, 2, Color::RED);
+        render::draw_border(&mut d, gui.theme(), &gui.board_sec, size.y);
         d.draw_rectangle_lines_ex(gui.white_name_sec, 2, Color::GREEN);
         d.draw_rectangle_lines_ex(gui.black_name_sec, 2, Color::GREEN);
 
-        draw_coords(&mut d, &bold_font, &gui.board_sec);
-        let skip_sq = if is_animating { source } else { None };
-        draw_pieces(&mut d, skip_sq, &piece_tex, &anim_board, &gui.board_sec);
-
-        if let Some(mv) = anim_mv {
-            // anim_t = (NOW - anim_start_time) / ANIM_DURATION_SECS;
-            let elapsed = Instant::now().duration_since(anim_start_time);
-            let anim_t = elapsed.div_f32(anim_duration_secs).as_secs_f32();
-            if is_animating && anim_t >= 1.0 {
-                is_animating = false;
-                anim_mv = None;
-                if let Some(board) = anim_target_board.take() {
-                    anim_board = board;
-                }
-                // Instantly make the move by drawing the target board
-                draw_pieces(&mut d, None, &piece_tex, &anim_board, &gui.board_sec);
-            }
-
-            if is_animating {
-                anim_piece(&mut d, &gui.board_sec, &piece_tex, mv, anim_t);
-            }
+        render::draw_coords(&mut d, &bold_font, gui.theme(), &gui.board_sec, flip, gui.coord_style);
+        let skip_sq = anim_t.and(source);
+        render::draw_pieces(&mut d, skip_sq, &piece_tex, board_at(game, anim.board()), &gui.board_sec, flip);
+        if let (Some(mv), Some(anim_t)) = (anim.mv(), anim_t) {
+            render::anim_piece(&mut d, &gui.board_sec, &piece_tex, mv, anim_t, flip);
         }
 
+        if gui.show_sq_indices {
+            render::draw_sq_indices(&mut d, &bold_font, &gui.board_sec, flip);
+        }
         if !game.is_ongoing() && move_index == manager.current_move_count() {
-            draw_markers(&mut d, &anim_board, &game_end_tex, &gui.board_sec, game.state());
+            render::draw_markers(&mut d, board_at(game, anim.board()), game_end_tex.as_ref(), &gui.board_sec, game.state(), flip);
+        }
+        match gui.confirm {
+            Some(Confirmation::NewGame) => draw_prompt(&mut d, &font, &gui.board_sec, &[
+                "The current game hasn't finished yet.",
+                "Press N again to discard it and start a new game,",
+                "or Space to continue playing.",
+            ]),
+            Some(Confirmation::NewMatch) => draw_prompt(&mut d, &font, &gui.board_sec, &[
+                "The current match hasn't finished yet.",
+                "Press M again to discard its score and start over,",
+                "or Space to continue playing.",
+            ]),
+            None => {},
+        }
+        if gui.is_promotion {
+            draw_promotion_picker(&mut d, &piece_tex, &gui.promotion_sec, game.board_after_last_move().unwrap().side_to_move(), flip);
+        }
+        if let Some(perft) = &gui.perft {
+            draw_perft(&mut d, &font, &bold_font, &gui.board_sec, perft);
+        }
+        if gui.show_help {
+            draw_help(&mut d, &font, &bold_font, &gui.board_sec);
+        }
+        if gui.fen_input.is_focused() {
+            gui.fen_input.draw(&mut d, &font, gui.theme().light_selected);
+        }
+        if let Some((text, shown_at)) = &gui.toast {
+            if shown_at.elapsed() < TOAST_DURATION {
+                draw_toast(&mut d, &font, &gui.board_sec, text);
+            } else {
+                gui.toast = None;
+            }
         }
         for btn in &move_btns {
             btn.draw(&mut d, mouse_pos);
@@ -849,6 +1797,10 @@ pub fn gui_main(engine_a_path: String, engine_b_path: Option<String>) -> Result<
                 width: min_side,
                 height: min_side,
             };
+            let btn_icons = if let Some(tex) = &btn_icons { tex } else {
+                draw_move_btn_shape(&mut d, btn.kind(), manager.playing(), target);
+                continue;
+            };
             let frame_width = btn_icons.width() as f32 / 6.0;
             let ind = match *btn.kind() {
                 MoveButtonType::First     => 4,
@@ -860,12 +1812,50 @@ pub fn gui_main(engine_a_path: String, engine_b_path: Option<String>) -> Result<
                 }
             } as f32;
             let source = Rectangle::new(ind*frame_width, 0.0, frame_width, btn_icons.height() as f32);
-            d.draw_texture_pro(&btn_icons, source, target, Vector2::zero(), 0.0, Color::WHITE);
+            d.draw_texture_pro(btn_icons, source, target, Vector2::zero(), 0.0, Color::WHITE);
+        }
+        if let Some(board) = game.board_after_last_move() {
+            let btn_rect = move_btns[0].content_rect();
+            let pos = Vector2::new(btn_rect.x, btn_rect.y + 2.0*btn_rect.height + margin.y);
+            // The position on the board, which follows the move list while scrubbing through it
+            let displayed = board_at(game, anim.latest());
+            draw_move_number(&mut d, &font, pos, displayed);
+            let pos = Vector2::new(pos.x, pos.y + font.baseSize as f32 + margin.y);
+            draw_fifty_move_counter(&mut d, &font, pos, board, game.repetition_count());
+            let pos = Vector2::new(pos.x, pos.y + font.baseSize as f32 + margin.y);
+            draw_opening(&mut d, &font, pos, game.opening());
+            let pos = Vector2::new(pos.x, pos.y + font.baseSize as f32 + margin.y);
+            let engine_is_white = game.board_before_move(move_index).map_or(true, |b| b.is_white_to_move());
+            draw_eval(&mut d, &font, pos, game.score_at(move_index), engine_is_white);
+            // The engine's line, from the position it was searching
+            let pv = game.search_at(move_index).zip(game.board_before_move(move_index))
+                .map(|(info, board)| pgn::pv_to_san(&info.pv, board, &attack_info, &zobrist_info));
+            let pos = Vector2::new(pos.x, pos.y + font.baseSize as f32);
+            let pv_width = gui.info_sec.x + gui.info_sec.width - pos.x - margin.x;
+            draw_pv(&mut d, &font, pos, pv.as_deref().unwrap_or("-"), pv_width);
+            // How the engines compare over the match so far
+            let mut pos = pos;
+            for summary in [manager.standings().elo_summary(), manager.sprt_summary()].iter().flatten() {
+                pos = Vector2::new(pos.x, pos.y + font.baseSize as f32 * 0.8);
+                d.draw_text_ex(&font, summary, pos, font.baseSize as f32 * 0.6, 0.0, Color::LIGHTGRAY);
+            }
+            let pos = Vector2::new(pos.x, pos.y + font.baseSize as f32 + margin.y);
+            draw_game_over_text(&mut d, &bold_font, pos, game.state());
+            if let Some((name, info)) = &gui.analysis {
+                let pos = Vector2::new(pos.x, pos.y + font.baseSize as f32 + margin.y);
+                let white_to_move = gui.analysis_fen.split_whitespace().nth(1) == Some("w");
+                let pv = fen::try_parse(&gui.analysis_fen, &zobrist_info)
+                    .map(|board| pgn::pv_to_san(&info.pv, &board, &attack_info, &zobrist_info))
+                    .unwrap_or_default();
+                draw_analysis(&mut d, &font, pos, name, info, &pv, white_to_move);
+            }
+        }
+        if gui.show_depth_graph {
+            draw_depth_graph(&mut d, &font, &gui.graph_sec, game, move_index);
         }
 
         let (white_time, black_time) = manager.time_left();
-        // '0' represents white, while '1' represents black
-        let is_white_to_move = manager.side() == 0;
+        let is_white_to_move = game.is_white_to_move();
         draw_players_name(&mut d, &font, &gui.white_name_sec, game.white_name(), white_time, is_white_to_move);
         draw_players_name(&mut d, &font, &gui.black_name_sec, game.black_name(), black_time, !is_white_to_move);
 
@@ -877,7 +1867,7 @@ pub fn gui_main(engine_a_path: String, engine_b_path: Option<String>) -> Result<
                 width: gui.info_sec.width,
                 height,
             };
-            d.draw_rectangle_lines_ex(gui.info_sec, 3, Color::GOLD);
+            render::draw_border(&mut d, gui.theme(), &gui.info_sec, size.y);
             d.draw_rectangle_lines_ex(author_rect, 3, Color::DARKBLUE);
             let text_dim = text::measure_text_ex(&author_font, AUTHOR_TEXT, font.baseSize as f32, 0.0);
             d.draw_text_ex(
@@ -890,15 +1880,342 @@ pub fn gui_main(engine_a_path: String, engine_b_path: Option<String>) -> Result<
             );
         }
 
-        let mut s = d.begin_scissor_mode(
-            gui.move_list_sec.x as i32,
-            gui.move_list_sec.y as i32,
-            gui.move_list_sec.width as i32,
-            gui.move_list_sec.height as i32,
-        );
-        gui.curr_move_rect = draw_moves(&mut s, &mut gui.move_list_rect, &move_list_font, &game, move_index);
-        s.draw_rectangle_lines_ex(gui.move_list_sec, 3, Color::RAYWHITE);
+        {
+            let mut s = d.begin_scissor_mode(
+                gui.move_list_sec.x as i32,
+                gui.move_list_sec.y as i32,
+                gui.move_list_sec.width as i32,
+                gui.move_list_sec.height as i32,
+            );
+            gui.curr_move_rect = draw_moves(
+                &mut s, &mut gui.move_list_rect, gui.move_list_sec.height, &move_list_font, &game, move_index
+            );
+            s.draw_rectangle_lines_ex(gui.move_list_sec, 3, Color::RAYWHITE);
+        }
+        if let Some(board) = hovered_move.and_then(|i| game.board_after_move(i)) {
+            let window = Rectangle::new(0.0, 0.0, size.x, size.y);
+            draw_tooltip(&mut d, &font, &window, mouse_pos, &fen::gen_fen(board));
+        }
+    }
+
+    manager.save_finished_game(&attack_info, &zobrist_info);
+    if let Err(e) = manager.match_state(engine_paths).save(MATCH_STATE_FILE) {
+        eprintln!("[WARN] {}", e);
+    }
+
+    // Remember the window and settings for the next launch
+    let window_pos = rl.get_window_position();
+    config.window_width = rl.get_screen_width();
+    config.window_height = rl.get_screen_height();
+    config.window_pos = Some((window_pos.x as i32, window_pos.y as i32));
+    config.theme = gui.theme().name.to_string();
+    config.piece_set = piece_set.0.to_string();
+    config.movetime_ms = manager.movetime().as_millis() as u64;
+    config.flip = gui.flip;
+    config.orientation = gui.orientation.name().to_string();
+    config.coord_style = gui.coord_style.name().to_string();
+    if let Err(e) = config.save(CONFIG_FILE) {
+        eprintln!("[WARN] {}", e);
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use raylib::prelude::*;
+
+    use chess::attack;
+    use chess::board::Board;
+    use chess::consts::{Piece, PieceColor, Sq};
+    use chess::moves;
+    use chess::zobrist;
+
+    use crate::comm::SearchInfo;
+    use crate::game::Game;
+    use crate::gui::{
+        can_select, describe_search, format_score, move_list_font_size, move_number_text, position_summary, promotion_choices,
+        sq_at, BoardAnimation, MoveListLayout, Orientation, GUI, KEY_BINDINGS,
+    };
+    use crate::render::CoordStyle;
+
+    use raylib::prelude::Vector2;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn name_boxes_follow_the_board_orientation() {
+        let mut gui = GUI::new();
+        let (size, margin) = (Vector2::new(1280.0, 720.0), Vector2::new(12.8, 21.6));
+        gui.update_sections(size, margin);
+        assert!(gui.white_name_sec.y > gui.board_sec.y + gui.board_sec.height);
+        assert!(gui.black_name_sec.y < gui.board_sec.y);
+
+        gui.flip = true;
+        gui.update_sections(size, margin);
+        assert!(gui.white_name_sec.y < gui.board_sec.y);
+        assert!(gui.black_name_sec.y > gui.board_sec.y + gui.board_sec.height);
+    }
+
+    #[test]
+    fn outside_coords_get_room_next_to_the_board() {
+        let mut gui = GUI::new();
+        let (size, margin) = (Vector2::new(1280.0, 720.0), Vector2::new(12.8, 21.6));
+        gui.update_sections(size, margin);
+        let inside = gui.board_sec;
+        let info_x = gui.info_sec.x;
+
+        for style in [CoordStyle::Outside, CoordStyle::AllEdges] {
+            gui.coord_style = style;
+            gui.update_sections(size, margin);
+            let board = gui.board_sec;
+            let gutter = style.gutter(inside.width);
+            assert_eq!(board.width, board.height);
+            assert_eq!(board.x, inside.x + gutter);
+            // Neither the names nor the info panel move
+            assert_eq!(gui.info_sec.x, info_x);
+            let top_gutter = if style == CoordStyle::AllEdges { gutter } else { 0.0 };
+            assert!(gui.black_name_sec.y + gui.black_name_sec.height <= board.y - top_gutter);
+            assert!(gui.white_name_sec.y >= board.y + board.height + gutter);
+        }
+        assert_eq!(CoordStyle::from_name(CoordStyle::AllEdges.name()), Some(CoordStyle::AllEdges));
+        assert_eq!(CoordStyle::from_name("left"), None);
+    }
+
+    #[test]
+    fn move_list_font_follows_the_panel() {
+        let mut gui = GUI::new();
+        gui.update_sections(Vector2::new(1000.0, 600.0), Vector2::new(10.0, 18.0));
+        let small = move_list_font_size(gui.info_sec.width);
+        gui.update_sections(Vector2::new(1920.0, 1080.0), Vector2::new(19.2, 32.4));
+        let big = move_list_font_size(gui.info_sec.width);
+        assert!(small >= 14 && small < big && big <= 40, "{} {}", small, big);
+        assert_eq!(move_list_font_size(0.0), 14);
+        assert_eq!(move_list_font_size(10000.0), 40);
+    }
+
+    #[test]
+    fn find_the_move_under_the_mouse() {
+        // One column of 6 rows, where row 0 is highlighted from y = 20 to y = 60
+        let sec = Rectangle::new(0.0, 0.0, 160.0, 300.0);
+        let layout = MoveListLayout::new(&sec, sec.height, 20.0, 10);
+        let move_at = |x, y| layout.move_at(&sec, Vector2::new(x, y), 10);
+        assert_eq!(move_at(48.0, 30.0), Some(0));
+        assert_eq!(move_at(128.0, 21.0), Some(1));
+        assert_eq!(move_at(48.0, 70.0), Some(2));
+        assert_eq!(move_at(128.0, 185.0), Some(9));
+        // The move number, above the first row and past the last move
+        assert_eq!(move_at(5.0, 30.0), None);
+        assert_eq!(move_at(48.0, 10.0), None);
+        assert_eq!(move_at(48.0, 225.0), None);
+
+        // Three columns of 7 rows, scrolled up by 40
+        let sec = Rectangle::new(0.0, -40.0, 480.0, 300.0);
+        let layout = MoveListLayout::new(&sec, sec.height, 20.0, 40);
+        assert_eq!(layout.move_at(&sec, Vector2::new(208.0, 30.0), 40), Some(16));
+        assert_eq!(layout.move_at(&sec, Vector2::new(208.0, -10.0), 40), Some(14));
+    }
+
+    #[test]
+    fn select_only_pieces_of_the_side_to_move() {
+        let zobrist_info = zobrist::shared_zobrist_info();
+        // Black to move after 1. e4
+        let board = Board::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1", zobrist_info);
+        let (e7, e4, e5) = (Sq::E7 as usize, Sq::E4 as usize, Sq::E5 as usize);
+        assert!(can_select(&board, e7, None));
+        assert!(!can_select(&board, e4, None));
+        assert!(!can_select(&board, e5, None));
+        assert!(can_select(&board, e7, Some(PieceColor::Dark)));
+        // It's not the human's turn
+        assert!(!can_select(&board, e7, Some(PieceColor::Light)));
+        assert!(!can_select(&board, e4, Some(PieceColor::Light)));
+    }
+
+    #[test]
+    fn move_number_follows_the_position() {
+        let zobrist_info = zobrist::shared_zobrist_info();
+        let board = Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", zobrist_info);
+        assert_eq!(move_number_text(&board), "Move 1, White to move");
+        let board = Board::from_fen("8/8/4k3/8/8/4K3/8/8 b - - 12 24", zobrist_info);
+        assert_eq!(move_number_text(&board), "Move 24, Black to move");
+    }
+
+    #[test]
+    fn promotion_choices_follow_side_and_flip() {
+        assert_eq!(promotion_choices(PieceColor::Light, false), [Piece::LN, Piece::LB, Piece::LR, Piece::LQ]);
+        assert_eq!(promotion_choices(PieceColor::Dark, false), [Piece::DN, Piece::DB, Piece::DR, Piece::DQ]);
+        assert_eq!(promotion_choices(PieceColor::Light, true), [Piece::LQ, Piece::LR, Piece::LB, Piece::LN]);
+        assert_eq!(promotion_choices(PieceColor::Dark, true), [Piece::DQ, Piece::DR, Piece::DB, Piece::DN]);
+    }
+
+    #[test]
+    fn summarize_position_with_eval() {
+        let attack_info = attack::shared_attack_info();
+        let zobrist_info = zobrist::shared_zobrist_info();
+        let mut game = Game::new("Alpha", "Beta", attack_info, zobrist_info);
+        assert_eq!(
+            position_summary(&game),
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 | eval - | Alpha vs Beta"
+        );
+        for (uci, score) in [("e2e4", "cp 30"), ("e7e5", "cp 45")] {
+            let mv = moves::from_uci(uci, game.board_after_last_move().unwrap(), attack_info).unwrap();
+            assert!(game.make_move(mv, attack_info, zobrist_info));
+            game.set_last_score(Some(score.to_string()));
+        }
+        // The last score is black's, so it's flipped to white's point of view
+        assert_eq!(
+            position_summary(&game),
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2 | eval -0.45 | Alpha vs Beta"
+        );
+    }
+
+    #[test]
+    fn format_scores_from_whites_point_of_view() {
+        assert_eq!(format_score("cp 25", true), ("+0.25".to_string(), None));
+        assert_eq!(format_score("cp 25", false), ("-0.25".to_string(), None));
+        assert_eq!(format_score("cp 0", false), ("+0.00".to_string(), None));
+        assert_eq!(format_score("mate 3", true), ("#3".to_string(), Some("White")));
+        assert_eq!(format_score("mate 3", false), ("#-3".to_string(), Some("Black")));
+        assert_eq!(format_score("mate -2", false), ("#2".to_string(), Some("White")));
+        assert_eq!(format_score("wdl 1 2 3", true), ("wdl 1 2 3".to_string(), None));
+    }
+
+    #[test]
+    fn key_bindings_are_listed_once() {
+        let keys: Vec<&str> = KEY_BINDINGS.iter().flat_map(|(key, _)| key.split(" / ")).collect();
+        for (i, key) in keys.iter().enumerate() {
+            assert!(!keys[i + 1..].contains(key), "'{}' is listed more than once", key);
+        }
+    }
+
+    #[test]
+    fn describe_searches() {
+        let info = SearchInfo { depth: Some(12), nodes: Some(5031), time_ms: Some(10), ..SearchInfo::default() };
+        assert_eq!(describe_search(&info), "depth 12, 5031 nodes, 10 ms");
+        let info = SearchInfo { depth: Some(3), ..SearchInfo::default() };
+        assert_eq!(describe_search(&info), "depth 3");
+        assert_eq!(describe_search(&SearchInfo::default()), "-");
+    }
+
+    #[test]
+    fn cancel_a_half_entered_move() {
+        let mut gui = GUI::new();
+        assert!(!gui.cancel_move_input());
+        gui.selected = Some(Sq::E7);
+        gui.target = Some(Sq::E8);
+        gui.is_promotion = true;
+        assert!(gui.cancel_move_input());
+        assert!(gui.selected.is_none() && gui.target.is_none() && gui.promoted_piece.is_none());
+        assert!(!gui.is_promotion);
+    }
+
+    #[test]
+    fn clicked_square_follows_the_flipped_board() {
+        let sec = Rectangle::new(100.0, 50.0, 800.0, 800.0);
+        // The top left square is a8, or h1 when the board is flipped
+        let top_left = Vector2::new(120.0, 60.0);
+        assert_eq!(sq_at(&sec, top_left, false).map(|sq| sq as usize), Some(Sq::A8 as usize));
+        assert_eq!(sq_at(&sec, top_left, true).map(|sq| sq as usize), Some(Sq::H1 as usize));
+        let e2 = Vector2::new(100.0 + 4.5 * 100.0, 50.0 + 6.5 * 100.0);
+        assert_eq!(sq_at(&sec, e2, false).map(|sq| sq as usize), Some(Sq::E2 as usize));
+        assert_eq!(sq_at(&sec, e2, true).map(|sq| sq as usize), Some(Sq::D7 as usize));
+        assert!(sq_at(&sec, Vector2::new(50.0, 60.0), false).is_none());
+    }
+
+    #[test]
+    fn take_the_entered_move() {
+        let mut gui = GUI::new();
+        gui.selected = Some(Sq::G1);
+        assert_eq!(gui.take_entered_move(), None);
+        gui.target = Some(Sq::F3);
+        assert_eq!(gui.take_entered_move().as_deref(), Some("g1f3"));
+        assert!(gui.selected.is_none() && gui.target.is_none());
+
+        // A promotion is only complete once the piece is picked
+        gui.selected = Some(Sq::E7);
+        gui.target = Some(Sq::E8);
+        gui.is_promotion = true;
+        assert_eq!(gui.take_entered_move(), None);
+        gui.is_promotion = false;
+        gui.promoted_piece = Some(Piece::DN);
+        assert_eq!(gui.take_entered_move().as_deref(), Some("e7e8n"));
+        assert!(gui.promoted_piece.is_none());
+    }
+
+    #[test]
+    fn orientation_modes() {
+        for (orientation, _) in Orientation::NAMES {
+            assert_eq!(Orientation::from_name(orientation.name()), Some(orientation));
+        }
+        assert_eq!(Orientation::from_name("black"), None);
+        assert_eq!(Orientation::Fixed.next().next().next(), Orientation::Fixed);
+
+        let mut gui = GUI::new();
+        gui.flip = true;
+        gui.side_to_move = PieceColor::Light;
+        assert!(gui.flipped());
+        gui.orientation = Orientation::AlwaysWhite;
+        assert!(!gui.flipped());
+        gui.orientation = Orientation::FollowSideToMove;
+        assert!(!gui.flipped());
+        gui.side_to_move = PieceColor::Dark;
+        assert!(gui.flipped());
+
+        // Playing a side keeps the board turned towards it
+        gui.face_side(PieceColor::Light);
+        assert_eq!(gui.orientation, Orientation::Fixed);
+        assert!(!gui.flipped());
+    }
+
+    #[test]
+    fn fast_moves_dont_queue_up() {
+        let attack_info = attack::shared_attack_info();
+        let zobrist_info = zobrist::shared_zobrist_info();
+        let board = Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", zobrist_info);
+        let mv = |uci| moves::from_uci(uci, &board, attack_info).unwrap();
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        let mut anim = BoardAnimation::new(0);
+        anim.push(mv("e2e4"), 1, start);
+        assert_eq!((anim.board(), anim.latest()), (0, 1));
+        assert_eq!(anim.update(at(100)), Some(0.5));
+        // Only the last of the moves played during an animation is shown after it
+        anim.push(mv("d2d4"), 2, at(120));
+        anim.push(mv("c2c4"), 3, at(150));
+        assert_eq!(anim.latest(), 3);
+        assert_eq!(anim.update(at(200)), Some(0.0));
+        assert_eq!((anim.board(), anim.mv()), (2, Some(mv("c2c4"))));
+        assert_eq!(anim.update(at(400)), None);
+        assert_eq!((anim.board(), anim.mv()), (3, None));
+
+        // Going through the moves drops the move that was waiting
+        anim.push(mv("e2e4"), 4, at(400));
+        anim.push(mv("d2d4"), 5, at(450));
+        anim.start(None, 1, None, at(500));
+        assert_eq!((anim.update(at(500)), anim.latest()), (None, 1));
+
+        // A new game is followed once nothing is moving
+        anim.follow(0);
+        assert_eq!(anim.board(), 0);
+    }
+
+    #[test]
+    fn jumps_skip_the_slide() {
+        let attack_info = attack::shared_attack_info();
+        let zobrist_info = zobrist::shared_zobrist_info();
+        let board = Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", zobrist_info);
+        let mv = moves::from_uci("e2e4", &board, attack_info).unwrap();
+        let now = Instant::now();
+
+        // Going from the 40th move back to the first one
+        let mut anim = BoardAnimation::new(40);
+        anim.go_to(Some(mv), 0, Some(1), now);
+        assert_eq!((anim.board(), anim.mv(), anim.update(now)), (1, None, None));
+        // Stepping to the next move slides it
+        anim.go_to(Some(mv), 1, Some(2), now);
+        assert_eq!((anim.board(), anim.mv(), anim.latest()), (1, Some(mv), 2));
+        // So does stepping back
+        anim.go_to(Some(mv), 0, Some(1), now + Duration::from_secs(1));
+        assert_eq!((anim.board(), anim.mv()), (0, Some(mv)));
+    }
+}
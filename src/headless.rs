@@ -0,0 +1,85 @@
+use chess::attack::AttackInfo;
+use chess::zobrist::ZobristInfo;
+
+use crate::comm::{self, EngineOptions};
+use crate::config::{Config, CONFIG_FILE};
+use crate::eco::{EcoTable, ECO_FILE};
+use crate::error::GuiError;
+use crate::game_manager::{self, GameManager, MatchSettings};
+use crate::match_state::{MatchState, MATCH_STATE_FILE};
+use crate::paths::Paths;
+
+use std::time::Duration;
+
+fn print_move_times(manager: &GameManager) {
+    for engine_index in 0..2 {
+        let times = manager.move_times(engine_index);
+        if times.count == 0 { continue; }
+        println!(
+            "{}: {} moves, average {:.3}s (min {:.3}s, max {:.3}s), total {:.1}s",
+            manager.engine_name(engine_index), times.count, times.average().as_secs_f32(),
+            times.min.as_secs_f32(), times.max.as_secs_f32(), times.total.as_secs_f32()
+        );
+    }
+}
+
+// Plays a match between the engines without opening a window. The games are written to the match
+// PGN and the score is printed after every game.
+pub fn headless_main(
+    engine_a_path: String, engine_b_path: Option<String>, engine_options: [EngineOptions; 2],
+    settings: MatchSettings, paths: &Paths
+) -> Result<(), GuiError> {
+    let attack_info = AttackInfo::new();
+    let zobrist_info = ZobristInfo::new();
+
+    let fens = game_manager::load_positions(paths, &attack_info, &zobrist_info)?;
+    let (engine_a, engine_b) = comm::start_engines(&engine_a_path, engine_b_path.as_deref(), &engine_options)
+        .map_err(GuiError::EngineInit)?;
+    let engine_paths = [engine_a_path.clone(), engine_b_path.unwrap_or(engine_a_path)];
+    let mut manager = GameManager::new(engine_a, engine_b, &attack_info, &zobrist_info);
+    let config = Config::load(CONFIG_FILE);
+    manager.set_movetime(Duration::from_millis(config.movetime_ms));
+    manager.set_watchdog_factor(config.watchdog_factor);
+    manager.apply_settings(&settings);
+    match EcoTable::load(&paths.optional_asset(ECO_FILE), &attack_info, &zobrist_info) {
+        Ok(openings) => manager.set_openings(openings),
+        Err(e) => eprintln!("[WARN] {}", e),
+    }
+    if settings.resume {
+        let state = MatchState::load(MATCH_STATE_FILE)?;
+        state.check_engines(&engine_paths)?;
+        manager.resume(&state, &fens, &attack_info, &zobrist_info)?;
+        manager.print_standings();
+    } else {
+        // The first round starts from the first position, with the first engine as white
+        manager.reset_match(&fens, &attack_info, &zobrist_info);
+    }
+    manager.toggle_playing();
+
+    loop {
+        manager.step(&attack_info, &zobrist_info);
+        manager.check_state();
+
+        if !manager.current_game().is_ongoing() {
+            let game = manager.current_game();
+            println!("{} vs {}: {}", game.white_name(), game.black_name(), game.state().description());
+            if let Some(summary) = manager.round_summary() {
+                println!("{}", summary);
+            }
+            manager.print_standings();
+            print_move_times(&manager);
+            if manager.is_match_over() {
+                println!("Match over");
+                break;
+            }
+            if !manager.start_new_game(&fens, &attack_info, &zobrist_info) {
+                break;
+            }
+            manager.toggle_playing();
+        }
+    }
+    manager.save_finished_game(&attack_info, &zobrist_info);
+    manager.match_state(engine_paths).save(MATCH_STATE_FILE)?;
+
+    Ok(())
+}
@@ -1,27 +1,79 @@
+mod args;
+mod bench;
+mod clock;
 mod comm;
+mod config;
+mod eco;
+mod error;
 mod game;
 mod game_manager;
 mod gui;
+mod headless;
+mod match_state;
+mod paths;
 mod pgn;
+mod render;
 mod utils;
 
+use game_manager::{MatchSettings, StopCondition};
+use paths::Paths;
+
 use std::env;
 
 fn main() {
     let mut args = env::args();
     let program = args.next().expect("Expected program name");
-
-    if args.len() < 1 {
-        // the first engine is a requirement, the second one is optional
-        eprintln!("Usage: '{}' <engine-1> [engine-2]", program);
-        std::process::exit(1);
+    let mut args = args.peekable();
+    // Measures or checks the move generation, which doesn't need any engines
+    if args.peek().map(String::as_str) == Some("bench") {
+        bench::bench_main();
+        return;
+    }
+    if args.peek().map(String::as_str) == Some("divide") {
+        args.next();
+        if let Err(e) = bench::divide_main(args) {
+            eprintln!("[ERROR] {e}");
+            eprintln!("{}", args::usage(&program));
+            std::process::exit(1);
+        }
+        return;
     }
 
-    let engine_a = args.next();
-    let engine_b = args.next();
+    let args = match args::parse(args) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("[ERROR] {e}");
+            eprintln!("{}", args::usage(&program));
+            std::process::exit(1);
+        }
+    };
 
-    if let Err(e) = gui::gui_main(engine_a.unwrap(), engine_b) {
+    // The seed is printed so that an interesting run can be repeated with '--seed'
+    let settings = MatchSettings {
+        stop_condition: StopCondition {
+            max_games: args.games, max_rounds: args.rounds, win_margin: args.margin, sprt: args.sprt
+        },
+        adjudication: args.adjudication.unwrap_or_default(),
+        time_control: args.time_control.unwrap_or_default(),
+        seed: args.seed.unwrap_or_else(rand::random),
+        depth: args.depth,
+        resume: args.resume,
+        human: args.human,
+        ponder: args.ponder,
+    };
+    println!("Seed: {}", settings.seed);
+    let paths = Paths::new(args.assets, args.fens, args.book);
+    let result = if args.headless {
+        headless::headless_main(args.engine_a, args.engine_b, args.engine_options, settings, &paths)
+    } else {
+        gui::gui_main(args.engine_a, args.engine_b, args.engine_options, settings, &paths)
+    };
+    if let Err(e) = result {
         eprintln!("[ERROR] Something went wrong!");
         eprintln!("[ERROR] {e}");
+        if let Some(hint) = e.hint() {
+            eprintln!("{hint}");
+        }
+        std::process::exit(1);
     }
 }
@@ -0,0 +1,224 @@
+use std::fmt;
+
+use crate::error::GuiError;
+use crate::game::GameState;
+use crate::game_manager::Standings;
+
+// What is needed to continue a match after the program was closed. The finished games are in
+// the match PGN, so only the score, where the match was at and the moves of an unfinished game
+// are kept. It's stored as 'key=value' lines, like the config.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MatchState {
+    // Paths of the engines, indexed like the engines of the match
+    pub engines: [String; 2],
+    pub standings: Standings,
+    // Number of games started before the current one, which decides the position and sides of
+    // the next game
+    pub game_count: usize,
+    // Number of games of the match in the match PGN, not counting the current game
+    pub saved_games: usize,
+    // Whether the current game had ended with any moves, in which case it was added to the match
+    // PGN on exit
+    pub current_saved: bool,
+    pub current_over: bool,
+    pub white_engine: usize,
+    // Points of the first engine in the first game of the current round, if it has ended
+    pub round_points: Option<f32>,
+    // Position the current game was played from
+    pub start_fen: String,
+    // Moves of the current game in UCI notation if it's unfinished, since it isn't in the match
+    // PGN. It's continued from them when the match is resumed.
+    pub moves: Vec<String>,
+}
+
+pub const MATCH_STATE_FILE: &str = "match.state";
+
+impl MatchState {
+    pub fn load(path: &str) -> Result<Self, GuiError> {
+        let content = std::fs::read_to_string(path).map_err(GuiError::io(path))?;
+        Self::parse(&content).map_err(|e| GuiError::Resume(format!("Invalid saved match in '{}': {}", path, e)))
+    }
+
+    // Unlike the config, every value has to be present, since a match can't be continued
+    // correctly with some of its state missing
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let mut engine_a = None;
+        let mut engine_b = None;
+        let mut wins = None;
+        let mut draws = None;
+        let mut pair_wins = None;
+        let mut pair_draws = None;
+        let mut game_count = None;
+        let mut saved_games = None;
+        let mut current_saved = None;
+        let mut current_over = None;
+        let mut white_engine = None;
+        let mut round_points = None;
+        let mut start_fen = None;
+        // Missing from matches saved before the moves of an unfinished game were kept
+        let mut moves = vec![];
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') { continue; }
+            let (key, value) = line.split_once('=').ok_or(format!("Invalid line: '{}'", line))?;
+            let (key, value) = (key.trim(), value.trim());
+            let is_valid = match key {
+                "engine_a" => { engine_a = Some(value.to_string()); true },
+                "engine_b" => { engine_b = Some(value.to_string()); true },
+                "wins" => { wins = parse_counts::<2>(value); wins.is_some() },
+                "draws" => { draws = parse_draws(value); draws.is_some() },
+                "pair_wins" => { pair_wins = parse_counts::<2>(value); pair_wins.is_some() },
+                "pair_draws" => { pair_draws = value.parse().ok(); pair_draws.is_some() },
+                "game_count" => { game_count = value.parse().ok(); game_count.is_some() },
+                "saved_games" => { saved_games = value.parse().ok(); saved_games.is_some() },
+                "current_saved" => { current_saved = value.parse().ok(); current_saved.is_some() },
+                "current_over" => { current_over = value.parse().ok(); current_over.is_some() },
+                "white_engine" => match value {
+                    "0" | "1" => { white_engine = value.parse().ok(); true },
+                    _ => false,
+                },
+                // Empty until the first game of the round has ended
+                "round_points" => match value {
+                    "" => { round_points = Some(None); true },
+                    "0" | "0.5" | "1" => { round_points = Some(value.parse().ok()); true },
+                    _ => false,
+                },
+                "start_fen" => { start_fen = Some(value.to_string()); true },
+                "moves" => { moves = value.split_whitespace().map(String::from).collect(); true },
+                _ => true,
+            };
+            if !is_valid {
+                return Err(format!("Invalid value for '{}': '{}'", key, value));
+            }
+        }
+        let missing = |key: &str| format!("'{}' is missing", key);
+        Ok(Self {
+            engines: [engine_a.ok_or(missing("engine_a"))?, engine_b.ok_or(missing("engine_b"))?],
+            standings: Standings {
+                wins: wins.ok_or(missing("wins"))?,
+                draws: draws.ok_or(missing("draws"))?,
+                pair_wins: pair_wins.ok_or(missing("pair_wins"))?,
+                pair_draws: pair_draws.ok_or(missing("pair_draws"))?,
+            },
+            game_count: game_count.ok_or(missing("game_count"))?,
+            saved_games: saved_games.ok_or(missing("saved_games"))?,
+            current_saved: current_saved.ok_or(missing("current_saved"))?,
+            current_over: current_over.ok_or(missing("current_over"))?,
+            white_engine: white_engine.ok_or(missing("white_engine"))?,
+            round_points: round_points.ok_or(missing("round_points"))?,
+            start_fen: start_fen.ok_or(missing("start_fen"))?,
+            moves,
+        })
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), GuiError> {
+        std::fs::write(path, self.to_string()).map_err(GuiError::io(path))
+    }
+
+    // The engines have to be the same ones, or the score would belong to a different match
+    pub fn check_engines(&self, engines: &[String; 2]) -> Result<(), GuiError> {
+        if self.engines != *engines {
+            return Err(GuiError::Resume(format!(
+                "The saved match is between '{}' and '{}', but '{}' and '{}' were given",
+                self.engines[0], self.engines[1], engines[0], engines[1]
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for MatchState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let join = |counts: &[u32]| counts.iter().map(u32::to_string).collect::<Vec<_>>().join(",");
+        writeln!(f, "engine_a={}", self.engines[0])?;
+        writeln!(f, "engine_b={}", self.engines[1])?;
+        writeln!(f, "wins={}", join(&self.standings.wins))?;
+        writeln!(f, "draws={}", join(&self.standings.draws))?;
+        writeln!(f, "pair_wins={}", join(&self.standings.pair_wins))?;
+        writeln!(f, "pair_draws={}", self.standings.pair_draws)?;
+        writeln!(f, "game_count={}", self.game_count)?;
+        writeln!(f, "saved_games={}", self.saved_games)?;
+        writeln!(f, "current_saved={}", self.current_saved)?;
+        writeln!(f, "current_over={}", self.current_over)?;
+        writeln!(f, "white_engine={}", self.white_engine)?;
+        let round_points = self.round_points.map_or(String::new(), |points| points.to_string());
+        writeln!(f, "round_points={}", round_points)?;
+        writeln!(f, "start_fen={}", self.start_fen)?;
+        writeln!(f, "moves={}", self.moves.join(" "))?;
+        Ok(())
+    }
+}
+
+// Reads exactly N comma-separated counts, e.g. "3,1"
+fn parse_counts<const N: usize>(value: &str) -> Option<[u32; N]> {
+    let counts: Vec<u32> = value.split(',').map(|c| c.trim().parse().ok()).collect::<Option<_>>()?;
+    counts.try_into().ok()
+}
+
+// Matches saved before games could be adjudicated have no count of the draws by adjudication,
+// which come last
+fn parse_draws(value: &str) -> Option<[u32; GameState::DRAWS.len()]> {
+    parse_counts(value).or_else(|| {
+        let counts = parse_counts::<{ GameState::DRAWS.len() - 1 }>(value)?;
+        let mut draws = [0; GameState::DRAWS.len()];
+        draws[..counts.len()].copy_from_slice(&counts);
+        Some(draws)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game_manager::Standings;
+    use crate::match_state::MatchState;
+
+    fn match_state() -> MatchState {
+        MatchState {
+            engines: ["engines/a".to_string(), "engines/b".to_string()],
+            standings: Standings { wins: [3, 1], draws: [0, 2, 1, 0, 1], pair_wins: [2, 0], pair_draws: 2 },
+            game_count: 8,
+            saved_games: 7,
+            current_saved: false,
+            current_over: false,
+            white_engine: 1,
+            round_points: Some(0.5),
+            start_fen: "4k3/8/8/8/8/8/8/4K3 w - - 0 1".to_string(),
+            moves: vec!["e1d2".to_string(), "e8e7".to_string()],
+        }
+    }
+
+    #[test]
+    fn match_state_round_trip() {
+        let state = match_state();
+        assert_eq!(MatchState::parse(&state.to_string()), Ok(state));
+        let state = MatchState { round_points: None, moves: vec![], ..match_state() };
+        assert_eq!(MatchState::parse(&state.to_string()), Ok(state));
+        // Saved before the moves of an unfinished game were kept
+        let content = match_state().to_string().replace("moves=e1d2 e8e7\n", "");
+        assert_eq!(MatchState::parse(&content).unwrap().moves, Vec::<String>::new());
+
+        // Saved before the draws by adjudication were counted
+        let content = match_state().to_string().replace("draws=0,2,1,0,1", "draws=0,2,1,0");
+        assert_eq!(MatchState::parse(&content).unwrap().standings.draws, [0, 2, 1, 0, 0]);
+    }
+
+    #[test]
+    fn reject_incomplete_match_state() {
+        let content = match_state().to_string();
+        for key in ["engine_b=", "draws=", "pair_wins=", "round_points=", "start_fen="] {
+            let without_key: String = content.lines().filter(|line| !line.starts_with(key))
+                .map(|line| format!("{}\n", line)).collect();
+            assert!(MatchState::parse(&without_key).is_err(), "{}", key);
+        }
+        for (key, value) in [("wins", "3"), ("draws", "0,2,1,0,x"), ("white_engine", "2"), ("current_over", "yes"), ("round_points", "2")] {
+            let content = format!("{}{}={}\n", content, key, value);
+            assert!(MatchState::parse(&content).is_err(), "{}={}", key, value);
+        }
+    }
+
+    #[test]
+    fn saved_engines_have_to_match() {
+        let state = match_state();
+        assert!(state.check_engines(&["engines/a".to_string(), "engines/b".to_string()]).is_ok());
+        assert!(state.check_engines(&["engines/b".to_string(), "engines/a".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,78 @@
+use crate::error::GuiError;
+
+use std::path::{Path, PathBuf};
+
+const ASSETS_DIR: &str = "assets";
+const FENS_FILE: &str = "fens.txt";
+
+// Where the files that come with the program are loaded from. Unless they are given on the
+// command line, they are looked for next to the executable first, so that an installed binary
+// can be run from anywhere, and then in the current directory (e.g. with 'cargo run').
+pub struct Paths {
+    assets: PathBuf,
+    fens: PathBuf,
+    // PGN of openings that the games are played from instead of the fens, if one was given
+    book: Option<PathBuf>,
+}
+
+impl Paths {
+    pub fn new(assets: Option<String>, fens: Option<String>, book: Option<String>) -> Self {
+        Self {
+            assets: assets.map_or_else(|| default_path(ASSETS_DIR), PathBuf::from),
+            fens: fens.map_or_else(|| default_path(FENS_FILE), PathBuf::from),
+            book: book.map(PathBuf::from),
+        }
+    }
+
+    // Path of a file in the assets directory, e.g. "fonts/Inter-Bold.ttf". A missing file is
+    // reported here, with its full path, rather than by whatever fails to load it later on.
+    pub fn asset(&self, name: &str) -> Result<String, GuiError> {
+        let path = self.assets.join(name);
+        if !path.is_file() {
+            let path = path.to_string_lossy().into_owned();
+            return Err(GuiError::AssetLoad { path, reason: "the file is missing".to_string() });
+        }
+        Ok(path.to_string_lossy().into_owned())
+    }
+
+    // Same as 'asset', for files that the program can do without
+    pub fn optional_asset(&self, name: &str) -> String {
+        self.assets.join(name).to_string_lossy().into_owned()
+    }
+
+    pub fn fens(&self) -> String {
+        self.fens.to_string_lossy().into_owned()
+    }
+
+    pub fn book(&self) -> Option<String> {
+        self.book.as_ref().map(|book| book.to_string_lossy().into_owned())
+    }
+}
+
+fn default_path(name: &str) -> PathBuf {
+    let next_to_exe = std::env::current_exe().ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.join(name)))
+        .filter(|path| path.exists());
+    next_to_exe.unwrap_or_else(|| Path::new(name).to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::paths::Paths;
+
+    #[test]
+    fn resolve_assets() {
+        let paths = Paths::new(Some("assets".to_string()), Some("other-fens.txt".to_string()), Some("book.pgn".to_string()));
+        assert_eq!(paths.asset("fonts/Inter-Bold.ttf").unwrap(), "assets/fonts/Inter-Bold.ttf");
+        assert!(paths.asset("fonts/missing.ttf").unwrap_err().to_string().contains("assets/fonts/missing.ttf"));
+        assert_eq!(paths.optional_asset("missing.tsv"), "assets/missing.tsv");
+        assert_eq!(paths.fens(), "other-fens.txt");
+        assert_eq!(paths.book().as_deref(), Some("book.pgn"));
+
+        // The test executable isn't next to the assets, so they're found in the current directory
+        let paths = Paths::new(None, None, None);
+        assert!(paths.asset("fonts/Inter-Bold.ttf").is_ok());
+        assert_eq!(paths.fens(), "fens.txt");
+        assert_eq!(paths.book(), None);
+    }
+}
@@ -1,48 +1,38 @@
 use chess::attack::{self, AttackInfo};
-use chess::bb::BBUtil;
 use chess::board::Board;
-use chess::moves::{Move, MoveUtil};
+use chess::moves::{self, Move, MoveFlag, MoveUtil};
 use chess::consts::{Piece, PieceColor, Sq};
 use chess::fen;
-use chess::COL;
+use chess::zobrist::ZobristInfo;
+use chess::{COL, ROW};
 
-use crate::game::{Game, GameState};
+use crate::game::{self, Game, GameState};
 
 use std::path::Path;
 use std::io::{self, BufWriter, Write};
 
-fn should_disambiguate(mv: Move, attack_info: &AttackInfo, board: &Board) -> (bool, bool) {
+// Which parts of the source square, (rank, file), tell 'mv' apart from the moves of the other
+// pieces of the same kind to the same square. Only legal moves count, so a pinned piece never
+// makes another one ambiguous.
+fn should_disambiguate(
+    mv: Move, attack_info: &AttackInfo, zobrist_info: &ZobristInfo, board: &Board
+) -> (bool, bool) {
     let piece = mv.piece();
     if (piece == Piece::LP || piece == Piece::DP) || (piece == Piece::LK || piece == Piece::DK) {
         return (false, false);
     }
-    let source = mv.source();
-    let target = mv.target();
-    let bb = match piece {
-        Piece::LN | Piece::DN => attack_info.knight[target as usize],
-        Piece::LB | Piece::DB => {
-            attack_info.get_bishop_attack(target, board.pos.units[PieceColor::Both as usize])
-        },
-        Piece::LR | Piece::DR => {
-            attack_info.get_rook_attack(target, board.pos.units[PieceColor::Both as usize])
-        },
-        Piece::LQ | Piece::DQ => {
-            attack_info.get_queen_attack(target, board.pos.units[PieceColor::Both as usize])
-        },
-        _ => unreachable!(),
-    };
-    let piece_bb = board.pos.piece[piece as usize];
-    let mut result = bb & piece_bb;
-    if result.count_ones() <= 1 {
+    let source = mv.source() as usize;
+    let others: Vec<usize> = game::legal_moves(board, attack_info, zobrist_info).iter()
+        .filter(|other| other.piece() == piece && other.target() == mv.target())
+        .map(|other| other.source() as usize)
+        .filter(|&other_source| other_source != source)
+        .collect();
+    if others.is_empty() {
         (false, false)
-    } else if result.count_ones() == 2 {
-        let a = result.pop_lsb();
-        let b = result.pop_lsb();
-        if COL!(a) == COL!(b) {
-            (true, false)
-        } else {
-            (false, true)
-        }
+    } else if others.iter().all(|&other| COL!(other) != COL!(source)) {
+        (false, true)
+    } else if others.iter().all(|&other| ROW!(other) != ROW!(source)) {
+        (true, false)
     } else {
         (true, true)
     }
@@ -56,11 +46,17 @@ fn coord_move_to_san(
     let target = mv.target();
     let piece = mv.piece();
     if mv.is_castling() {
-        if COL!(target as usize) == 6 {
-            return "O-O".to_string();
-        } else if COL!(target as usize) == 2 {
-            return "O-O-O".to_string();
-        }
+        // By convention, the king always lands on the g-file when castling with the h-side rook
+        // and on the c-file when castling with the a-side rook, no matter which file it started
+        // on (Chess960). If the move is encoded as the king capturing its own rook instead, the
+        // side is given by the direction the king moves in.
+        let (source_col, target_col) = (COL!(source as usize), COL!(target as usize));
+        let is_kingside = match target_col {
+            6 => true,
+            2 => false,
+            _ => target_col > source_col,
+        };
+        return if is_kingside { "O-O" } else { "O-O-O" }.to_string();
     }
     let mut output = String::new();
     // Piece
@@ -106,24 +102,81 @@ fn coord_move_to_san(
     output
 }
 
+// SAN of a legal move, including whether it gives check or mate
+pub fn move_to_san(mv: Move, board: &Board, attack_info: &AttackInfo, zobrist_info: &ZobristInfo) -> String {
+    let disambiguate = should_disambiguate(mv, attack_info, zobrist_info, board);
+    let mut next_board = board.clone();
+    moves::make(&mut next_board, attack_info, zobrist_info, mv, MoveFlag::AllMoves);
+    let check = next_board.is_in_check(attack_info, next_board.state.xside);
+    let checkmate = check && game::legal_moves(&next_board, attack_info, zobrist_info).is_empty();
+    coord_move_to_san(mv, attack_info, check, disambiguate, checkmate)
+}
+
+// An engine's principal variation, given in UCI notation, as SAN moves played from 'board'. The
+// line stops at the first move that isn't legal, e.g. when the engine got the position wrong.
+pub fn pv_to_san(pv: &[String], board: &Board, attack_info: &AttackInfo, zobrist_info: &ZobristInfo) -> String {
+    let mut board = board.clone();
+    let mut sans = vec![];
+    for uci in pv {
+        let mv = if let Some(mv) = moves::from_uci(uci, &board, attack_info) { mv } else { break; };
+        let mut next_board = board.clone();
+        if !moves::make(&mut next_board, attack_info, zobrist_info, mv, MoveFlag::AllMoves) {
+            break;
+        }
+        sans.push(move_to_san(mv, &board, attack_info, zobrist_info));
+        board = next_board;
+    }
+    sans.join(" ")
+}
+
+// Engine names can contain any character, but quotes and backslashes have to be escaped inside
+// of a tag value
+fn escape_tag(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
 pub fn save(
-    filename: &str, game: &Game, attack_info: &AttackInfo
+    filename: &str, game: &Game, attack_info: &AttackInfo, zobrist_info: &ZobristInfo
 ) -> Result<bool, io::Error> {
     let f = std::fs::File::create(Path::new(filename))?;
     let mut f = BufWriter::new(f);
+    write_game(&mut f, game, attack_info, zobrist_info)?;
+    Ok(true)
+}
+
+// Adds the game to the end of a PGN file (e.g. the one of a match), creating it if needed
+pub fn append(
+    filename: &str, game: &Game, attack_info: &AttackInfo, zobrist_info: &ZobristInfo
+) -> Result<bool, io::Error> {
+    let f = std::fs::OpenOptions::new().create(true).append(true).open(Path::new(filename))?;
+    let is_empty = f.metadata()?.len() == 0;
+    let mut f = BufWriter::new(f);
+    // Games in the same file are separated by an empty line
+    if !is_empty {
+        writeln!(f)?;
+    }
+    write_game(&mut f, game, attack_info, zobrist_info)?;
+    Ok(true)
+}
+
+fn write_game<W: Write>(
+    f: &mut W, game: &Game, attack_info: &AttackInfo, zobrist_info: &ZobristInfo
+) -> Result<(), io::Error> {
     writeln!(f, "[Event \"?\"]")?;
     writeln!(f, "[Site \"?\"]")?;
     writeln!(f, "[Date \"????.??.??\"]")?;
     writeln!(f, "[Round \"?\"]")?;
-    writeln!(f, "[White \"{}\"]", game.white_name())?;
-    writeln!(f, "[Black \"{}\"]", game.black_name())?;
-    let result_str = match game.state() {
-        GameState::Ongoing => "*",
-        GameState::LightWinByCheckmate => "1-0",
-        GameState::DarkWinByCheckmate => "0-1",
-        _ => "1/2-1/2"
-    };
+    writeln!(f, "[White \"{}\"]", escape_tag(game.white_name()))?;
+    writeln!(f, "[Black \"{}\"]", escape_tag(game.black_name()))?;
+    let result_str = result_token(game);
     writeln!(f, "[Result \"{}\"]", result_str)?;
+    if let Some(time_control) = game.time_control() {
+        writeln!(f, "[TimeControl \"{}\"]", time_control.to_pgn())?;
+    }
+    if let Some(opening) = game.opening() {
+        writeln!(f, "[ECO \"{}\"]", escape_tag(&opening.eco))?;
+        writeln!(f, "[Opening \"{}\"]", escape_tag(&opening.name))?;
+    }
     let start_fen = game.start_fen();
     if start_fen != fen::FEN_POSITIONS[1] {
         writeln!(f, "[FEN \"{}\"]", start_fen)?;
@@ -131,31 +184,256 @@ pub fn save(
     }
     writeln!(f)?;
 
+    // Numbering continues from the start position, which can have black to move
+    let start_board = game.board_before_move(0).unwrap();
+    let first_move_number = start_board.full_moves().max(1) as usize;
+    let black_first = !start_board.is_white_to_move();
     for i in 0..game.move_count() {
-        if i % 2 == 0 {
-            write!(f, "{}. ", (i / 2) + 1)?;
+        let ply = i + black_first as usize;
+        if ply % 2 == 0 {
+            write!(f, "{}. ", first_move_number + ply / 2)?;
+        } else if i == 0 {
+            write!(f, "{}... ", first_move_number)?;
         }
         if let Some(mv) = game.move_at(i) {
-            // write!(f, "{}", mv.to_str().trim())?;
-            let disambiguate = should_disambiguate(*mv, attack_info, game.board_before_move(i).unwrap());
-            // let ind = if i + 1 > board_info.len() - 1 { board_info.len() - 1 } else { i + 1 };
-            let next_board = game.board_after_move(i).unwrap();
-            let check = next_board.is_in_check(&attack_info, next_board.state.xside);
-            write!(f, "{}", coord_move_to_san(*mv, attack_info, check, disambiguate, false))?;
+            let board = game.board_before_move(i).unwrap();
+            let disambiguate = should_disambiguate(*mv, attack_info, zobrist_info, board);
+            let (check, checkmate) = (game.gave_check(i), game.gave_checkmate(i));
+            write!(f, "{}", coord_move_to_san(*mv, attack_info, check, disambiguate, checkmate))?;
         }
         // Every 5 moves from each side, add a newline
-        if i < game.move_count() - 1 {
-            if i != 0 && i % 10 == 0 {
-                writeln!(f)?;
-            } else {
-                write!(f, " ")?;
-            }
+        if i != 0 && i % 10 == 0 {
+            writeln!(f)?;
+        } else {
+            write!(f, " ")?;
         }
     }
-    writeln!(f, " {}", result_str)?;
+    // The result always ends the movetext, even when there are no moves
+    writeln!(f, "{}", result_str)?;
+    f.flush()
+}
 
-    Ok(true)
+// The result of the game as it is written in the movetext and the 'Result' tag. A game that
+// isn't over (e.g. one that was stopped to be continued later) is '*'.
+fn result_token(game: &Game) -> &str {
+    let state = game.state();
+    if state == GameState::Ongoing {
+        return game.loaded_result().map_or("*", |result| result.as_str());
+    }
+    match state.winner() {
+        Some(PieceColor::Light) => "1-0",
+        Some(PieceColor::Dark) => "0-1",
+        _ => "1/2-1/2",
+    }
 }
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Tag(String, String),
+    Move(String),
+    Result(String),
+    Comment(String),
+    Nag(u32),
+    VariationStart,
+    VariationEnd,
+}
+
+const RESULTS: [&str; 4] = ["1-0", "0-1", "1/2-1/2", "*"];
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let mut chars = text.chars().peekable();
+    let mut at_line_start = true;
+    while let Some(ch) = chars.next() {
+        let was_at_line_start = at_line_start;
+        at_line_start = ch == '\n';
+        match ch {
+            _ if ch.is_whitespace() || ch == '\u{feff}' => {},
+            // Lines starting with '%' are escaped and ignored
+            '%' if was_at_line_start => {
+                while chars.next_if(|&ch| ch != '\n').is_some() {}
+            },
+            '[' => {
+                let mut tag = String::new();
+                let mut in_string = false;
+                loop {
+                    match chars.next() {
+                        Some('\\') if in_string => tag.extend(chars.next()),
+                        Some('"') => { in_string = !in_string; tag.push('"'); },
+                        Some(']') if !in_string => break,
+                        Some(ch) => tag.push(ch),
+                        None => return Err("Unterminated tag".to_string()),
+                    }
+                }
+                let (name, value) = tag.trim().split_once(char::is_whitespace)
+                    .ok_or(format!("Invalid tag: '[{}]'", tag))?;
+                tokens.push(Token::Tag(name.to_string(), value.trim().trim_matches('"').to_string()));
+            },
+            '{' => {
+                let mut comment = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(ch) => comment.push(ch),
+                        None => return Err("Unterminated comment".to_string()),
+                    }
+                }
+                tokens.push(Token::Comment(comment.trim().to_string()));
+            },
+            ';' => {
+                let mut comment = String::new();
+                while let Some(ch) = chars.next_if(|&ch| ch != '\n') {
+                    comment.push(ch);
+                }
+                tokens.push(Token::Comment(comment.trim().to_string()));
+            },
+            '$' => {
+                let mut nag = String::new();
+                while let Some(ch) = chars.next_if(|ch| ch.is_ascii_digit()) {
+                    nag.push(ch);
+                }
+                tokens.push(Token::Nag(nag.parse().map_err(|_| "Invalid NAG".to_string())?));
+            },
+            '(' => tokens.push(Token::VariationStart),
+            ')' => tokens.push(Token::VariationEnd),
+            _ => {
+                let mut word = ch.to_string();
+                while let Some(ch) = chars.next_if(|&ch| !ch.is_whitespace() && !"[]{}();$".contains(ch)) {
+                    word.push(ch);
+                }
+                if RESULTS.contains(&word.as_str()) {
+                    tokens.push(Token::Result(word));
+                    continue;
+                }
+                // Move numbers can be written separately ("12.", "12...") or together with the
+                // move ("12.e4", "12...Nf6")
+                let san = word.trim_start_matches(|ch: char| ch.is_ascii_digit()).trim_start_matches('.');
+                // Some exports mark enpassant captures, which isn't part of the SAN
+                if !san.is_empty() && san != "e.p." {
+                    tokens.push(Token::Move(san.to_string()));
+                }
+            },
+        }
+    }
+    Ok(tokens)
+}
+
+// Annotations ('+', '#', '!', '?') and the '=' of promotions are optional, so they are left out
+// when comparing moves
+fn normalize_san(san: &str) -> String {
+    san.replace("0-0", "O-O").chars().filter(|ch| !"+#!?=".contains(*ch)).collect()
+}
+
+// Finds the legal move in 'board' that is written as 'san'. Moves are matched by their piece,
+// target square and promotion, and by whichever parts of the source square are given, so extra
+// disambiguation and lower case promotions (e.g. "e8=q") are accepted too.
+fn move_from_san(san: &str, board: &Board, attack_info: &AttackInfo, zobrist_info: &ZobristInfo) -> Option<Move> {
+    let san = normalize_san(san);
+    let legal_moves = game::legal_moves(board, attack_info, zobrist_info);
+    if san == "O-O" || san == "O-O-O" {
+        return legal_moves.into_iter().find(|&mv| {
+            mv.is_castling() && coord_move_to_san(mv, attack_info, false, (false, false), false) == san
+        });
+    }
+    let mut chars: Vec<char> = san.chars().collect();
+    // Piece letters are always upper case, since a lower case 'b' is the b-file
+    let piece = match chars.first() {
+        Some(&ch) if "NBRQK".contains(ch) => { chars.remove(0); ch },
+        _ => 'P',
+    };
+    let promoted = match chars.last() {
+        Some(&ch) if piece == 'P' && "NBRQnbrq".contains(ch) => { chars.pop(); Some(ch.to_ascii_uppercase()) },
+        _ => None,
+    };
+    if chars.len() < 2 {
+        return None;
+    }
+    let target: String = chars.split_off(chars.len() - 2).into_iter().collect();
+    // What's left is the capture and the parts of the source square that were given
+    let source_parts: Vec<char> = chars.into_iter().filter(|&ch| ch != 'x').collect();
+    if source_parts.iter().any(|ch| !matches!(ch, 'a'..='h' | '1'..='8')) {
+        return None;
+    }
+    let mut candidates = legal_moves.into_iter().filter(|&mv| {
+        let source = Sq::to_string(mv.source());
+        !mv.is_castling()
+            && Piece::to_char(Some(mv.piece())).to_ascii_uppercase() == piece
+            && Sq::to_string(mv.target()) == target
+            && mv.promoted().map(|p| Piece::to_char(Some(p)).to_ascii_uppercase()) == promoted
+            && source_parts.iter().all(|&ch| source.contains(ch))
+    });
+    // Moves that could be any of several pieces aren't valid SAN
+    let mv = candidates.next()?;
+    if candidates.next().is_some() {
+        return None;
+    }
+    Some(mv)
+}
+
+fn build_game(
+    tags: &[(String, String)], san_moves: &[String], result: Option<&str>,
+    attack_info: &AttackInfo, zobrist_info: &ZobristInfo
+) -> Result<Game, String> {
+    let tag = |name: &str| tags.iter().find(|(n, _)| n == name).map(|(_, value)| value.as_str());
+    let start_fen = tag("FEN").unwrap_or(fen::FEN_POSITIONS[1]);
+    fen::try_parse(start_fen, zobrist_info)?;
+    let mut game = Game::from_fen(tag("White").unwrap_or("?"), tag("Black").unwrap_or("?"), start_fen, attack_info, zobrist_info);
+    for san in san_moves {
+        let mv = game.board_after_last_move()
+            .and_then(|board| move_from_san(san, board, attack_info, zobrist_info))
+            .ok_or(format!("Illegal move '{}' after {} moves", san, game.move_count()))?;
+        game.make_move(mv, attack_info, zobrist_info);
+    }
+    if let Some(result) = result.or(tag("Result")) {
+        if game.is_ongoing() && result != "*" {
+            game.set_loaded_result(result);
+        }
+    }
+    Ok(game)
+}
+
+// Reads the main line of every game in a PGN. Comments, NAGs and variations are skipped.
+pub fn load(text: &str, attack_info: &AttackInfo, zobrist_info: &ZobristInfo) -> Result<Vec<Game>, String> {
+    let mut games = vec![];
+    let mut tags = vec![];
+    let mut san_moves = vec![];
+    // How many variations deep the current token is; only the main line (depth 0) is kept
+    let mut variation_depth = 0;
+    for token in tokenize(text)? {
+        match token {
+            Token::VariationStart => variation_depth += 1,
+            Token::VariationEnd if variation_depth == 0 => {
+                return Err("Unexpected ')' outside of a variation".to_string());
+            },
+            Token::VariationEnd => variation_depth -= 1,
+            _ if variation_depth > 0 => {},
+            Token::Tag(name, value) => {
+                // A game without a result ends where the tags of the next one start
+                if !san_moves.is_empty() {
+                    games.push(build_game(&tags, &san_moves, None, attack_info, zobrist_info)?);
+                    tags.clear();
+                    san_moves.clear();
+                }
+                tags.push((name, value));
+            },
+            Token::Move(san) => san_moves.push(san),
+            Token::Result(result) => {
+                games.push(build_game(&tags, &san_moves, Some(&result), attack_info, zobrist_info)?);
+                tags.clear();
+                san_moves.clear();
+            },
+            Token::Comment(_) | Token::Nag(_) => {},
+        }
+    }
+    if variation_depth > 0 {
+        return Err("Unterminated variation".to_string());
+    }
+    if !tags.is_empty() || !san_moves.is_empty() {
+        games.push(build_game(&tags, &san_moves, None, attack_info, zobrist_info)?);
+    }
+    Ok(games)
+}
+
 /*
 pub fn save(
     filename: &str, white_name: &str, black_name: &str, fen: &str,
@@ -211,14 +489,32 @@ pub fn save(
 
 #[cfg(test)]
 mod tests {
-    use chess::attack::AttackInfo;
+    use chess::attack::{self, AttackInfo};
     use chess::board::Board;
-    use chess::zobrist::ZobristInfo;
+    use chess::zobrist::{self, ZobristInfo};
     use chess::moves::{self, Move, MoveFlag, MoveUtil};
     use chess::consts::Piece;
 
+    use crate::game::{Game, GameState};
     use crate::pgn;
 
+    const ANNOTATED_PGN: &str = r#"[Event "Casual game"]
+[White "Alice \"A\" Smith"]
+[Black "Bob"]
+[Result "0-1"]
+
+% This line is escaped
+1. e4 {[%clk 0:03:00]} e5 $1 2. Nf3 Nc6 ; rest of the line is a comment
+3. Bc4!? 3... Bc5 { Giuoco Piano,
+   spread over two lines } 4. O-O $14 Nf6 5. d3 d6 6. c3 O-O 7. Re1 a6?! 8. Bb3 Ba7
+9. h3 h6 10. Nbd2 Re8 11. Nf1 Be6 12. Bc2 12... d5 0-1
+"#;
+
+    const VARIATIONS_PGN: &str = "1. e4 e5 (1... c5 2. Nf3 (2. c3 d5 (2... Nf6 3. e5) 3. exd5) 2... d6 1-0) \
+        2. Nf3 {main line} (2. f4 $2 exf4) Nc6 (2... d6 3. d4 (3. Bc4)) 3. Bb5 a6 *";
+
+    const MATED_PGN: &str = "1. f3 e5 2. g4 Qh4# 0-1\n\n1. d4 *";
+
     #[test]
     fn move_to_san() {
         let white_moves_arr = [
@@ -262,48 +558,119 @@ mod tests {
             (Move::from_str("e8g8", Piece::DK, false, false, false, true), "O-O"),
         ];
 
-        let attack_info = AttackInfo::new();
-        let zobrist_info = ZobristInfo::new();
+        let attack_info = attack::shared_attack_info();
+        let zobrist_info = zobrist::shared_zobrist_info();
         // Board to test white's moves
-        let board = Board::from_fen("r3k2r/pP1pqpb1/bn2pnp1/2pPN3/1p2P3/2N2Q2/PPPBBPpP/R3K2R w KQkq c6 0 1", &zobrist_info);
+        let board = Board::from_fen("r3k2r/pP1pqpb1/bn2pnp1/2pPN3/1p2P3/2N2Q2/PPPBBPpP/R3K2R w KQkq c6 0 1", zobrist_info);
 
         for (i, (mv, expected)) in white_moves_arr.iter().enumerate() {
-            check_move((*mv, expected), &board, &attack_info, &zobrist_info, false);
+            check_move((*mv, expected), &board, attack_info, zobrist_info, false);
         }
 
         // Board to test black's moves
-        let board = Board::from_fen("r3k2r/pP1pqpb1/bn2pnp1/2pPN3/1p2P3/2N2Q2/PPPBBPpP/R3K2R b KQkq - 0 1", &zobrist_info);
+        let board = Board::from_fen("r3k2r/pP1pqpb1/bn2pnp1/2pPN3/1p2P3/2N2Q2/PPPBBPpP/R3K2R b KQkq - 0 1", zobrist_info);
 
         for (i, (mv, expected)) in black_moves_arr.iter().enumerate() {
-            check_move((*mv, expected), &board, &attack_info, &zobrist_info, false);
+            check_move((*mv, expected), &board, attack_info, zobrist_info, false);
         }
     }
 
     #[test]
     fn move_to_san_2() {
-        let attack_info = AttackInfo::new();
-        let zobrist_info = ZobristInfo::new();
-        let board = Board::from_fen("k7/8/1K6/8/8/8/2R5/8 w - - 0 1", &zobrist_info);
+        let attack_info = attack::shared_attack_info();
+        let zobrist_info = zobrist::shared_zobrist_info();
+        let board = Board::from_fen("k7/8/1K6/8/8/8/2R5/8 w - - 0 1", zobrist_info);
         let (mv, expected) = (Move::from_str("c2c8", Piece::LR, false, false, false, false), "Rc8#");
-        check_move((mv, expected), &board, &attack_info, &zobrist_info, true);
+        check_move((mv, expected), &board, attack_info, zobrist_info, true);
     }
 
     #[test]
     fn move_to_san_3() {
-        let attack_info = AttackInfo::new();
-        let zobrist_info = ZobristInfo::new();
-        let board = Board::from_fen("8/8/8/8/3b1kbK/8/8/8 b - - 0 1", &zobrist_info);
+        let attack_info = attack::shared_attack_info();
+        let zobrist_info = zobrist::shared_zobrist_info();
+        let board = Board::from_fen("8/8/8/8/3b1kbK/8/8/8 b - - 0 1", zobrist_info);
         let (mv, expected) = (Move::from_str("d4f2", Piece::DB, false, false, false, false), "Bf2#");
-        check_move((mv, expected), &board, &attack_info, &zobrist_info, true);
+        check_move((mv, expected), &board, attack_info, zobrist_info, true);
+    }
+
+    #[test]
+    fn pinned_pieces_are_not_ambiguous() {
+        let attack_info = attack::shared_attack_info();
+        let zobrist_info = zobrist::shared_zobrist_info();
+        // The knight on c3 is pinned, so only the one on g1 can go to e2
+        let fen = "rnbqk1nr/pppp1ppp/8/4p3/1b6/2NP4/PPP2PPP/R1BQKBNR w KQkq - 0 1";
+        let board = Board::from_fen(fen, zobrist_info);
+        let (mv, expected) = (Move::from_str("g1e2", Piece::LN, false, false, false, false), "Ne2");
+        check_move((mv, expected), &board, attack_info, zobrist_info, false);
+
+        for movetext in ["1. Ne2 *", "1. Nge2 *", "1. Ng1e2 *"] {
+            let games = pgn::load(&format!("[FEN \"{}\"]\n\n{}", fen, movetext), attack_info, zobrist_info).unwrap();
+            assert_eq!(games[0].move_at(0).unwrap().to_str().trim(), "g1e2");
+            assert!(write_to_string(&games[0], attack_info, zobrist_info).ends_with("\n1. Ne2 *\n"));
+        }
+        // Both knights can go to d2, so the move has to say which one
+        assert!(pgn::load("1. Nf3 e5 2. d3 e4 3. Nd2 *", attack_info, zobrist_info).is_err());
+        assert!(pgn::load("1. Nf3 e5 2. d3 e4 3. Nbd2 *", attack_info, zobrist_info).is_ok());
+    }
+
+    #[test]
+    fn load_lower_case_promotions() {
+        let attack_info = attack::shared_attack_info();
+        let zobrist_info = zobrist::shared_zobrist_info();
+        let fen = "8/4P3/8/8/8/8/k7/7K w - - 0 1";
+        for movetext in ["1. e8=q *", "1. e8q *", "1. e8=Q *", "1. e8Q *"] {
+            let games = pgn::load(&format!("[FEN \"{}\"]\n\n{}", fen, movetext), attack_info, zobrist_info).unwrap();
+            assert_eq!(games[0].move_at(0).unwrap().to_str().trim(), "e7e8Q");
+        }
+        assert!(pgn::load(&format!("[FEN \"{}\"]\n\n1. e8 *", fen), attack_info, zobrist_info).is_err());
+    }
+
+    #[test]
+    fn pv_in_san() {
+        let attack_info = attack::shared_attack_info();
+        let zobrist_info = zobrist::shared_zobrist_info();
+        let pv = |moves: &str| moves.split_whitespace().map(String::from).collect::<Vec<_>>();
+        let board = Board::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1", zobrist_info);
+        assert_eq!(pgn::pv_to_san(&pv("e7e5 g1f3 b8c6 f1b5"), &board, attack_info, zobrist_info), "e5 Nf3 Nc6 Bb5");
+        // The line stops at an illegal move, even if later moves would be legal
+        assert_eq!(pgn::pv_to_san(&pv("e7e5 e1e2 e2e3"), &board, attack_info, zobrist_info), "e5 Ke2");
+        assert_eq!(pgn::pv_to_san(&pv("e7e5 e4e5 g1f3"), &board, attack_info, zobrist_info), "e5");
+        assert_eq!(pgn::pv_to_san(&pv("e2e4"), &board, attack_info, zobrist_info), "");
+        assert_eq!(pgn::pv_to_san(&[], &board, attack_info, zobrist_info), "");
+
+        let board = Board::from_fen("6k1/5ppp/8/8/8/8/5PPP/R3R1K1 w - - 0 1", zobrist_info);
+        assert_eq!(pgn::pv_to_san(&pv("a1d1 h7h6 e1e8"), &board, attack_info, zobrist_info), "Rad1 h6 Re8+");
     }
 
     #[test]
     fn move_to_san_4() {
-        let attack_info = AttackInfo::new();
-        let zobrist_info = ZobristInfo::new();
-        let board = Board::from_fen("8/8/8/8/8/4k3/7p/4K1R1 b - - 0 1", &zobrist_info);
+        let attack_info = attack::shared_attack_info();
+        let zobrist_info = zobrist::shared_zobrist_info();
+        let board = Board::from_fen("8/8/8/8/8/4k3/7p/4K1R1 b - - 0 1", zobrist_info);
         let (mv, expected) = (Move::from_str("h2g1q", Piece::DP, true, false, false, false), "hxg1=Q#");
-        check_move((mv, expected), &board, &attack_info, &zobrist_info, true);
+        check_move((mv, expected), &board, attack_info, zobrist_info, true);
+    }
+
+    #[test]
+    fn move_to_san_960_castling() {
+        let attack_info = attack::shared_attack_info();
+        let zobrist_info = zobrist::shared_zobrist_info();
+        // King on f1 castling with the h1 rook
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/5K1R w - - 0 1", zobrist_info);
+        let (mv, expected) = (Move::from_str("f1g1", Piece::LK, false, false, false, true), "O-O");
+        check_move((mv, expected), &board, attack_info, zobrist_info, false);
+
+        // King on b1 castling with the a1 rook: the king moves towards the h-file, but it's
+        // still queenside castling
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/RK6 w - - 0 1", zobrist_info);
+        let (mv, expected) = (Move::from_str("b1c1", Piece::LK, false, false, false, true), "O-O-O");
+        check_move((mv, expected), &board, attack_info, zobrist_info, false);
+
+        // Castling encoded as the king capturing its own rook
+        let mv = Move::from_str("b8a8", Piece::DK, false, false, false, true);
+        assert_eq!(pgn::coord_move_to_san(mv, attack_info, false, (false, false), false), "O-O-O");
+        let mv = Move::from_str("d8h8", Piece::DK, false, false, false, true);
+        assert_eq!(pgn::coord_move_to_san(mv, attack_info, false, (false, false), false), "O-O");
     }
 
     fn check_move(
@@ -311,13 +678,122 @@ mod tests {
         zobrist_info: &ZobristInfo, checkmate: bool
     ) {
         let mut clone_board = board.clone();
-        let legal_move = moves::make(&mut clone_board, &attack_info, &zobrist_info, mv, MoveFlag::AllMoves);
+        let legal_move = moves::make(&mut clone_board, attack_info, zobrist_info, mv, MoveFlag::AllMoves);
         assert_eq!(legal_move, true);
 
-        let disambiguate = pgn::should_disambiguate(mv, &attack_info, &board);
-        let check = clone_board.is_in_check(&attack_info, clone_board.state.xside);
-        let generated = pgn::coord_move_to_san(mv, &attack_info, check, disambiguate, checkmate);
+        let disambiguate = pgn::should_disambiguate(mv, attack_info, zobrist_info, &board);
+        let check = clone_board.is_in_check(attack_info, clone_board.state.xside);
+        let generated = pgn::coord_move_to_san(mv, attack_info, check, disambiguate, checkmate);
         assert_eq!(&generated, expected);
     }
-}
 
+    #[test]
+    fn load_annotated_game() {
+        let attack_info = attack::shared_attack_info();
+        let zobrist_info = zobrist::shared_zobrist_info();
+        let games = pgn::load(ANNOTATED_PGN, attack_info, zobrist_info).unwrap();
+        assert_eq!(games.len(), 1);
+        let game = &games[0];
+        assert_eq!(game.white_name(), "Alice \"A\" Smith");
+        assert_eq!(game.black_name(), "Bob");
+        assert_eq!(game.move_count(), 24);
+        assert_eq!(game.move_at(6).unwrap().to_str().trim(), "e1g1");
+        assert_eq!(game.last_move().unwrap().to_str().trim(), "d6d5");
+        assert_eq!(game.loaded_result().unwrap(), "0-1");
+
+        // Writing the game back keeps the moves and the result
+        let mut written = vec![];
+        pgn::write_game(&mut written, game, attack_info, zobrist_info).unwrap();
+        let written = String::from_utf8(written).unwrap();
+        assert!(written.contains("[Result \"0-1\"]"));
+        let reloaded = pgn::load(&written, attack_info, zobrist_info).unwrap();
+        assert_eq!(reloaded[0].current_fen(), game.current_fen());
+        assert_eq!(reloaded[0].loaded_result().unwrap(), "0-1");
+    }
+
+    #[test]
+    fn load_multiple_games() {
+        let attack_info = attack::shared_attack_info();
+        let zobrist_info = zobrist::shared_zobrist_info();
+        let games = pgn::load(MATED_PGN, attack_info, zobrist_info).unwrap();
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].state(), GameState::DarkWinByCheckmate);
+        assert_eq!(games[0].loaded_result(), None);
+        assert!(games[0].gave_check(3) && games[0].gave_checkmate(3));
+        assert!(!games[0].gave_check(2) && !games[0].gave_checkmate(2));
+        let mut written = vec![];
+        pgn::write_game(&mut written, &games[0], attack_info, zobrist_info).unwrap();
+        assert!(String::from_utf8(written).unwrap().contains("2. g4 Qh4#"));
+        assert_eq!(games[1].move_count(), 1);
+
+        assert!(pgn::load("1. e4 {unterminated", attack_info, zobrist_info).is_err());
+        assert!(pgn::load("1. e4 e5 2. Ke3", attack_info, zobrist_info).is_err());
+    }
+
+    #[test]
+    fn load_skips_variations() {
+        let attack_info = attack::shared_attack_info();
+        let zobrist_info = zobrist::shared_zobrist_info();
+        let games = pgn::load(VARIATIONS_PGN, attack_info, zobrist_info).unwrap();
+        assert_eq!(games.len(), 1);
+        let main_line: Vec<String> = (0..games[0].move_count())
+            .map(|i| games[0].move_at(i).unwrap().to_str().trim().to_string())
+            .collect();
+        assert_eq!(main_line, ["e2e4", "e7e5", "g1f3", "b8c6", "f1b5", "a7a6"]);
+
+        assert!(pgn::load("1. e4 (1. d4 d5 *", attack_info, zobrist_info).is_err());
+        assert!(pgn::load("1. e4 e5) *", attack_info, zobrist_info).is_err());
+    }
+
+    fn write_to_string(game: &Game, attack_info: &AttackInfo, zobrist_info: &ZobristInfo) -> String {
+        let mut written = vec![];
+        pgn::write_game(&mut written, game, attack_info, zobrist_info).unwrap();
+        String::from_utf8(written).unwrap()
+    }
+
+    #[test]
+    fn save_ongoing_game() {
+        let attack_info = attack::shared_attack_info();
+        let zobrist_info = zobrist::shared_zobrist_info();
+        let mut games = pgn::load("1. e4 e5 2. Nf3 *", attack_info, zobrist_info).unwrap();
+        let written = write_to_string(&games[0], attack_info, zobrist_info);
+        assert!(written.contains("[Result \"*\"]"));
+        assert!(written.ends_with("\n1. e4 e5 2. Nf3 *\n"));
+        let reloaded = pgn::load(&written, attack_info, zobrist_info).unwrap();
+        assert_eq!(reloaded.len(), 1);
+        assert_eq!(reloaded[0].move_count(), 3);
+        assert!(reloaded[0].is_ongoing());
+        assert_eq!(reloaded[0].loaded_result(), None);
+
+        // A game that was stopped by a forfeit is a loss, not a draw
+        games[0].lost_on_time(false);
+        let written = write_to_string(&games[0], attack_info, zobrist_info);
+        assert!(written.contains("[Result \"1-0\"]") && written.ends_with("2. Nf3 1-0\n"));
+
+        // Black moves first, so the numbering starts with '12...'
+        let fen = "4k3/4p3/8/8/8/8/8/4K3 b - - 0 12";
+        let games = pgn::load(&format!("[FEN \"{}\"]\n\n12... e5 13. Kd2 *", fen), attack_info, zobrist_info).unwrap();
+        let written = write_to_string(&games[0], attack_info, zobrist_info);
+        assert!(written.ends_with("\n12... e5 13. Kd2 *\n"));
+        assert_eq!(pgn::load(&written, attack_info, zobrist_info).unwrap()[0].current_fen(), games[0].current_fen());
+    }
+
+    #[test]
+    fn save_empty_game() {
+        let attack_info = attack::shared_attack_info();
+        let zobrist_info = zobrist::shared_zobrist_info();
+        let path = std::env::temp_dir().join(format!("empty-game-{}.pgn", std::process::id()));
+        let path = path.to_str().unwrap();
+        let game = Game::new("Engine A", "Engine B", attack_info, zobrist_info);
+        assert!(pgn::save(path, &game, attack_info, zobrist_info).unwrap());
+        let written = std::fs::read_to_string(path).unwrap();
+        let _ = std::fs::remove_file(path);
+        assert!(written.contains("[Result \"*\"]"));
+        assert!(written.ends_with("\n\n*\n"));
+        let reloaded = pgn::load(&written, attack_info, zobrist_info).unwrap();
+        assert_eq!(reloaded.len(), 1);
+        assert_eq!(reloaded[0].move_count(), 0);
+        assert_eq!(reloaded[0].white_name(), "Engine A");
+        assert!(reloaded[0].is_ongoing());
+    }
+}
@@ -0,0 +1,475 @@
+use raylib::prelude::*;
+
+use chess::bb::BBUtil;
+use chess::board::Board;
+use chess::consts::{Piece, Sq};
+use chess::moves::{Move, MoveUtil};
+use chess::{COL, ROW, SQ};
+
+use crate::game::GameState;
+
+use std::borrow::Cow;
+
+// The colors are named after the squares they are drawn on: the 'light' squares are the ones
+// where the rank plus the file is odd (b8, a7, ...), and get the darker color.
+#[derive(Clone, Debug)]
+pub struct Theme {
+    pub name: Cow<'static, str>,
+    pub light_sq: Color,
+    pub dark_sq: Color,
+    // Tints of the last move's squares, its target standing out more than its source
+    pub light_source: Color,
+    pub dark_source: Color,
+    pub light_target: Color,
+    pub dark_target: Color,
+    // Tint of the square a human player has selected, which is unlike the last move's
+    pub light_selected: Color,
+    pub dark_selected: Color,
+}
+
+impl Theme {
+    pub const GREEN: Theme = Theme {
+        name: Cow::Borrowed("green"),
+        light_sq: Color::new(118, 150, 86, 255),
+        dark_sq: Color::new(238, 238, 210, 255),
+        light_source: Color::new(160, 181, 76, 255),
+        dark_source: Color::new(242, 243, 168, 255),
+        light_target: Color::new(187, 204, 68, 255),
+        dark_target: Color::new(244, 246, 128, 255),
+        light_selected: Color::new(104, 158, 148, 255),
+        dark_selected: Color::new(190, 222, 210, 255),
+    };
+    pub const BROWN: Theme = Theme::from_squares(
+        Cow::Borrowed("brown"), Color::new(181, 136, 99, 255), Color::new(240, 217, 181, 255)
+    );
+    pub const BLUE: Theme = Theme::from_squares(
+        Cow::Borrowed("blue"), Color::new(140, 162, 173, 255), Color::new(222, 227, 230, 255)
+    );
+    pub const GREY: Theme = Theme::from_squares(
+        Cow::Borrowed("grey"), Color::new(134, 134, 134, 255), Color::new(220, 220, 220, 255)
+    );
+
+    pub const BUILT_IN: [Theme; 4] = [Theme::GREEN, Theme::BROWN, Theme::BLUE, Theme::GREY];
+
+    // The highlights are worked out from the colors of the squares
+    const fn from_squares(name: Cow<'static, str>, light_sq: Color, dark_sq: Color) -> Theme {
+        const MOVE: Color = Color::new(235, 235, 40, 255);
+        const SELECTED: Color = Color::new(40, 170, 200, 255);
+        Theme {
+            name,
+            light_sq,
+            dark_sq,
+            light_source: blend(light_sq, MOVE, 35),
+            dark_source: blend(dark_sq, MOVE, 35),
+            light_target: blend(light_sq, MOVE, 55),
+            dark_target: blend(dark_sq, MOVE, 55),
+            light_selected: blend(light_sq, SELECTED, 35),
+            dark_selected: blend(dark_sq, SELECTED, 35),
+        }
+    }
+
+    // Reads a theme defined in the config, e.g. "ocean #8ca2ad #dee3e6": its name and then the
+    // colors of the dark and light squares
+    pub fn parse(spec: &str) -> Result<Theme, String> {
+        let parts: Vec<&str> = spec.split_whitespace().collect();
+        if parts.len() != 3 {
+            return Err(format!("Expected a name and two colors, got '{}'", spec));
+        }
+        let color = |text: &str| parse_hex_color(text).ok_or(format!("Invalid color '{}'", text));
+        Ok(Theme::from_squares(Cow::Owned(parts[0].to_string()), color(parts[1])?, color(parts[2])?))
+    }
+}
+
+// Mixes 'percent' percent of 'with' into 'color'
+const fn blend(color: Color, with: Color, percent: u16) -> Color {
+    const fn mix(a: u8, b: u8, percent: u16) -> u8 {
+        ((a as u16 * (100 - percent) + b as u16 * percent) / 100) as u8
+    }
+    Color::new(mix(color.r, with.r, percent), mix(color.g, with.g, percent), mix(color.b, with.b, percent), 255)
+}
+
+// Color written as "#rrggbb"
+fn parse_hex_color(text: &str) -> Option<Color> {
+    let hex = text.strip_prefix('#')?;
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some(Color::new(channel(0)?, channel(2)?, channel(4)?, 255))
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::GREEN
+    }
+}
+
+// Where the files and ranks are labeled
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CoordStyle {
+    // In the corners of the squares along the bottom and left edges
+    Inside,
+    // Next to the board, along the bottom and left edges
+    Outside,
+    // Next to the board, along all four edges
+    AllEdges,
+}
+
+impl CoordStyle {
+    const NAMES: [(CoordStyle, &'static str); 3] = [
+        (CoordStyle::Inside, "inside"),
+        (CoordStyle::Outside, "outside"),
+        (CoordStyle::AllEdges, "all"),
+    ];
+
+    pub fn from_name(name: &str) -> Option<CoordStyle> {
+        Self::NAMES.iter().find(|(_, n)| *n == name).map(|(style, _)| *style)
+    }
+
+    pub fn name(self) -> &'static str {
+        Self::NAMES.iter().find(|(style, _)| *style == self).unwrap().1
+    }
+
+    // Space left next to each labeled edge of a board that takes up 'side' pixels along with it
+    pub fn gutter(self, side: f32) -> f32 {
+        if self == CoordStyle::Inside { 0.0 } else { side * 0.05 }
+    }
+}
+
+impl Default for CoordStyle {
+    fn default() -> Self {
+        CoordStyle::Inside
+    }
+}
+
+// Converts a square on the board to the square it is displayed on. When the board is flipped,
+// the board is rotated by 180 degrees, which maps square 'sq' to square '63 - sq'.
+pub fn display_sq(sq: usize, flip: bool) -> usize {
+    if flip { 63 - sq } else { sq }
+}
+
+// TODO: display checks
+pub fn draw_board(
+    d: &mut RaylibDrawHandle, theme: &Theme, sec: &Rectangle, flip: bool,
+    source: Option<Sq>, target: Option<Sq>, selected: Option<Sq>
+) {
+    let mut cell_size = Vector2::one();
+    cell_size.scale(sec.width / 8.0);
+
+    for r in 0..8 {
+        for f in 0..8 {
+            let light_sq = (r + f) % 2 != 0;
+            let mut sq_clr = if light_sq { theme.light_sq } else { theme.dark_sq };
+            let board_sq = display_sq(SQ!(r, f), flip);
+            let is_sq = |sq: Option<Sq>| sq.map_or(false, |sq| sq as usize == board_sq);
+            if is_sq(selected) {
+                sq_clr = if light_sq { theme.light_selected } else { theme.dark_selected };
+            } else if is_sq(target) {
+                sq_clr = if light_sq { theme.light_target } else { theme.dark_target };
+            } else if is_sq(source) {
+                sq_clr = if light_sq { theme.light_source } else { theme.dark_source };
+            }
+            /*
+            if let Some(sq) = b_ui.check {
+                let sq = sq as usize;
+                if sq == SQ!(r, f) {
+                    let check_clr = Color::new(189, 55, 55, 255);
+                    sq_clr = Color::color_alpha_blend(&sq_clr, &check_clr, &Color::new(255, 255, 255, 200));
+                }
+            }
+            */
+
+            d.draw_rectangle_v(
+                Vector2::new(
+                    sec.x + (f as f32) * cell_size.x,
+                    sec.y + (r as f32) * cell_size.y
+                ),
+                cell_size,
+                sq_clr
+            );
+        }
+    }
+}
+
+// Files are labeled along the bottom edge and ranks along the left edge, from the viewer's
+// perspective, and along the other two edges as well with 'CoordStyle::AllEdges'
+pub fn draw_coords(d: &mut RaylibDrawHandle, font: &Font, theme: &Theme, sec: &Rectangle, flip: bool, style: CoordStyle) {
+    if style != CoordStyle::Inside {
+        draw_outside_coords(d, font, theme, sec, flip, style == CoordStyle::AllEdges);
+        return;
+    }
+    // File markings
+    let sq_size = sec.width / 8.0;
+    for f in 0..8 {
+        // row(r) = 7
+        let text_color = if (7+f) % 2 != 0 { theme.dark_sq } else { theme.light_sq };
+        let file = COL!(display_sq(SQ!(7, f as usize), flip)) as u8;
+        d.draw_text_ex(
+            font,
+            &format!("{}", (b'a' + file) as char),
+            Vector2::new(
+                sec.x + f as f32 * sq_size + (sq_size * 0.83),
+                sec.y + 0.965*sec.height
+            ),
+            font.baseSize as f32 * 0.5,
+            0.0,
+            text_color
+        );
+    }
+    // Row markings
+    for r in 0..8 {
+        // file(f) = 0
+        let text_color = if (r+0) % 2 != 0 { theme.dark_sq } else { theme.light_sq };
+        let row = ROW!(display_sq(SQ!(r, 0), flip));
+        d.draw_text_ex(
+            font,
+            &format!("{}", 8-row),
+            Vector2::new(
+                sec.x + 0.01*sec.width,
+                sec.y + r as f32 * sq_size + (0.01 * sec.height),
+            ),
+            font.baseSize as f32 * 0.5,
+            0.0,
+            text_color
+        );
+    }
+}
+
+// The labels are centered on their file or rank, in the space the GUI leaves around the board
+fn draw_outside_coords(d: &mut RaylibDrawHandle, font: &Font, theme: &Theme, sec: &Rectangle, flip: bool, all_edges: bool) {
+    let sq_size = sec.width / 8.0;
+    let font_size = sq_size * 0.28;
+    let padding = sq_size * 0.1;
+    for i in 0..8 {
+        let file = (b'a' + COL!(display_sq(SQ!(7, i), flip)) as u8) as char;
+        let text = file.to_string();
+        let text_dim = text::measure_text_ex(font, &text, font_size, 0.0);
+        let x = sec.x + (i as f32 + 0.5) * sq_size - text_dim.x / 2.0;
+        let bottom = Vector2::new(x, sec.y + sec.height + padding);
+        d.draw_text_ex(font, &text, bottom, font_size, 0.0, theme.dark_sq);
+        if all_edges {
+            let top = Vector2::new(x, sec.y - padding - text_dim.y);
+            d.draw_text_ex(font, &text, top, font_size, 0.0, theme.dark_sq);
+        }
+
+        let text = (8 - ROW!(display_sq(SQ!(i, 0), flip))).to_string();
+        let text_dim = text::measure_text_ex(font, &text, font_size, 0.0);
+        let y = sec.y + (i as f32 + 0.5) * sq_size - text_dim.y / 2.0;
+        let left = Vector2::new(sec.x - padding - text_dim.x, y);
+        d.draw_text_ex(font, &text, left, font_size, 0.0, theme.dark_sq);
+        if all_edges {
+            let right = Vector2::new(sec.x + sec.width + padding, y);
+            d.draw_text_ex(font, &text, right, font_size, 0.0, theme.dark_sq);
+        }
+    }
+}
+
+// Debug overlay with the index of every square (0 for a8 to 63 for h1, like 'SQ!'), drawn in
+// the top right corner of the square so that it doesn't cover the coordinates
+pub fn draw_sq_indices(d: &mut RaylibDrawHandle, font: &Font, sec: &Rectangle, flip: bool) {
+    let sq_size = sec.width / 8.0;
+    let font_size = sq_size * 0.25;
+    let padding = sq_size * 0.05;
+    for sq in 0..64 {
+        let rect = piece_rect_on_board(sec, sq, flip);
+        let text = sq.to_string();
+        let text_dim = text::measure_text_ex(font, &text, font_size, 0.0);
+        let pos = Vector2::new(rect.x + rect.width - padding - text_dim.x, rect.y + padding);
+        d.draw_text_ex(font, &text, pos, font_size, 0.0, Color::RED);
+    }
+}
+
+// Frame drawn just outside of a panel, so that it doesn't cover any of its content. It gets a
+// little thicker on bigger windows so it still stands out when the window is maximized.
+pub fn draw_border(d: &mut RaylibDrawHandle, theme: &Theme, sec: &Rectangle, window_height: f32) {
+    let thickness = (window_height * 0.003).round().max(2.0);
+    let frame = Rectangle::new(
+        sec.x - thickness, sec.y - thickness, sec.width + 2.0*thickness, sec.height + 2.0*thickness
+    );
+    d.draw_rectangle_lines_ex(frame, thickness as i32, theme.dark_sq);
+}
+
+pub fn draw_piece(d: &mut RaylibDrawHandle, tex: &Texture2D, target: Rectangle, piece: Piece) {
+    let (color, kind) = Piece::to_tuple(Some(piece));
+    let source_rect = Rectangle::new(
+        (kind as i32 * tex.width() / 6) as f32,
+        (color as i32 * tex.height() / 2) as f32,
+        (tex.width() / 6) as f32,
+        (tex.height() / 2) as f32,
+    );
+    d.draw_texture_pro(
+        &tex,
+        source_rect,
+        target,
+        Vector2::zero(),
+        0.0,
+        Color::WHITE,
+    );
+}
+
+pub fn piece_rect_on_board(sec: &Rectangle, sq: usize, flip: bool) -> Rectangle {
+    let min_side = f32::min(sec.width, sec.height);
+    let mut cell_size = Vector2::one();
+    cell_size.scale(min_side / 8.0);
+
+    let sq = display_sq(sq, flip);
+    let r = ROW!(sq);
+    let f = COL!(sq);
+    Rectangle::new(
+        sec.x + (f as f32) * cell_size.x,
+        sec.y + (r as f32) * cell_size.y,
+        cell_size.x,
+        cell_size.y
+    )
+}
+
+pub fn draw_pieces(
+    d: &mut RaylibDrawHandle, skip_sq: Option<Sq>, tex: &Texture2D, board: &Board,
+    sec: &Rectangle, flip: bool
+) {
+    for r in 0..8 {
+        for f in 0..8 {
+            let sq = SQ!(r, f);
+            if let Some(s_sq) = skip_sq {
+                if s_sq as usize == sq {
+                    continue;
+                }
+            }
+            if let Some(piece) = board.find_piece(sq) {
+                draw_piece(d, tex, piece_rect_on_board(sec, sq, flip), piece);
+            }
+        }
+    }
+}
+
+pub fn anim_piece(d: &mut RaylibDrawHandle, boundary: &Rectangle, tex: &Texture2D, mv: Move, t: f32, flip: bool) {
+    let source_rect = piece_rect_on_board(boundary, mv.source() as usize, flip);
+    let target_rect = piece_rect_on_board(boundary, mv.target() as usize, flip);
+    let piece = mv.piece();
+    let source_vec = Vector2::new(source_rect.x, source_rect.y);
+    let target_vec = Vector2::new(target_rect.x, target_rect.y);
+    let anim_pos = source_vec.lerp(target_vec, t as f32);
+    let anim_rect = Rectangle::new(anim_pos.x, anim_pos.y, source_rect.width, source_rect.height);
+    draw_piece(d, tex, anim_rect, piece);
+}
+
+// Icons of the light and dark kings for how the game ended, as indices into the texture of
+// game end icons: the winner's crown (0), checkmate (1), and a light and a dark version of the
+// draw (2, 3), resignation (4, 5) and timeout (6, 7) icons
+fn marker_icons(game_state: GameState) -> Option<(usize, usize)> {
+    match game_state {
+        GameState::Ongoing => None,
+        GameState::LightWinByCheckmate => Some((0, 1)),
+        GameState::DarkWinByCheckmate => Some((1, 0)),
+        GameState::LightLostOnTime => Some((6, 0)),
+        GameState::DarkLostOnTime => Some((0, 7)),
+        // An engine that sends an illegal move or no move at all (e.g. because it crashed)
+        // forfeits the game, which is shown like a resignation. So is a game that was called
+        // because the loser's position was hopeless.
+        GameState::LightIllegalMove | GameState::LightNoResponse | GameState::DarkWinByAdjudication => Some((4, 0)),
+        GameState::DarkIllegalMove | GameState::DarkNoResponse | GameState::LightWinByAdjudication => Some((0, 5)),
+        GameState::DrawByStalemate | GameState::DrawByFiftyMoveRule | GameState::DrawByThreefoldRepetition
+            | GameState::DrawByInsufficientMaterial | GameState::DrawByAdjudication => Some((2, 3)),
+    }
+}
+
+// Where the marker of the king on 'king_sq' goes: in the top right corner of the square it's
+// displayed on, so that it stays on the board even for kings on the edges
+fn marker_rect(sec: &Rectangle, king_sq: usize, flip: bool) -> Rectangle {
+    let sq_rect = piece_rect_on_board(sec, king_sq, flip);
+    let side = sq_rect.width * 0.4;
+    Rectangle::new(sq_rect.x + sq_rect.width - side, sq_rect.y, side, side)
+}
+
+// When the icons couldn't be loaded, each king is marked with a circle colored by its result
+pub fn draw_markers(
+    d: &mut RaylibDrawHandle, board: &Board, tex: Option<&Texture2D>, sec: &Rectangle,
+    game_state: GameState, flip: bool
+) {
+    let (l_ind, d_ind) = if let Some(icons) = marker_icons(game_state) { icons } else { return; };
+    // A position that was set up without one of the kings has nothing to mark for it
+    let light_king = board.pos.piece[Piece::LK as usize].squares().next();
+    let dark_king = board.pos.piece[Piece::DK as usize].squares().next();
+    for (king, ind) in [(light_king, l_ind), (dark_king, d_ind)] {
+        let king = if let Some(king) = king { king } else { continue; };
+        let target_rect = marker_rect(sec, king, flip);
+        let tex = if let Some(tex) = tex { tex } else {
+            // Icon 0 is the winner's and 2 and 3 are the draw ones
+            let color = match ind {
+                0 => Color::new(97, 153, 59, 255),
+                2 | 3 => Color::GRAY,
+                _ => Color::new(202, 52, 49, 255),
+            };
+            let center = Vector2::new(target_rect.x + target_rect.width / 2.0, target_rect.y + target_rect.height / 2.0);
+            d.draw_circle_v(center, target_rect.width / 2.0, color);
+            continue;
+        };
+        // This texture has 8 icons in it so each 'frame' has a width of 1/8 of the total width
+        let frame_width = tex.width() as f32 / 8.0;
+        let source_rect = Rectangle {
+            x: ind as f32 * frame_width,
+            y: 0.0,
+            width: frame_width,
+            height: tex.height() as f32
+        };
+        d.draw_texture_pro(
+            tex,
+            source_rect,
+            target_rect,
+            Vector2::zero(),
+            0.0,
+            Color::WHITE,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chess::consts::Sq;
+
+    use crate::game::GameState;
+    use crate::render::{marker_icons, marker_rect, Theme};
+
+    use raylib::prelude::Rectangle;
+
+    #[test]
+    fn parse_custom_themes() {
+        let theme = Theme::parse("ocean  #8ca2ad #DEE3E6").unwrap();
+        assert_eq!(theme.name, "ocean");
+        let (light, dark) = (theme.light_sq, theme.dark_sq);
+        assert_eq!((light.r, light.g, light.b, light.a), (0x8c, 0xa2, 0xad, 255));
+        assert_eq!((dark.r, dark.g, dark.b, dark.a), (0xde, 0xe3, 0xe6, 255));
+        for spec in ["ocean #8ca2ad", "ocean 8ca2ad #dee3e6", "ocean #8ca2ad #dee3zz", "ocean #8ca2ad #dee3e6 x", "ocean #8ca #dee3e6"] {
+            assert!(Theme::parse(spec).is_err(), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn markers_follow_the_flipped_board() {
+        let sec = Rectangle::new(10.0, 20.0, 800.0, 800.0);
+        // h1 is at the bottom right, or at the top left when flipped
+        let rect = marker_rect(&sec, Sq::H1 as usize, false);
+        assert_eq!((rect.x, rect.y, rect.width), (770.0, 720.0, 40.0));
+        let rect = marker_rect(&sec, Sq::H1 as usize, true);
+        assert_eq!((rect.x, rect.y), (70.0, 20.0));
+        // Markers of kings on the edges stay on the board
+        for sq in [Sq::A8, Sq::H8, Sq::H1] {
+            for flip in [false, true] {
+                let rect = marker_rect(&sec, sq as usize, flip);
+                assert!(rect.x >= sec.x && rect.x + rect.width <= sec.x + sec.width);
+                assert!(rect.y >= sec.y && rect.y + rect.height <= sec.y + sec.height);
+            }
+        }
+    }
+
+    #[test]
+    fn marker_icons_of_finished_games() {
+        assert_eq!(marker_icons(GameState::Ongoing), None);
+        assert_eq!(marker_icons(GameState::DarkWinByCheckmate), Some((1, 0)));
+        assert_eq!(marker_icons(GameState::DarkLostOnTime), Some((0, 7)));
+        assert_eq!(marker_icons(GameState::LightNoResponse), Some((4, 0)));
+        for state in GameState::DRAWS {
+            assert_eq!(marker_icons(state), Some((2, 3)));
+        }
+    }
+}
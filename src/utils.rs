@@ -75,3 +75,175 @@ impl<T: Clone> Button<T> {
         return false;
     }
 }
+
+// A line of text typed in by the user, e.g. a FEN. Keys only go to the input while it has focus,
+// which it gets when clicked and loses when something else is clicked, or on Enter or Escape.
+pub struct TextInput {
+    rect: Rectangle,
+    value: String,
+    // Shown in place of the text while there's none
+    placeholder: String,
+    max_len: usize,
+    focused: bool,
+    // Time since the caret was last shown, which restarts on every edit
+    blink_time: f32,
+    // How long backspace has been held down, to keep deleting while it is
+    backspace_held: f32,
+}
+
+impl TextInput {
+    const BLINK_PERIOD: f32 = 1.0;
+    const REPEAT_DELAY: f32 = 0.4;
+    const REPEAT_INTERVAL: f32 = 0.04;
+
+    pub fn new(placeholder: &str, max_len: usize) -> Self {
+        Self {
+            rect: Rectangle::default(),
+            value: String::new(),
+            placeholder: placeholder.to_string(),
+            max_len,
+            focused: false,
+            blink_time: 0.0,
+            backspace_held: 0.0,
+        }
+    }
+
+    pub fn set_rect(&mut self, rect: Rectangle) {
+        self.rect = rect;
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    pub fn set_value(&mut self, value: &str) {
+        self.value = value.chars().take(self.max_len).collect();
+        self.blink_time = 0.0;
+    }
+
+    pub fn is_focused(&self) -> bool {
+        self.focused
+    }
+
+    pub fn focus(&mut self) {
+        self.focused = true;
+        self.blink_time = 0.0;
+    }
+
+    pub fn unfocus(&mut self) {
+        self.focused = false;
+    }
+
+    // Takes the keys typed since the last frame. When Enter is pressed, 'on_submit' is called
+    // with the text and the input loses focus.
+    pub fn update(&mut self, rl: &mut RaylibHandle, on_submit: impl FnOnce(&str)) {
+        if rl.is_mouse_button_pressed(MouseButton::MOUSE_LEFT_BUTTON) {
+            let clicked = self.rect.check_collision_point_rec(rl.get_mouse_position());
+            if clicked { self.focus(); } else { self.unfocus(); }
+        }
+        if !self.focused {
+            return;
+        }
+        let frame_time = rl.get_frame_time();
+        self.blink_time += frame_time;
+        while let Some(ch) = rl.get_char_pressed() {
+            self.insert(ch);
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_BACKSPACE) {
+            self.backspace_held = 0.0;
+            self.backspace();
+        } else if rl.is_key_down(KeyboardKey::KEY_BACKSPACE) {
+            // Deletes a character every interval once the key has been held for the delay
+            let before = self.backspace_held;
+            self.backspace_held += frame_time;
+            let repeats = |held: f32| ((held - Self::REPEAT_DELAY) / Self::REPEAT_INTERVAL).floor().max(-1.0);
+            for _ in 0..(repeats(self.backspace_held) - repeats(before)) as usize {
+                self.backspace();
+            }
+        }
+        if rl.is_key_pressed(KeyboardKey::KEY_ESCAPE) {
+            self.unfocus();
+        } else if rl.is_key_pressed(KeyboardKey::KEY_ENTER) || rl.is_key_pressed(KeyboardKey::KEY_KP_ENTER) {
+            self.unfocus();
+            on_submit(&self.value);
+        }
+    }
+
+    fn insert(&mut self, ch: char) {
+        if !ch.is_control() && self.value.chars().count() < self.max_len {
+            self.value.push(ch);
+            self.blink_time = 0.0;
+        }
+    }
+
+    fn backspace(&mut self) {
+        if self.value.pop().is_some() {
+            self.blink_time = 0.0;
+        }
+    }
+
+    // The caret is shown for the first half of every period
+    fn caret_visible(&self) -> bool {
+        self.focused && self.blink_time % Self::BLINK_PERIOD < Self::BLINK_PERIOD / 2.0
+    }
+
+    // When the text is too long for the box, its end is shown, since that's where the caret is.
+    // 'accent' marks the focus and the caret, e.g. a color of the board theme.
+    pub fn draw(&self, d: &mut RaylibDrawHandle, font: &Font, accent: Color) {
+        let border = if self.focused { accent } else { Color::GRAY };
+        d.draw_rectangle_rec(self.rect, Color::new(30, 30, 30, 240));
+        d.draw_rectangle_lines_ex(self.rect, 2, border);
+
+        let font_size = f32::min(font.baseSize as f32 * 0.7, self.rect.height * 0.6);
+        let padding = (self.rect.height - font_size) / 2.0;
+        let max_width = self.rect.width - 2.0 * padding;
+        let (text, color) = if self.value.is_empty() {
+            (self.placeholder.as_str(), Color::GRAY)
+        } else {
+            let mut start = 0;
+            while text::measure_text_ex(font, &self.value[start..], font_size, 0.0).x > max_width {
+                start += self.value[start..].chars().next().map_or(1, char::len_utf8);
+            }
+            (&self.value[start..], Color::RAYWHITE)
+        };
+        let pos = Vector2::new(self.rect.x + padding, self.rect.y + padding);
+        d.draw_text_ex(font, text, pos, font_size, 0.0, color);
+        if self.caret_visible() {
+            let text_width = if self.value.is_empty() { 0.0 } else { text::measure_text_ex(font, text, font_size, 0.0).x };
+            let caret = Rectangle::new(pos.x + text_width + 1.0, pos.y, 2.0, font_size);
+            d.draw_rectangle_rec(caret, accent);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::TextInput;
+
+    #[test]
+    fn edit_text_input() {
+        let mut input = TextInput::new("Type a FEN", 8);
+        assert_eq!(input.value(), "");
+        assert!(!input.caret_visible());
+        input.focus();
+        assert!(input.caret_visible());
+        for ch in "8/8 w\n -\t - -".chars() {
+            input.insert(ch);
+        }
+        // Control characters are dropped and the text stops at its maximum length
+        assert_eq!(input.value(), "8/8 w - ");
+        input.backspace();
+        input.backspace();
+        assert_eq!(input.value(), "8/8 w ");
+        input.blink_time = TextInput::BLINK_PERIOD * 0.75;
+        assert!(!input.caret_visible());
+        input.insert('é');
+        assert!(input.caret_visible());
+        input.set_value("rnbqkbnr/pppppppp");
+        assert_eq!(input.value(), "rnbqkbnr");
+        for _ in 0..10 {
+            input.backspace();
+        }
+        assert_eq!(input.value(), "");
+    }
+}